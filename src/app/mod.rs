@@ -5,7 +5,14 @@ mod modal;
 mod model;
 mod view;
 mod viewmodel;
+#[cfg(target_arch = "wasm32")]
+mod worker;
 
+use image::DynamicImage;
+use model::operation::Operation;
+use model::parameter::Parameters;
+use model::task::Progress;
+use model::viewport::ViewLink;
 use model::ImageService;
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown. (currently not used)
@@ -25,27 +32,76 @@ impl Default for MyApp {
 
         let model_current = image_service.get_current_image();
         let model_preview = image_service.get_preview_image();
+        let model_active_operation = image_service.get_active_operation();
+        let model_log = image_service.get_log();
+        let model_task = image_service.get_task();
+        let view_link = Arc::new(ViewLink::default());
 
         let views: Vec<Box<dyn view::View>> = vec![
-            Box::new(view::TopPanel::new(viewmodel::TopPanel::new(
-                Arc::clone(&image_service),
-                Arc::clone(&model_current),
-                Arc::clone(&model_preview),
-            ))),
+            Box::new(view::TopPanel::new(
+                viewmodel::TopPanel::new(
+                    Arc::clone(&image_service),
+                    Arc::clone(&model_current),
+                    Arc::clone(&model_preview),
+                ),
+                view::ExportWindow::new(viewmodel::ExportWindow::new(
+                    Arc::clone(&image_service),
+                    Arc::clone(&model_current),
+                )),
+                view::CompareWindow::new(viewmodel::CompareWindow::new(
+                    Arc::clone(&model_current),
+                    Arc::clone(&model_preview),
+                )),
+                view::HistogramWindow::new(viewmodel::HistogramWindow::new(
+                    Arc::clone(&model_current),
+                    Arc::clone(&model_preview),
+                    image_service.get_roi(),
+                )),
+                view::ChannelsWindow::new(viewmodel::ChannelsWindow::new(
+                    Arc::clone(&image_service),
+                    Arc::clone(&model_current),
+                    Arc::clone(&view_link),
+                )),
+                view::ComponentsWindow::new(viewmodel::ComponentsWindow::new(
+                    Arc::clone(&image_service),
+                    Arc::clone(&model_current),
+                    Arc::clone(&view_link),
+                )),
+                view::ContoursWindow::new(viewmodel::ContoursWindow::new(
+                    Arc::clone(&image_service),
+                    Arc::clone(&model_current),
+                    Arc::clone(&view_link),
+                )),
+            )),
+            Box::new(view::LogPanel::new(viewmodel::Log::new(Arc::clone(
+                &model_log,
+            )))),
             Box::new(view::CentralPanel::new(vec![
                 Box::new(view::ImageFrame::new(viewmodel::ImageFrame::new(
                     "Current",
                     true,
                     Arc::clone(&image_service),
                     Arc::clone(&model_current),
+                    None,
+                    Arc::clone(&view_link),
                 ))),
                 Box::new(view::ImageFrame::new(viewmodel::ImageFrame::new(
                     "Preview",
                     false,
                     Arc::clone(&image_service),
                     Arc::clone(&model_preview),
+                    Some(Arc::clone(&model_task)),
+                    Arc::clone(&view_link),
                 ))),
             ])),
+            Box::new(view::ParameterWindow::new(viewmodel::ParameterWindow::new(
+                Arc::clone(&image_service),
+                Arc::clone(&model_active_operation),
+                Arc::clone(&model_task),
+            ))),
+            Box::new(view::Notifications::new(viewmodel::Log::new(Arc::clone(
+                &model_log,
+            )))),
         ];
 
         Self {
@@ -83,6 +139,9 @@ impl eframe::App for MyApp {
     }
 }
 
+/// Runs `f` without blocking the UI thread.
+/// On the web there are no threads, so `f` runs on the main thread once the current frame is done.
+/// Heavy work like operations should go through `execute_operation` instead.
 #[cfg(not(target_arch = "wasm32"))]
 fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
     std::thread::spawn(move || futures::executor::block_on(f));
@@ -91,3 +150,34 @@ fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
 fn execute<F: Future<Output = ()> + 'static>(f: F) {
     wasm_bindgen_futures::spawn_local(f);
 }
+
+/// Applies `operation` to `image` without blocking the UI thread and hands the result to `done`.
+/// On the web the operation runs in a web worker and `done` runs on the main thread.
+#[cfg(not(target_arch = "wasm32"))]
+fn execute_operation(
+    operation: Arc<dyn Operation>,
+    parameters: Parameters,
+    image: Arc<Option<DynamicImage>>,
+    progress: Progress,
+    done: impl FnOnce(Option<DynamicImage>, &Progress) + Send + 'static,
+) {
+    execute(async move {
+        let result = (*image)
+            .as_ref()
+            .and_then(|image| operation.apply(image, &parameters, &progress));
+        done(result, &progress);
+    });
+}
+#[cfg(target_arch = "wasm32")]
+fn execute_operation(
+    operation: Arc<dyn Operation>,
+    parameters: Parameters,
+    image: Arc<Option<DynamicImage>>,
+    progress: Progress,
+    done: impl FnOnce(Option<DynamicImage>, &Progress) + 'static,
+) {
+    match &*image {
+        Some(image) => worker::apply(operation.as_ref(), &parameters, image, progress, done),
+        None => done(None, &progress),
+    }
+}
@@ -1,3 +1,5 @@
 pub mod open_file_dialog;
+pub mod save_file_dialog;
 
 pub use open_file_dialog::open_file_dialog;
+pub use save_file_dialog::SaveFile;
@@ -4,7 +4,10 @@ use tokio::sync::oneshot;
 
 pub fn open_file_dialog() -> oneshot::Receiver<Option<FileHandle>> {
     let task = rfd::AsyncFileDialog::new()
-        .add_filter("Image files", &["png", "jpg", "jpeg"])
+        .add_filter(
+            "Image files",
+            &["png", "jpg", "jpeg", "bmp", "tif", "tiff", "webp"],
+        )
         .set_directory("/")
         .pick_file();
 
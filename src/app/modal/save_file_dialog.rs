@@ -0,0 +1,120 @@
+use std::io;
+use tokio::sync::oneshot;
+
+/// A save file dialog whose outcome views poll every frame.
+#[derive(Default)]
+pub struct SaveFile {
+    promise: Option<oneshot::Receiver<Option<io::Result<()>>>>,
+}
+
+impl SaveFile {
+    /// Opens the dialog, see `save_file_dialog`.
+    pub fn start(
+        &mut self,
+        file_name: String,
+        extension: &'static str,
+        mime_type: &'static str,
+        data: Vec<u8>,
+    ) {
+        self.promise = Some(save_file_dialog(file_name, extension, mime_type, data));
+    }
+
+    /// `true` while the dialog is open or the data is written.
+    pub fn is_busy(&self) -> bool {
+        self.promise.is_some()
+    }
+
+    /// The outcome once it is there, to be reported with `Log::report_saved`.
+    pub fn poll(&mut self) -> Option<Option<io::Result<()>>> {
+        let result = self.promise.as_mut()?.try_recv().ok()?;
+        self.promise = None;
+        Some(result)
+    }
+}
+
+/// Asks the user where to store `data` and writes it there.
+/// Resolves to `None` if the dialog was cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file_dialog(
+    file_name: String,
+    extension: &'static str,
+    _mime_type: &'static str,
+    data: Vec<u8>,
+) -> oneshot::Receiver<Option<io::Result<()>>> {
+    use crate::app;
+
+    let task = rfd::AsyncFileDialog::new()
+        .add_filter(extension, &[extension])
+        .set_file_name(&file_name)
+        .save_file();
+
+    let (sender, receiver) = oneshot::channel();
+
+    app::execute(async move {
+        let result = task.await.map(|file| std::fs::write(file.path(), &data));
+        sender.send(result).ok();
+    });
+
+    receiver
+}
+
+/// Browsers do not offer a save dialog, so the data is handed to the browser as a download.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file_dialog(
+    file_name: String,
+    _extension: &'static str,
+    mime_type: &'static str,
+    data: Vec<u8>,
+) -> oneshot::Receiver<Option<io::Result<()>>> {
+    let (sender, receiver) = oneshot::channel();
+    sender
+        .send(Some(download(&file_name, mime_type, &data).map_err(
+            |error| io::Error::new(io::ErrorKind::Other, format!("{:?}", error)),
+        )))
+        .ok();
+
+    receiver
+}
+
+/// How long the browser gets to start a download before its object url is revoked.
+#[cfg(target_arch = "wasm32")]
+const REVOKE_DELAY_MS: i32 = 10_000;
+
+#[cfg(target_arch = "wasm32")]
+fn download(
+    file_name: &str,
+    mime_type: &str,
+    data: &[u8],
+) -> Result<(), eframe::wasm_bindgen::JsValue> {
+    use eframe::wasm_bindgen::closure::Closure;
+    use eframe::wasm_bindgen::JsCast;
+
+    let array = js_sys::Uint8Array::from(data);
+    let parts = js_sys::Array::of1(&array);
+    let mut properties = web_sys::BlobPropertyBag::new();
+    properties.type_(mime_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &properties)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // the download starts asynchronously, revoking the url right away can cancel it
+    let revoke = Closure::once_into_js(move || {
+        web_sys::Url::revoke_object_url(&url).ok();
+    });
+    web_sys::window()
+        .ok_or("no window")?
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.unchecked_ref(),
+            REVOKE_DELAY_MS,
+        )?;
+    Ok(())
+}
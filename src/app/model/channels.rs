@@ -0,0 +1,114 @@
+use crate::app::model;
+use crate::app::model::color_space::LumaStandard;
+use crate::app::model::pixel;
+use crate::app::model::planes::{Depth, Planes};
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// One channel of a split image, kept as a single channel image of the original pixel type.
+pub struct Channel {
+    pub name: String,
+    pub image: Arc<model::Image>,
+}
+
+/// Channel layouts that `merge` can build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeLayout {
+    Luma,
+    LumaAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl MergeLayout {
+    pub const ALL: [MergeLayout; 4] = [
+        MergeLayout::Luma,
+        MergeLayout::LumaAlpha,
+        MergeLayout::Rgb,
+        MergeLayout::Rgba,
+    ];
+
+    pub const NAMES: [&'static str; 4] = ["L", "LA", "RGB", "RGBA"];
+
+    /// Names of the channels, one per input of `merge`.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            MergeLayout::Luma => &["L"],
+            MergeLayout::LumaAlpha => &["L", "A"],
+            MergeLayout::Rgb => &["R", "G", "B"],
+            MergeLayout::Rgba => &["R", "G", "B", "A"],
+        }
+    }
+}
+
+/// Splits `image` into one single channel image per channel, named like the channels of the
+/// pixel info. Every channel image keeps the pixel type of `image`.
+pub fn split(image: &DynamicImage) -> Vec<(&'static str, DynamicImage)> {
+    let planes = Planes::from_image(image);
+    let names = pixel::channel_names(image.color());
+    names
+        .iter()
+        .zip(&planes.planes)
+        .map(|(name, values)| (*name, planes.luma_image(values.clone())))
+        .collect()
+}
+
+/// Builds an image with `layout` from one image per channel. Each input contributes its
+/// (BT.601) luma, so single channel images from `split` are taken as they are. The result gets
+/// the deepest pixel type of the inputs.
+pub fn merge(images: &[&DynamicImage], layout: MergeLayout) -> Result<DynamicImage, String> {
+    let channel_count = layout.channels().len();
+    if images.len() != channel_count {
+        return Err(format!(
+            "{} needs {} channels, got {}",
+            MergeLayout::NAMES[layout as usize],
+            channel_count,
+            images.len()
+        ));
+    }
+    let (width, height) = (images[0].width(), images[0].height());
+    if let Some(image) = images
+        .iter()
+        .find(|image| image.width() != width || image.height() != height)
+    {
+        return Err(format!(
+            "all channels need the same size, {}x{} and {}x{} differ",
+            width,
+            height,
+            image.width(),
+            image.height()
+        ));
+    }
+
+    let planes: Vec<Planes> = images
+        .iter()
+        .map(|image| Planes::from_image(image))
+        .collect();
+    let depth = planes.iter().map(Planes::depth).max().unwrap_or(Depth::U8);
+    let merged = planes
+        .iter()
+        .map(|planes| {
+            let scale = depth.max_value() / planes.max_value();
+            luma(planes)
+                .into_iter()
+                .map(|value| value * scale)
+                .collect()
+        })
+        .collect();
+
+    Planes::from_planes(width, height, merged, depth)
+        .map(Planes::into_image)
+        .ok_or_else(|| "unsupported channel count".to_string())
+}
+
+fn luma(planes: &Planes) -> Vec<f32> {
+    match planes.color_planes().as_slice() {
+        [r, g, b] => planes.planes[*r]
+            .iter()
+            .zip(&planes.planes[*g])
+            .zip(&planes.planes[*b])
+            .map(|((r, g), b)| LumaStandard::Bt601.luma([*r, *g, *b]))
+            .collect(),
+        color_planes => planes.planes[color_planes[0]].clone(),
+    }
+}
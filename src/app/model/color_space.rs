@@ -0,0 +1,350 @@
+use crate::app::model::planes::Planes;
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+
+/// D65 reference white, used to normalize CIE XYZ.
+const WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+/// Colour spaces an RGB image can be converted to. Converted images are stored as RGB images of
+/// the same pixel type, with every channel scaled to `0.0..=1.0` of the full range as described
+/// by `channels`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// sRGB.
+    Rgb,
+    Hsv,
+    Hsl,
+    /// BT.601 full range, as used by JPEG.
+    YCbCr,
+    /// CIE 1931 XYZ relative to the D65 white point.
+    Xyz,
+    /// CIE 1976 L*a*b* relative to the D65 white point.
+    Lab,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 6] = [
+        ColorSpace::Rgb,
+        ColorSpace::Hsv,
+        ColorSpace::Hsl,
+        ColorSpace::YCbCr,
+        ColorSpace::Xyz,
+        ColorSpace::Lab,
+    ];
+
+    pub const NAMES: [&'static str; 6] = ["RGB", "HSV", "HSL", "YCbCr", "CIE XYZ", "CIE L*a*b*"];
+
+    /// How the channels are stored, e.g. for a hint next to the converted image.
+    pub fn channels(&self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "R, G, B",
+            ColorSpace::Hsv => "H / 360°, S, V",
+            ColorSpace::Hsl => "H / 360°, S, L",
+            ColorSpace::YCbCr => "Y, Cb + 0.5, Cr + 0.5",
+            ColorSpace::Xyz => "X / Xn, Y / Yn, Z / Zn",
+            ColorSpace::Lab => "L* / 100, (a* + 128) / 255, (b* + 128) / 255",
+        }
+    }
+
+    /// Converts normalized sRGB to the stored channels of this space.
+    pub fn encode(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace::Rgb => [r, g, b],
+            ColorSpace::Hsv => {
+                let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+                let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+                [hue([r, g, b]) / 360.0, saturation, max]
+            }
+            ColorSpace::Hsl => {
+                let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+                let lightness = (max + min) / 2.0;
+                let saturation = if max == min {
+                    0.0
+                } else {
+                    (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+                };
+                [hue([r, g, b]) / 360.0, saturation, lightness]
+            }
+            ColorSpace::YCbCr => {
+                let y = 0.299 * r + 0.587 * g + 0.114 * b;
+                [y, (b - y) * 0.564 + 0.5, (r - y) * 0.713 + 0.5]
+            }
+            ColorSpace::Xyz => {
+                let xyz = rgb_to_xyz([r, g, b]);
+                [xyz[0] / WHITE[0], xyz[1] / WHITE[1], xyz[2] / WHITE[2]]
+            }
+            ColorSpace::Lab => {
+                let xyz = rgb_to_xyz([r, g, b]);
+                let [fx, fy, fz] = [0, 1, 2].map(|index| lab_f(xyz[index] / WHITE[index]));
+                let l = 116.0 * fy - 16.0;
+                let a = 500.0 * (fx - fy);
+                let b = 200.0 * (fy - fz);
+                [l / 100.0, (a + 128.0) / 255.0, (b + 128.0) / 255.0]
+            }
+        }
+    }
+
+    /// Converts the stored channels of this space back to normalized sRGB.
+    pub fn decode(&self, [c0, c1, c2]: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace::Rgb => [c0, c1, c2],
+            ColorSpace::Hsv => {
+                let chroma = c2 * c1;
+                hue_to_rgb(c0 * 360.0, chroma, c2 - chroma)
+            }
+            ColorSpace::Hsl => {
+                let chroma = (1.0 - (2.0 * c2 - 1.0).abs()) * c1;
+                hue_to_rgb(c0 * 360.0, chroma, c2 - chroma / 2.0)
+            }
+            ColorSpace::YCbCr => {
+                let (cb, cr) = (c1 - 0.5, c2 - 0.5);
+                let r = c0 + 1.402 * cr;
+                let b = c0 + 1.772 * cb;
+                let g = (c0 - 0.299 * r - 0.114 * b) / 0.587;
+                [r, g, b]
+            }
+            ColorSpace::Xyz => xyz_to_rgb([c0 * WHITE[0], c1 * WHITE[1], c2 * WHITE[2]]),
+            ColorSpace::Lab => {
+                let (l, a, b) = (c0 * 100.0, c1 * 255.0 - 128.0, c2 * 255.0 - 128.0);
+                let fy = (l + 16.0) / 116.0;
+                let f = [fy + a / 500.0, fy, fy - b / 200.0];
+                xyz_to_rgb([0, 1, 2].map(|index| lab_f_inverse(f[index]) * WHITE[index]))
+            }
+        }
+    }
+}
+
+/// Interprets the colour channels of `image` in `from` and converts them to `to`. Grayscale
+/// images are treated as RGB with equal channels. Alpha and the pixel type are kept, so values
+/// outside of `0.0..=1.0` are clamped for integer images.
+pub fn convert(
+    image: &DynamicImage,
+    from: ColorSpace,
+    to: ColorSpace,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let promoted;
+    let image = match image {
+        DynamicImage::ImageLuma8(_) => {
+            promoted = DynamicImage::ImageRgb8(image.to_rgb8());
+            &promoted
+        }
+        DynamicImage::ImageLumaA8(_) => {
+            promoted = DynamicImage::ImageRgba8(image.to_rgba8());
+            &promoted
+        }
+        DynamicImage::ImageLuma16(_) => {
+            promoted = DynamicImage::ImageRgb16(image.to_rgb16());
+            &promoted
+        }
+        DynamicImage::ImageLumaA16(_) => {
+            promoted = DynamicImage::ImageRgba16(image.to_rgba16());
+            &promoted
+        }
+        _ => image,
+    };
+
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let max_value = planes.max_value();
+    let width = planes.width as usize;
+    for y in 0..planes.height as usize {
+        for index in y * width..(y + 1) * width {
+            let stored = [0, 1, 2].map(|channel| planes.planes[color_planes[channel]][index]);
+            let rgb = from.decode(stored.map(|value| value / max_value));
+            let converted = to.encode(rgb);
+            for (channel, value) in converted.iter().enumerate() {
+                planes.planes[color_planes[channel]][index] = value * max_value;
+            }
+        }
+        if !progress.row_done(y as u32, planes.height) {
+            return None;
+        }
+    }
+
+    Some(planes.into_image())
+}
+
+/// Ways of reducing a colour to a single grey value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LumaStandard {
+    /// 0.299 R + 0.587 G + 0.114 B, as in SD video and JPEG.
+    Bt601,
+    /// 0.2126 R + 0.7152 G + 0.0722 B, as in HD video and sRGB.
+    Bt709,
+    /// The mean of R, G and B.
+    Average,
+    /// The mean of the largest and the smallest channel, the L of HSL.
+    Lightness,
+}
+
+impl LumaStandard {
+    pub const ALL: [LumaStandard; 4] = [
+        LumaStandard::Bt601,
+        LumaStandard::Bt709,
+        LumaStandard::Average,
+        LumaStandard::Lightness,
+    ];
+
+    pub const NAMES: [&'static str; 4] = ["BT.601", "BT.709", "average", "lightness"];
+
+    pub fn luma(&self, [r, g, b]: [f32; 3]) -> f32 {
+        match self {
+            LumaStandard::Bt601 => 0.299 * r + 0.587 * g + 0.114 * b,
+            LumaStandard::Bt709 => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+            LumaStandard::Average => (r + g + b) / 3.0,
+            LumaStandard::Lightness => (r.max(g).max(b) + r.min(g).min(b)) / 2.0,
+        }
+    }
+}
+
+/// Hue in degrees, 0 for grey.
+fn hue([r, g, b]: [f32; 3]) -> f32 {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let chroma = max - min;
+    if chroma <= 0.0 {
+        return 0.0;
+    }
+    let hue = if max == r {
+        (g - b) / chroma
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    (hue * 60.0).rem_euclid(360.0)
+}
+
+/// The RGB colour with `hue` in degrees and `chroma`, with `offset` added to every channel.
+pub fn hue_to_rgb(hue: f32, chroma: f32, offset: f32) -> [f32; 3] {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let [r, g, b] = match sector as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    [r + offset, g + offset, b + offset]
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn rgb_to_xyz(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b,
+        0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b,
+    ]
+}
+
+fn xyz_to_rgb([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    ]
+    .map(linear_to_srgb)
+}
+
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+fn lab_f(t: f32) -> f32 {
+    if t > LAB_EPSILON {
+        t.cbrt()
+    } else {
+        (LAB_KAPPA * t + 16.0) / 116.0
+    }
+}
+
+fn lab_f_inverse(f: f32) -> f32 {
+    if f.powi(3) > LAB_EPSILON {
+        f.powi(3)
+    } else {
+        (116.0 * f - 16.0) / LAB_KAPPA
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        assert!(
+            actual
+                .iter()
+                .zip(&expected)
+                .all(|(a, e)| (a - e).abs() <= tolerance),
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn every_space_round_trips() {
+        let colors = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.5, 0.5, 0.5],
+            [1.0, 0.0, 0.0],
+            [0.2, 0.7, 0.3],
+            [0.9, 0.4, 0.8],
+            [0.05, 0.1, 0.6],
+        ];
+        for space in ColorSpace::ALL {
+            for rgb in colors {
+                let decoded = space.decode(space.encode(rgb));
+                assert!(
+                    decoded.iter().zip(&rgb).all(|(a, e)| (a - e).abs() < 1e-3),
+                    "{:?} turns {:?} into {:?}",
+                    space,
+                    rgb,
+                    decoded
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pure_red_has_known_coordinates() {
+        let red = [1.0, 0.0, 0.0];
+        assert_close(ColorSpace::Hsv.encode(red), [0.0, 1.0, 1.0], 1e-6);
+        assert_close(ColorSpace::Hsl.encode(red), [0.0, 1.0, 0.5], 1e-6);
+        // L* = 53.24, a* = 80.09, b* = 67.20
+        assert_close(
+            ColorSpace::Lab.encode(red),
+            [0.5324, (80.09 + 128.0) / 255.0, (67.20 + 128.0) / 255.0],
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn white_is_the_reference_white() {
+        let white = [1.0, 1.0, 1.0];
+        assert_close(ColorSpace::Xyz.encode(white), [1.0, 1.0, 1.0], 1e-4);
+        assert_close(
+            ColorSpace::Lab.encode(white),
+            [1.0, 128.0 / 255.0, 128.0 / 255.0],
+            1e-4,
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// Per-pixel absolute difference between two images of the same size.
+/// Values are the mean of the red, green and blue differences, in `0.0..=1.0`.
+pub struct Difference {
+    pub width: u32,
+    pub height: u32,
+    /// The values quantized to `0..=u16::MAX`, so that heatmaps can be rendered with a lookup.
+    pub levels: Vec<u16>,
+    pub max: f32,
+    pub mean: f32,
+}
+
+/// Colours of the heatmap, from no difference to maximal difference.
+const HEATMAP: [[f32; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [1.0, 0.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 1.0, 1.0],
+];
+
+/// Compares the images in floating point, so images of different pixel types can be compared.
+/// Returns `None` if the sizes differ.
+pub fn difference(a: &DynamicImage, b: &DynamicImage) -> Option<Difference> {
+    if a.width() != b.width() || a.height() != b.height() {
+        return None;
+    }
+
+    let a = a.to_rgb32f();
+    let b = b.to_rgb32f();
+    let values: Vec<f32> = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(a, b)| {
+            ((a[0] - b[0]).abs() + (a[1] - b[1]).abs() + (a[2] - b[2]).abs()).min(3.0) / 3.0
+        })
+        .collect();
+
+    let max = values.iter().copied().fold(0.0, f32::max);
+    let mean = values.iter().sum::<f32>() / values.len().max(1) as f32;
+
+    Some(Difference {
+        width: a.width(),
+        height: a.height(),
+        levels: values
+            .iter()
+            .map(|value| (value * u16::MAX as f32).round() as u16)
+            .collect(),
+        max,
+        mean,
+    })
+}
+
+/// Renders the difference as a heatmap. Differences are multiplied by `amplification` first,
+/// so that small changes become visible. Only the palette depends on `amplification`, the pixels
+/// are looked up in it.
+pub fn heatmap(difference: &Difference, amplification: f32) -> DynamicImage {
+    let palette: Vec<Rgb<u8>> = (0..=u16::MAX)
+        .map(|level| {
+            heatmap_color((level as f32 / u16::MAX as f32 * amplification).clamp(0.0, 1.0))
+        })
+        .collect();
+    let mut image = RgbImage::new(difference.width, difference.height);
+    for (pixel, level) in image.pixels_mut().zip(&difference.levels) {
+        *pixel = palette[*level as usize];
+    }
+
+    DynamicImage::ImageRgb8(image)
+}
+
+fn heatmap_color(value: f32) -> Rgb<u8> {
+    let position = value * (HEATMAP.len() - 1) as f32;
+    let index = (position.floor() as usize).min(HEATMAP.len() - 2);
+    let t = position - index as f32;
+    let (from, to) = (HEATMAP[index], HEATMAP[index + 1]);
+
+    Rgb([0, 1, 2]
+        .map(|channel| ((from[channel] + (to[channel] - from[channel]) * t) * 255.0).round() as u8))
+}
@@ -0,0 +1,271 @@
+use crate::app::model::edges;
+use crate::app::model::task::Progress;
+use image::{GrayImage, Rgb, RgbImage};
+use std::collections::VecDeque;
+
+/// Which neighbours of a pixel belong to the same component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Horizontal and vertical neighbours.
+    Four,
+    /// Also diagonal neighbours.
+    Eight,
+}
+
+impl Connectivity {
+    pub const ALL: [Connectivity; 2] = [Connectivity::Four, Connectivity::Eight];
+
+    pub const NAMES: [&'static str; 2] = ["4", "8"];
+
+    fn offsets(&self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            Connectivity::Eight => &[
+                (1, 0),
+                (-1, 0),
+                (0, 1),
+                (0, -1),
+                (1, 1),
+                (1, -1),
+                (-1, 1),
+                (-1, -1),
+            ],
+        }
+    }
+}
+
+/// Measurements of one connected component, in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Component {
+    /// Starts at 1, 0 is the background in `Labeling::labels`.
+    pub label: u32,
+    pub area: u32,
+    /// x, y, width and height of the bounding box.
+    pub bounds: [u32; 4],
+    pub centroid: [f32; 2],
+    /// Length of the boundary between the component and other pixels, counted in pixel edges.
+    pub perimeter: u32,
+    /// Eccentricity of the ellipse with the same second moments, 0 for a circle and close to 1
+    /// for a line.
+    pub eccentricity: f32,
+}
+
+/// Components of a binary image and the label of every pixel.
+#[derive(Clone, Debug)]
+pub struct Labeling {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major component labels, 0 for the background.
+    pub labels: Vec<u32>,
+    /// Sorted by label, so the component with label `n` is at index `n - 1`.
+    pub components: Vec<Component>,
+}
+
+impl Labeling {
+    /// The pixels of `component` as horizontal runs `[y, first x, last x + 1]`.
+    pub fn runs(&self, component: &Component) -> Vec<[u32; 3]> {
+        let [x0, y0, width, height] = component.bounds;
+        let mut runs = Vec::new();
+        for y in y0..y0 + height {
+            let row = &self.labels[(y * self.width) as usize..((y + 1) * self.width) as usize];
+            let mut start = None;
+            for x in x0..=x0 + width {
+                let inside = x < x0 + width && row[x as usize] == component.label;
+                match (inside, start) {
+                    (true, None) => start = Some(x),
+                    (false, Some(first)) => {
+                        runs.push([y, first, x]);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        runs
+    }
+}
+
+/// Labels the components of the non-zero pixels of `image`, numbered in the order of their
+/// topmost, then leftmost pixel.
+pub fn label(
+    image: &GrayImage,
+    connectivity: Connectivity,
+    progress: &Progress,
+) -> Option<Labeling> {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let foreground = |x: i64, y: i64| {
+        x >= 0 && y >= 0 && x < width && y < height && image.as_raw()[(y * width + x) as usize] != 0
+    };
+
+    let mut labels = vec![0; (width * height) as usize];
+    let mut components = Vec::new();
+    let mut queue = VecDeque::new();
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start = (start_y * width + start_x) as usize;
+            if labels[start] != 0 || !foreground(start_x, start_y) {
+                continue;
+            }
+
+            let label = components.len() as u32 + 1;
+            labels[start] = label;
+            queue.push_back((start_x, start_y));
+            let mut moments = Moments::default();
+            let mut perimeter = 0;
+            while let Some((x, y)) = queue.pop_front() {
+                moments.add(x as f64, y as f64);
+                perimeter += [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                    .iter()
+                    .filter(|(dx, dy)| !foreground(x + dx, y + dy))
+                    .count() as u32;
+                for (dx, dy) in connectivity.offsets() {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if foreground(nx, ny) {
+                        let index = (ny * width + nx) as usize;
+                        if labels[index] == 0 {
+                            labels[index] = label;
+                            queue.push_back((nx, ny));
+                        }
+                    }
+                }
+            }
+            components.push(moments.component(label, perimeter));
+        }
+        if !progress.row_done(start_y as u32, height as u32) {
+            return None;
+        }
+    }
+
+    Some(Labeling {
+        width: width as u32,
+        height: height as u32,
+        labels,
+        components,
+    })
+}
+
+/// Shows every component in its own colour on black. Components for which `visible` returns
+/// `false` are left out.
+pub fn render(labeling: &Labeling, visible: impl Fn(&Component) -> bool) -> RgbImage {
+    let colors: Vec<Rgb<u8>> = labeling
+        .components
+        .iter()
+        .map(|component| {
+            if visible(component) {
+                label_color(component.label)
+            } else {
+                Rgb([0, 0, 0])
+            }
+        })
+        .collect();
+    RgbImage::from_fn(labeling.width, labeling.height, |x, y| {
+        match labeling.labels[(y * labeling.width + x) as usize] {
+            0 => Rgb([0, 0, 0]),
+            label => colors[label as usize - 1],
+        }
+    })
+}
+
+/// A bright colour that looks random but is the same for the same label.
+pub fn label_color(label: u32) -> Rgb<u8> {
+    // spread the hues with the golden ratio, vary brightness a little with a hash
+    let hue = (label as f32 * 0.618_034).fract() * 360.0;
+    let hash = label.wrapping_mul(0x9E37_79B1) >> 24;
+    let value = 0.7 + (hash % 4) as f32 * 0.1;
+    edges::hsv_to_rgb(hue, 0.85, value)
+}
+
+/// Running sums for area, bounds, centroid and second moments of a component.
+struct Moments {
+    count: u32,
+    min: [i64; 2],
+    max: [i64; 2],
+    sum: [f64; 2],
+    sum_xx: f64,
+    sum_xy: f64,
+    sum_yy: f64,
+}
+
+impl Default for Moments {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            min: [i64::MAX; 2],
+            max: [i64::MIN; 2],
+            sum: [0.0; 2],
+            sum_xx: 0.0,
+            sum_xy: 0.0,
+            sum_yy: 0.0,
+        }
+    }
+}
+
+impl Moments {
+    fn add(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        self.min = [self.min[0].min(x as i64), self.min[1].min(y as i64)];
+        self.max = [self.max[0].max(x as i64), self.max[1].max(y as i64)];
+        self.sum[0] += x;
+        self.sum[1] += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+        self.sum_yy += y * y;
+    }
+
+    fn component(&self, label: u32, perimeter: u32) -> Component {
+        let count = self.count as f64;
+        let [mean_x, mean_y] = [self.sum[0] / count, self.sum[1] / count];
+        let xx = self.sum_xx / count - mean_x * mean_x;
+        let xy = self.sum_xy / count - mean_x * mean_y;
+        let yy = self.sum_yy / count - mean_y * mean_y;
+        // eigenvalues of the covariance matrix are the squared semi-axes up to a common factor
+        let half_trace = (xx + yy) / 2.0;
+        let root = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        let (major, minor) = (half_trace + root, half_trace - root);
+        let eccentricity = if major > f64::EPSILON {
+            (1.0 - minor.max(0.0) / major).sqrt()
+        } else {
+            0.0
+        };
+
+        Component {
+            label,
+            area: self.count,
+            bounds: [
+                self.min[0] as u32,
+                self.min[1] as u32,
+                (self.max[0] - self.min[0] + 1) as u32,
+                (self.max[1] - self.min[1] + 1) as u32,
+            ],
+            // pixel centers are at half integer coordinates
+            centroid: [(mean_x + 0.5) as f32, (mean_y + 0.5) as f32],
+            perimeter,
+            eccentricity: eccentricity as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_cover_only_the_pixels_of_the_component() {
+        // a U shape, whose gap lies within its bounding box, next to another component
+        #[rustfmt::skip]
+        let pixels = vec![
+            1, 0, 1, 0, 1,
+            1, 0, 1, 0, 0,
+            1, 1, 1, 0, 0,
+        ];
+        let image = GrayImage::from_raw(5, 3, pixels).unwrap();
+        let labeling = label(&image, Connectivity::Four, &Progress::none()).unwrap();
+
+        let runs = labeling.runs(&labeling.components[0]);
+        assert_eq!(
+            runs,
+            vec![[0, 0, 1], [0, 2, 3], [1, 0, 1], [1, 2, 3], [2, 0, 3]]
+        );
+        assert_eq!(labeling.runs(&labeling.components[1]), vec![[0, 4, 5]]);
+    }
+}
@@ -0,0 +1,789 @@
+use crate::app::model::task::Progress;
+use image::GrayImage;
+use std::f64::consts::TAU;
+
+/// Number of segments the outline of an enclosing circle is drawn with.
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// Neighbours in clockwise order (with y pointing down), as row and column offsets.
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// The border of a component of non-zero pixels or of a hole in one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    /// Centers of the border pixels in tracing order, or a simplification of them.
+    pub points: Vec<[f32; 2]>,
+    pub is_hole: bool,
+    /// Index of the enclosing contour: the outer border around a hole, or the hole around an
+    /// outer border. `None` for outer borders that are not inside any component.
+    pub parent: Option<usize>,
+}
+
+/// Finds the borders of the non-zero pixels of `image` and how they are nested, by border
+/// following (Suzuki and Abe). Components use 8-connectivity, holes 4-connectivity.
+pub fn find_contours(image: &GrayImage, progress: &Progress) -> Option<Vec<Contour>> {
+    // pixels get the number of the border they belong to, so a frame of 0 is added around the
+    // image to keep the tracing inside
+    let (width, height) = (image.width() as i64 + 2, image.height() as i64 + 2);
+    let mut labels: Vec<i64> = vec![0; (width * height) as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[0] != 0 {
+            labels[((y as i64 + 1) * width + x as i64 + 1) as usize] = 1;
+        }
+    }
+    let at = |labels: &[i64], (row, column): (i64, i64)| labels[(row * width + column) as usize];
+
+    // border 1 is the frame, a hole without parent
+    let mut borders: Vec<(bool, Option<usize>)> = vec![(true, None)];
+    let mut contours = Vec::new();
+    for row in 1..height - 1 {
+        let mut last_border = 1;
+        for column in 1..width - 1 {
+            let start = (row, column);
+            let value = at(&labels, start);
+            let (is_hole, from) = if value == 1 && at(&labels, (row, column - 1)) == 0 {
+                (false, (row, column - 1))
+            } else if value >= 1 && at(&labels, (row, column + 1)) == 0 {
+                if value > 1 {
+                    last_border = value as usize;
+                }
+                (true, (row, column + 1))
+            } else {
+                if value != 0 && value != 1 {
+                    last_border = value.unsigned_abs() as usize;
+                }
+                continue;
+            };
+
+            let (last_is_hole, last_parent) = borders[last_border - 1];
+            let parent = if is_hole == last_is_hole {
+                last_parent
+            } else {
+                Some(last_border)
+            };
+            borders.push((is_hole, parent));
+            let border = borders.len() as i64;
+
+            let points = follow_border(&mut labels, width, start, from, border);
+            contours.push(Contour {
+                points: points
+                    .into_iter()
+                    .map(|(row, column)| [column as f32 - 0.5, row as f32 - 0.5])
+                    .collect(),
+                is_hole,
+                // border numbers start at 1 for the frame and 2 for the first contour
+                parent: parent.filter(|parent| *parent > 1).map(|parent| parent - 2),
+            });
+
+            let value = at(&labels, start);
+            if value != 1 {
+                last_border = value.unsigned_abs() as usize;
+            }
+        }
+        if !progress.row_done(row as u32 - 1, height as u32 - 2) {
+            return None;
+        }
+    }
+
+    Some(contours)
+}
+
+/// Traces the border through `start`, coming from the background pixel `from`, and marks its
+/// pixels with `border` (negative where the pixel to the right is background). Returns the
+/// border pixels as rows and columns.
+fn follow_border(
+    labels: &mut [i64],
+    width: i64,
+    start: (i64, i64),
+    from: (i64, i64),
+    border: i64,
+) -> Vec<(i64, i64)> {
+    let index = |(row, column): (i64, i64)| (row * width + column) as usize;
+    let direction = |from: (i64, i64), to: (i64, i64)| {
+        let offset = (to.0 - from.0, to.1 - from.1);
+        NEIGHBOURS
+            .iter()
+            .position(|neighbour| *neighbour == offset)
+            .unwrap_or(0)
+    };
+    let step = |pixel: (i64, i64), direction: usize| {
+        let (row, column) = NEIGHBOURS[direction % 8];
+        (pixel.0 + row, pixel.1 + column)
+    };
+
+    // clockwise from `from` for the first pixel of the border
+    let first_direction = direction(start, from);
+    let first = (0..8)
+        .map(|offset| step(start, first_direction + offset))
+        .find(|pixel| labels[index(*pixel)] != 0);
+    let first = match first {
+        Some(first) => first,
+        None => {
+            // a single pixel
+            labels[index(start)] = -border;
+            return vec![start];
+        }
+    };
+
+    let mut points = Vec::new();
+    let (mut previous, mut current) = (first, start);
+    loop {
+        points.push(current);
+        // counterclockwise from the pixel after `previous`
+        let back = direction(current, previous);
+        let mut right_is_background = false;
+        let mut next = current;
+        for offset in 1..=8 {
+            let direction = (back + 8 - offset) % 8;
+            let pixel = step(current, direction);
+            if labels[index(pixel)] != 0 {
+                next = pixel;
+                break;
+            }
+            if direction == 0 {
+                right_is_background = true;
+            }
+        }
+
+        if right_is_background {
+            labels[index(current)] = -border;
+        } else if labels[index(current)] == 1 {
+            labels[index(current)] = border;
+        }
+
+        if next == start && current == first {
+            return points;
+        }
+        previous = current;
+        current = next;
+    }
+}
+
+/// Simplifies the closed polygon `points` (Douglas-Peucker), keeping every removed point within
+/// `epsilon` of the result. An `epsilon` of 0 keeps all points.
+pub fn approximate(points: &[[f32; 2]], epsilon: f32) -> Vec<[f32; 2]> {
+    if epsilon <= 0.0 || points.len() < 3 {
+        return points.to_vec();
+    }
+
+    // split the closed polygon at the point farthest from the first one
+    let farthest = (1..points.len())
+        .max_by(|a, b| distance(points[0], points[*a]).total_cmp(&distance(points[0], points[*b])))
+        .unwrap_or(0);
+    let mut result = Vec::new();
+    simplify(&points[..=farthest], epsilon, &mut result);
+    let mut back: Vec<[f32; 2]> = points[farthest..].to_vec();
+    back.push(points[0]);
+    simplify(&back, epsilon, &mut result);
+    result
+}
+
+/// Pushes the simplified open polyline `points`, without its last point, onto `result`.
+fn simplify(points: &[[f32; 2]], epsilon: f32, result: &mut Vec<[f32; 2]>) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let farthest = (1..points.len().saturating_sub(1))
+        .map(|index| (index, segment_distance(points[index], first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match farthest {
+        Some((index, distance)) if distance > epsilon => {
+            simplify(&points[..=index], epsilon, result);
+            simplify(&points[index..], epsilon, result);
+        }
+        _ => result.push(first),
+    }
+}
+
+/// The smallest convex polygon containing `points`, clockwise on screen.
+pub fn convex_hull(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    // monotone chain, lower and upper half
+    let mut hull: Vec<[f32; 2]> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        for point in &sorted {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], *point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+        if pass == 0 {
+            sorted.reverse();
+        }
+    }
+    hull
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RotatedRect {
+    pub center: [f32; 2],
+    /// Length along the angle, then across it.
+    pub size: [f32; 2],
+    /// Direction of the first side in degrees, clockwise on screen from the x axis.
+    pub angle: f32,
+}
+
+impl RotatedRect {
+    pub fn corners(&self) -> Vec<[f32; 2]> {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let [half_u, half_v] = [self.size[0] / 2.0, self.size[1] / 2.0];
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(|(u, v)| {
+                let (u, v) = (u * half_u, v * half_v);
+                [
+                    self.center[0] + u * cos - v * sin,
+                    self.center[1] + u * sin + v * cos,
+                ]
+            })
+            .collect()
+    }
+}
+
+/// The rotated rectangle of smallest area containing `points`. One of its sides lies on an edge
+/// of the convex hull, so only those directions are tried.
+pub fn min_area_rect(points: &[[f32; 2]]) -> RotatedRect {
+    let hull = convex_hull(points);
+    let mut best: Option<(f32, RotatedRect)> = None;
+    for (index, start) in hull.iter().enumerate() {
+        let end = hull[(index + 1) % hull.len()];
+        let length = distance(*start, end);
+        if length == 0.0 {
+            continue;
+        }
+        let u = [(end[0] - start[0]) / length, (end[1] - start[1]) / length];
+        let v = [-u[1], u[0]];
+        let project = |axis: [f32; 2]| {
+            hull.iter()
+                .map(|point| point[0] * axis[0] + point[1] * axis[1])
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                })
+        };
+        let ((min_u, max_u), (min_v, max_v)) = (project(u), project(v));
+        let area = (max_u - min_u) * (max_v - min_v);
+        if best.map_or(true, |(best_area, _)| area < best_area) {
+            let (center_u, center_v) = ((min_u + max_u) / 2.0, (min_v + max_v) / 2.0);
+            best = Some((
+                area,
+                RotatedRect {
+                    center: [
+                        center_u * u[0] + center_v * v[0],
+                        center_u * u[1] + center_v * v[1],
+                    ],
+                    size: [max_u - min_u, max_v - min_v],
+                    angle: u[1].atan2(u[0]).to_degrees(),
+                },
+            ));
+        }
+    }
+
+    best.map(|(_, rect)| rect).unwrap_or(RotatedRect {
+        center: hull.first().copied().unwrap_or([0.0, 0.0]),
+        size: [0.0, 0.0],
+        angle: 0.0,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: [f32; 2],
+    pub radius: f32,
+}
+
+impl Circle {
+    /// Points along the outline, for drawing it.
+    pub fn outline(&self) -> Vec<[f32; 2]> {
+        (0..CIRCLE_SEGMENTS)
+            .map(|index| {
+                let angle = (index as f64 / CIRCLE_SEGMENTS as f64 * TAU) as f32;
+                [
+                    self.center[0] + self.radius * angle.cos(),
+                    self.center[1] + self.radius * angle.sin(),
+                ]
+            })
+            .collect()
+    }
+
+    fn contains(&self, point: [f32; 2]) -> bool {
+        // some slack for rounding, the circles are built from the points themselves
+        distance(self.center, point) <= self.radius * (1.0 + 1e-5) + 1e-4
+    }
+}
+
+/// The smallest circle containing `points` (Welzl's algorithm, on the convex hull).
+pub fn min_enclosing_circle(points: &[[f32; 2]]) -> Circle {
+    let hull = convex_hull(points);
+    let mut circle = Circle {
+        center: hull.first().copied().unwrap_or([0.0, 0.0]),
+        radius: 0.0,
+    };
+    for i in 0..hull.len() {
+        if circle.contains(hull[i]) {
+            continue;
+        }
+        circle = Circle {
+            center: hull[i],
+            radius: 0.0,
+        };
+        for j in 0..i {
+            if circle.contains(hull[j]) {
+                continue;
+            }
+            circle = diameter_circle(hull[i], hull[j]);
+            for k in 0..j {
+                if !circle.contains(hull[k]) {
+                    circle = circumcircle(hull[i], hull[j], hull[k]);
+                }
+            }
+        }
+    }
+    circle
+}
+
+fn diameter_circle(a: [f32; 2], b: [f32; 2]) -> Circle {
+    Circle {
+        center: [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0],
+        radius: distance(a, b) / 2.0,
+    }
+}
+
+/// The circle through three points, or around the two farthest apart if they are collinear.
+fn circumcircle(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> Circle {
+    let [ax, ay] = [a[0] as f64, a[1] as f64];
+    let [bx, by] = [b[0] as f64 - ax, b[1] as f64 - ay];
+    let [cx, cy] = [c[0] as f64 - ax, c[1] as f64 - ay];
+    let d = 2.0 * (bx * cy - by * cx);
+    if d.abs() < 1e-12 {
+        return [
+            diameter_circle(a, b),
+            diameter_circle(a, c),
+            diameter_circle(b, c),
+        ]
+        .into_iter()
+        .max_by(|x, y| x.radius.total_cmp(&y.radius))
+        .unwrap_or_else(|| diameter_circle(a, b));
+    }
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let ux = (cy * b2 - by * c2) / d;
+    let uy = (bx * c2 - cx * b2) / d;
+    Circle {
+        center: [(ax + ux) as f32, (ay + uy) as f32],
+        radius: (ux * ux + uy * uy).sqrt() as f32,
+    }
+}
+
+/// Shape measurements of a closed polygon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Descriptors {
+    pub area: f64,
+    pub perimeter: f64,
+    pub convex_hull: Vec<[f32; 2]>,
+    pub min_area_rect: RotatedRect,
+    pub enclosing_circle: Circle,
+    /// The seven moment invariants of Hu, unchanged by translation, scale and rotation (the last
+    /// one changes sign under reflection).
+    pub hu_moments: [f64; 7],
+}
+
+impl Descriptors {
+    pub fn of(points: &[[f32; 2]]) -> Self {
+        let moments = Moments::of(points);
+        Self {
+            area: moments.m00,
+            perimeter: points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(a, b)| distance(*a, *b) as f64)
+                .sum(),
+            convex_hull: convex_hull(points),
+            min_area_rect: min_area_rect(points),
+            enclosing_circle: min_enclosing_circle(points),
+            hu_moments: moments.hu(),
+        }
+    }
+}
+
+/// Raw moments up to order 3 of the area inside a polygon.
+struct Moments {
+    m00: f64,
+    m10: f64,
+    m01: f64,
+    m20: f64,
+    m11: f64,
+    m02: f64,
+    m30: f64,
+    m21: f64,
+    m12: f64,
+    m03: f64,
+}
+
+impl Moments {
+    /// Sums over the edges by Green's theorem. The orientation of the polygon does not matter.
+    fn of(points: &[[f32; 2]]) -> Self {
+        let mut sums = [0.0f64; 10];
+        for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+            let [xi, yi] = [a[0] as f64, a[1] as f64];
+            let [xj, yj] = [b[0] as f64, b[1] as f64];
+            let cross = xi * yj - xj * yi;
+            let terms = [
+                1.0,
+                xi + xj,
+                yi + yj,
+                xi * xi + xi * xj + xj * xj,
+                xi * (2.0 * yi + yj) + xj * (yi + 2.0 * yj),
+                yi * yi + yi * yj + yj * yj,
+                (xi + xj) * (xi * xi + xj * xj),
+                xi * xi * (3.0 * yi + yj) + 2.0 * xi * xj * (yi + yj) + xj * xj * (yi + 3.0 * yj),
+                yi * yi * (3.0 * xi + xj) + 2.0 * yi * yj * (xi + xj) + yj * yj * (xi + 3.0 * xj),
+                (yi + yj) * (yi * yi + yj * yj),
+            ];
+            for (sum, term) in sums.iter_mut().zip(terms) {
+                *sum += cross * term;
+            }
+        }
+        let divisors = [2.0, 6.0, 6.0, 12.0, 24.0, 12.0, 20.0, 60.0, 60.0, 20.0];
+        let sign = if sums[0] < 0.0 { -1.0 } else { 1.0 };
+        let [m00, m10, m01, m20, m11, m02, m30, m21, m12, m03] = {
+            let mut moments = [0.0; 10];
+            for ((moment, sum), divisor) in moments.iter_mut().zip(sums).zip(divisors) {
+                *moment = sign * sum / divisor;
+            }
+            moments
+        };
+        Self {
+            m00,
+            m10,
+            m01,
+            m20,
+            m11,
+            m02,
+            m30,
+            m21,
+            m12,
+            m03,
+        }
+    }
+
+    /// All 0 for polygons without area.
+    fn hu(&self) -> [f64; 7] {
+        if self.m00 <= f64::EPSILON {
+            return [0.0; 7];
+        }
+        let (x, y) = (self.m10 / self.m00, self.m01 / self.m00);
+        let mu20 = self.m20 - x * self.m10;
+        let mu11 = self.m11 - x * self.m01;
+        let mu02 = self.m02 - y * self.m01;
+        let mu30 = self.m30 - 3.0 * x * self.m20 + 2.0 * x * x * self.m10;
+        let mu21 = self.m21 - 2.0 * x * self.m11 - y * self.m20 + 2.0 * x * x * self.m01;
+        let mu12 = self.m12 - 2.0 * y * self.m11 - x * self.m02 + 2.0 * y * y * self.m10;
+        let mu03 = self.m03 - 3.0 * y * self.m02 + 2.0 * y * y * self.m01;
+
+        let normalize = |mu: f64, order: i32| mu / self.m00.powf(1.0 + order as f64 / 2.0);
+        let (n20, n11, n02) = (normalize(mu20, 2), normalize(mu11, 2), normalize(mu02, 2));
+        let (n30, n21, n12, n03) = (
+            normalize(mu30, 3),
+            normalize(mu21, 3),
+            normalize(mu12, 3),
+            normalize(mu03, 3),
+        );
+
+        let (a, b) = (n30 + n12, n21 + n03);
+        let (c, d) = (n30 - 3.0 * n12, 3.0 * n21 - n03);
+        [
+            n20 + n02,
+            (n20 - n02).powi(2) + 4.0 * n11 * n11,
+            c * c + d * d,
+            a * a + b * b,
+            c * a * (a * a - 3.0 * b * b) + d * b * (3.0 * a * a - b * b),
+            (n20 - n02) * (a * a - b * b) + 4.0 * n11 * a * b,
+            d * a * (a * a - 3.0 * b * b) - c * b * (3.0 * a * a - b * b),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContourFormat {
+    Json,
+    Svg,
+}
+
+impl ContourFormat {
+    pub const ALL: [ContourFormat; 2] = [ContourFormat::Json, ContourFormat::Svg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContourFormat::Json => "JSON",
+            ContourFormat::Svg => "SVG",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ContourFormat::Json => "json",
+            ContourFormat::Svg => "svg",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ContourFormat::Json => "application/json",
+            ContourFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Writes `contours` of a `width` x `height` image with their descriptors.
+pub fn encode(
+    contours: &[(Contour, Descriptors)],
+    width: u32,
+    height: u32,
+    format: ContourFormat,
+) -> Vec<u8> {
+    match format {
+        ContourFormat::Json => to_json(contours, width, height),
+        ContourFormat::Svg => to_svg(contours, width, height),
+    }
+    .into_bytes()
+}
+
+fn to_json(contours: &[(Contour, Descriptors)], width: u32, height: u32) -> String {
+    let point = |[x, y]: [f32; 2]| format!("[{},{}]", number(x as f64), number(y as f64));
+    let points = |points: &[[f32; 2]]| {
+        let points: Vec<String> = points.iter().map(|p| point(*p)).collect();
+        format!("[{}]", points.join(","))
+    };
+    let entries: Vec<String> = contours
+        .iter()
+        .map(|(contour, descriptors)| {
+            let rect = &descriptors.min_area_rect;
+            let circle = &descriptors.enclosing_circle;
+            let hu: Vec<String> = descriptors.hu_moments.iter().map(|v| number(*v)).collect();
+            format!(
+                concat!(
+                    "{{\"parent\":{},\"hole\":{},\"points\":{},\"area\":{},\"perimeter\":{},",
+                    "\"convex_hull\":{},",
+                    "\"min_area_rect\":{{\"center\":{},\"size\":{},\"angle\":{}}},",
+                    "\"enclosing_circle\":{{\"center\":{},\"radius\":{}}},",
+                    "\"hu_moments\":[{}]}}"
+                ),
+                contour
+                    .parent
+                    .map_or("null".to_string(), |parent| parent.to_string()),
+                contour.is_hole,
+                points(&contour.points),
+                number(descriptors.area),
+                number(descriptors.perimeter),
+                points(&descriptors.convex_hull),
+                point(rect.center),
+                point(rect.size),
+                number(rect.angle as f64),
+                point(circle.center),
+                number(circle.radius as f64),
+                hu.join(",")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"width\":{},\"height\":{},\"contours\":[\n{}\n]}}\n",
+        width,
+        height,
+        entries.join(",\n")
+    )
+}
+
+fn to_svg(contours: &[(Contour, Descriptors)], width: u32, height: u32) -> String {
+    let mut svg = format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" ",
+            "viewBox=\"0 0 {0} {1}\">\n"
+        ),
+        width, height
+    );
+    for (index, (contour, _)) in contours.iter().enumerate() {
+        let points: Vec<String> = contour
+            .points
+            .iter()
+            .map(|[x, y]| format!("{},{}", number(*x as f64), number(*y as f64)))
+            .collect();
+        svg.push_str(&format!(
+            "  <polygon id=\"contour-{}\" class=\"{}\" points=\"{}\" fill=\"none\" stroke=\"{}\"/>\n",
+            index,
+            if contour.is_hole { "hole" } else { "outer" },
+            points.join(" "),
+            if contour.is_hole { "red" } else { "lime" }
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Shortest form that reads back the same, non-finite values as 0 since JSON has no NaN.
+fn number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "0".to_string()
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+/// Distance from `point` to the segment from `a` to `b`.
+fn segment_distance(point: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return distance(point, a);
+    }
+    let t = (((point[0] - a[0]) * dx + (point[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0);
+    distance(point, [a[0] + t * dx, a[1] + t * dy])
+}
+
+/// Positive if `o`, `a`, `b` turn counterclockwise in a y-up system.
+fn cross(o: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// An image of `width` x `height` with the pixels for which `foreground` is `true` set.
+    fn image(width: u32, height: u32, foreground: impl Fn(u32, u32) -> bool) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            Luma([if foreground(x, y) { 255 } else { 0 }])
+        })
+    }
+
+    fn square(x: u32, y: u32, x0: u32, y0: u32, size: u32) -> bool {
+        (x0..x0 + size).contains(&x) && (y0..y0 + size).contains(&y)
+    }
+
+    #[test]
+    fn filled_square_has_one_outer_border_through_its_border_pixels() {
+        let contours = find_contours(
+            &image(7, 7, |x, y| square(x, y, 2, 2, 3)),
+            &Progress::none(),
+        )
+        .unwrap();
+
+        assert_eq!(contours.len(), 1);
+        assert!(!contours[0].is_hole);
+        assert_eq!(contours[0].parent, None);
+        let mut points = contours[0].points.clone();
+        points.sort_by(|a, b| a[1].total_cmp(&b[1]).then(a[0].total_cmp(&b[0])));
+        assert_eq!(
+            points,
+            vec![
+                [2.5, 2.5],
+                [3.5, 2.5],
+                [4.5, 2.5],
+                [2.5, 3.5],
+                [4.5, 3.5],
+                [2.5, 4.5],
+                [3.5, 4.5],
+                [4.5, 4.5],
+            ]
+        );
+    }
+
+    #[test]
+    fn ring_has_an_outer_border_and_a_hole_inside_it() {
+        let ring = image(9, 9, |x, y| square(x, y, 1, 1, 7) && !square(x, y, 3, 3, 3));
+        let contours = find_contours(&ring, &Progress::none()).unwrap();
+
+        assert_eq!(contours.len(), 2);
+        assert!(!contours[0].is_hole);
+        assert_eq!(contours[0].parent, None);
+        assert!(contours[1].is_hole);
+        assert_eq!(contours[1].parent, Some(0));
+        assert_eq!(Descriptors::of(&contours[0].points).area, 36.0);
+    }
+
+    #[test]
+    fn island_in_a_hole_is_a_component_inside_the_hole() {
+        let island = image(11, 11, |x, y| {
+            (square(x, y, 1, 1, 9) && !square(x, y, 3, 3, 5)) || (x, y) == (5, 5)
+        });
+        let contours = find_contours(&island, &Progress::none()).unwrap();
+
+        let nesting: Vec<(bool, Option<usize>)> = contours
+            .iter()
+            .map(|contour| (contour.is_hole, contour.parent))
+            .collect();
+        assert_eq!(
+            nesting,
+            vec![(false, None), (true, Some(0)), (false, Some(1))]
+        );
+        assert_eq!(contours[2].points, vec![[5.5, 5.5]]);
+    }
+
+    #[test]
+    fn single_pixel_is_a_contour_of_one_point() {
+        let contours =
+            find_contours(&image(5, 4, |x, y| (x, y) == (3, 2)), &Progress::none()).unwrap();
+
+        assert_eq!(
+            contours,
+            vec![Contour {
+                points: vec![[3.5, 2.5]],
+                is_hole: false,
+                parent: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn hu_moments_do_not_change_under_rotation_and_scale() {
+        let shape = [
+            [0.0, 0.0],
+            [6.0, 0.0],
+            [6.0, 2.0],
+            [2.0, 2.0],
+            [2.0, 7.0],
+            [0.0, 7.0],
+        ];
+        let (sin, cos) = 0.6f32.sin_cos();
+        let transformed: Vec<[f32; 2]> = shape
+            .iter()
+            .map(|[x, y]| {
+                [
+                    10.0 + 2.5 * (cos * x - sin * y),
+                    -4.0 + 2.5 * (sin * x + cos * y),
+                ]
+            })
+            .collect();
+
+        let expected = Descriptors::of(&shape).hu_moments;
+        let actual = Descriptors::of(&transformed).hu_moments;
+        for (expected, actual) in expected.iter().zip(&actual) {
+            assert!(
+                (expected - actual).abs() <= 1e-4 * expected.abs().max(1e-6),
+                "{:?} is not {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use crate::app::model::planes::Planes;
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+
+/// How pixels outside of the image are made up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Border {
+    /// A fixed value, e.g. `iiii|abcdefgh|iiii`.
+    Constant(f32),
+    /// The nearest edge pixel, `aaaa|abcdefgh|hhhh`.
+    Replicate,
+    /// Mirrored without repeating the edge pixel, `edcb|abcdefgh|gfed`.
+    Reflect,
+    /// The opposite edge, `efgh|abcdefgh|abcd`.
+    Wrap,
+}
+
+impl Border {
+    /// Choice names for the modes that need no further value. `Constant` uses 0.
+    pub const NAMES: [&'static str; 4] = ["constant", "replicate", "reflect", "wrap"];
+
+    pub fn from_choice(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Border::Constant(0.0)),
+            1 => Some(Border::Replicate),
+            2 => Some(Border::Reflect),
+            3 => Some(Border::Wrap),
+            _ => None,
+        }
+    }
+
+    /// Maps the coordinate `i` to a coordinate inside `0..n`, or `None` for a constant border.
+    fn index(&self, i: i64, n: i64) -> Option<usize> {
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            Border::Constant(_) => None,
+            Border::Replicate => Some(i.clamp(0, n - 1) as usize),
+            Border::Reflect => {
+                if n == 1 {
+                    return Some(0);
+                }
+                let period = 2 * n - 2;
+                let i = i.rem_euclid(period);
+                Some(if i < n { i } else { period - i } as usize)
+            }
+            Border::Wrap => Some(i.rem_euclid(n) as usize),
+        }
+    }
+}
+
+/// A correlation kernel with odd width and height, anchored at its center.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kernel {
+    Full {
+        width: u32,
+        height: u32,
+        values: Vec<f32>,
+    },
+    /// The outer product of a horizontal and a vertical kernel, applied in two 1D passes.
+    Separable {
+        horizontal: Vec<f32>,
+        vertical: Vec<f32>,
+    },
+}
+
+impl Kernel {
+    /// Normalized Gaussian with a radius of three standard deviations.
+    pub fn gaussian(sigma: f32) -> Self {
+        let weights = gaussian_weights(sigma);
+        Kernel::Separable {
+            horizontal: weights.clone(),
+            vertical: weights,
+        }
+    }
+
+    /// Mean of a `size` x `size` neighbourhood.
+    pub fn box_filter(size: u32) -> Self {
+        let weights = vec![1.0 / size as f32; size as usize];
+        Kernel::Separable {
+            horizontal: weights.clone(),
+            vertical: weights,
+        }
+    }
+
+    /// Rotates the kernel by 180°, which turns a correlation into a convolution.
+    pub fn flipped(&self) -> Self {
+        match self {
+            Kernel::Full {
+                width,
+                height,
+                values,
+            } => Kernel::Full {
+                width: *width,
+                height: *height,
+                values: values.iter().rev().copied().collect(),
+            },
+            Kernel::Separable {
+                horizontal,
+                vertical,
+            } => Kernel::Separable {
+                horizontal: horizontal.iter().rev().copied().collect(),
+                vertical: vertical.iter().rev().copied().collect(),
+            },
+        }
+    }
+
+    /// Number of 1D or 2D passes `correlate_plane` makes.
+    fn passes(&self) -> usize {
+        match self {
+            Kernel::Full { .. } => 1,
+            Kernel::Separable { .. } => 2,
+        }
+    }
+}
+
+pub fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(0.01);
+    let radius = (3.0 * sigma).ceil() as i32;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / sum).collect()
+}
+
+/// Correlates every colour channel of `image` with `kernel`. Alpha is left unchanged, so that
+/// kernels which do not sum up to one (e.g. edge detectors) keep the image visible.
+/// The result has the pixel type of `image`.
+pub fn correlate(
+    image: &DynamicImage,
+    kernel: &Kernel,
+    border: Border,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+
+    let total_rows = (color_planes.len() * kernel.passes()) as u32 * planes.height;
+    let mut rows_done = 0;
+    let mut row_done = || {
+        rows_done += 1;
+        progress.row_done(rows_done - 1, total_rows)
+    };
+
+    for index in color_planes {
+        planes.planes[index] = correlate_plane(
+            &planes.planes[index],
+            planes.width,
+            planes.height,
+            kernel,
+            border,
+            &mut row_done,
+        )?;
+    }
+
+    Some(planes.into_image())
+}
+
+/// Correlates a single plane. `row_done` is called after every output row of every pass and
+/// stops the computation when it returns `false`.
+pub fn correlate_plane(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    kernel: &Kernel,
+    border: Border,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    match kernel {
+        Kernel::Full {
+            width: kernel_width,
+            height: kernel_height,
+            values,
+        } => correlate_2d(
+            plane,
+            width,
+            height,
+            (*kernel_width, *kernel_height, values),
+            border,
+            row_done,
+        ),
+        Kernel::Separable {
+            horizontal,
+            vertical,
+        } => {
+            let plane = correlate_2d(
+                plane,
+                width,
+                height,
+                (horizontal.len() as u32, 1, horizontal),
+                border,
+                row_done,
+            )?;
+            correlate_2d(
+                &plane,
+                width,
+                height,
+                (1, vertical.len() as u32, vertical),
+                border,
+                row_done,
+            )
+        }
+    }
+}
+
+fn correlate_2d(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    (kernel_width, kernel_height, values): (u32, u32, &[f32]),
+    border: Border,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    let (width, height) = (width as i64, height as i64);
+    let (radius_x, radius_y) = (kernel_width as i64 / 2, kernel_height as i64 / 2);
+    let constant = match border {
+        Border::Constant(value) => value,
+        _ => 0.0,
+    };
+
+    // source coordinates for every position a kernel can reach, so the inner loop does not
+    // need to care about the border
+    let columns: Vec<Option<usize>> = (-radius_x..width + radius_x)
+        .map(|x| border.index(x, width))
+        .collect();
+    let rows: Vec<Option<usize>> = (-radius_y..height + radius_y)
+        .map(|y| border.index(y, height))
+        .collect();
+
+    let mut result = Vec::with_capacity((width * height) as usize);
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let mut sum = 0.0;
+            for ky in 0..kernel_height as usize {
+                let row = rows[y + ky].map(|row| row * width as usize);
+                let weights = &values[ky * kernel_width as usize..][..kernel_width as usize];
+                for (kx, weight) in weights.iter().enumerate() {
+                    let value = match (row, columns[x + kx]) {
+                        (Some(row), Some(column)) => plane[row + column],
+                        _ => constant,
+                    };
+                    sum += weight * value;
+                }
+            }
+            result.push(sum);
+        }
+        if !row_done() {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `border` applied to the 8 pixels `abcdefgh`, four on either side, as in the docs.
+    fn pattern(border: Border) -> String {
+        (-4..12)
+            .map(|i| match border.index(i, 8) {
+                Some(index) => (b'a' + index as u8) as char,
+                None => 'i',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn borders_follow_their_patterns() {
+        assert_eq!(pattern(Border::Constant(0.0)), "iiiiabcdefghiiii");
+        assert_eq!(pattern(Border::Replicate), "aaaaabcdefghhhhh");
+        assert_eq!(pattern(Border::Reflect), "edcbabcdefghgfed");
+        assert_eq!(pattern(Border::Wrap), "efghabcdefghabcd");
+    }
+
+    #[test]
+    fn borders_reach_further_than_the_image() {
+        let indices = |border: Border, n: i64| -> Vec<Option<usize>> {
+            [-7, -3, n + 2, n + 6]
+                .iter()
+                .map(|i| border.index(*i, n))
+                .collect()
+        };
+        // the reflection repeats every 2 * 3 - 2 = 4 pixels: ...abcbabcbabc...
+        assert_eq!(
+            indices(Border::Reflect, 3),
+            vec![Some(1), Some(1), Some(1), Some(1)]
+        );
+        assert_eq!(
+            indices(Border::Wrap, 3),
+            vec![Some(2), Some(0), Some(2), Some(0)]
+        );
+        assert_eq!(
+            indices(Border::Replicate, 3),
+            vec![Some(0), Some(0), Some(2), Some(2)]
+        );
+    }
+
+    #[test]
+    fn borders_of_a_single_pixel_repeat_it() {
+        for border in [Border::Replicate, Border::Reflect, Border::Wrap] {
+            for i in [-5, -1, 1, 4] {
+                assert_eq!(border.index(i, 1), Some(0), "{:?} at {}", border, i);
+            }
+        }
+    }
+
+    #[test]
+    fn separable_kernel_matches_its_full_form() {
+        let (width, height) = (7, 5);
+        let plane: Vec<f32> = (0..width * height)
+            .map(|index| ((index * 37) % 11) as f32)
+            .collect();
+        let (horizontal, vertical) = (vec![1.0, 2.0, -1.0], vec![0.5, 1.0, 2.0, 1.0, 0.5]);
+        let separable = Kernel::Separable {
+            horizontal: horizontal.clone(),
+            vertical: vertical.clone(),
+        };
+        let full = Kernel::Full {
+            width: 3,
+            height: 5,
+            values: vertical
+                .iter()
+                .flat_map(|v| horizontal.iter().map(move |h| h * v))
+                .collect(),
+        };
+
+        for border in [
+            Border::Constant(0.0),
+            Border::Replicate,
+            Border::Reflect,
+            Border::Wrap,
+        ] {
+            let correlate = |kernel: &Kernel| {
+                correlate_plane(&plane, width, height, kernel, border, &mut || true).unwrap()
+            };
+            for (a, b) in correlate(&separable).iter().zip(correlate(&full)) {
+                assert!((a - b).abs() < 1e-4, "{:?}: {} is not {}", border, a, b);
+            }
+        }
+    }
+}
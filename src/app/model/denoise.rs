@@ -0,0 +1,428 @@
+use crate::app::model::planes::{Depth, Planes};
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+
+/// Largest radius of the median for floating point images, whose neighbourhoods are sorted.
+const MAX_SORT_RADIUS: u32 = 10;
+
+/// Median of the (2 * radius + 1)^2 neighbourhood of every pixel, per colour channel. Alpha is
+/// left unchanged and pixels outside of the image repeat the nearest edge pixel.
+/// 8 bit images use sliding histograms, whose cost per pixel does not depend on the radius.
+/// 16 bit images use a sliding two level histogram, whose cost grows with the radius rather than
+/// with its square. Floating point images sort every neighbourhood, so their radius is limited
+/// to `MAX_SORT_RADIUS`.
+pub fn median(image: &DynamicImage, radius: u32, progress: &Progress) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let (width, height) = (planes.width, planes.height);
+    let depth = planes.depth();
+    let radius = if depth == Depth::F32 && radius > MAX_SORT_RADIUS {
+        progress.note(format!(
+            "the radius is limited to {} for floating point images",
+            MAX_SORT_RADIUS
+        ));
+        MAX_SORT_RADIUS
+    } else {
+        radius
+    };
+
+    let total_rows = color_planes.len() as u32 * height;
+    let mut rows_done = 0;
+    let mut row_done = || {
+        rows_done += 1;
+        progress.row_done(rows_done - 1, total_rows)
+    };
+
+    for index in color_planes {
+        let plane = &planes.planes[index];
+        planes.planes[index] = match depth {
+            Depth::U8 => median_histogram(plane, width, height, radius, &mut row_done)?,
+            Depth::U16 => median_histogram_16(plane, width, height, radius, &mut row_done)?,
+            Depth::F32 => median_sort(plane, width, height, radius, &mut row_done)?,
+        };
+    }
+
+    Some(planes.into_image())
+}
+
+/// The constant time median filter by Perreault and Hébert: one histogram per column covers the
+/// column's part of the window and moves down one row per image row, the window histogram is
+/// the sum of the column histograms and moves right one column per pixel.
+fn median_histogram(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    radius: u32,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    let (width, height, radius) = (width as i64, height as i64, radius as i64);
+    let clamp_x = |x: i64| x.clamp(0, width - 1) as usize;
+    let clamp_y = |y: i64| y.clamp(0, height - 1) as usize;
+    let bin = |x: usize, y: usize| plane[y * width as usize + x] as usize;
+
+    let mut columns = vec![[0u32; 256]; width as usize];
+    for (x, column) in columns.iter_mut().enumerate() {
+        for y in -radius..=radius {
+            column[bin(x, clamp_y(y))] += 1;
+        }
+    }
+
+    let median_rank = ((2 * radius + 1) * (2 * radius + 1) / 2) as u32;
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0..height {
+        if y > 0 {
+            let (removed, added) = (clamp_y(y - radius - 1), clamp_y(y + radius));
+            for (x, column) in columns.iter_mut().enumerate() {
+                column[bin(x, removed)] -= 1;
+                column[bin(x, added)] += 1;
+            }
+        }
+
+        let mut window = [0u32; 256];
+        for x in -radius..=radius {
+            add_histogram(&mut window, &columns[clamp_x(x)]);
+        }
+        for x in 0..width {
+            if x > 0 {
+                subtract_histogram(&mut window, &columns[clamp_x(x - radius - 1)]);
+                add_histogram(&mut window, &columns[clamp_x(x + radius)]);
+            }
+
+            let mut count = 0;
+            let median = window
+                .iter()
+                .position(|bin| {
+                    count += bin;
+                    count > median_rank
+                })
+                .unwrap_or(255);
+            result.push(median as f32);
+        }
+        if !row_done() {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+fn add_histogram(histogram: &mut [u32; 256], other: &[u32; 256]) {
+    for (bin, other) in histogram.iter_mut().zip(other) {
+        *bin += other;
+    }
+}
+
+fn subtract_histogram(histogram: &mut [u32; 256], other: &[u32; 256]) {
+    for (bin, other) in histogram.iter_mut().zip(other) {
+        *bin -= other;
+    }
+}
+
+/// Huang's sliding window with a two level histogram for 16 bit values: the window moves right
+/// one column per pixel, and the median is found in the 256 coarse bins of 256 values each, then
+/// in the 256 fine bins of that coarse bin.
+fn median_histogram_16(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    radius: u32,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    let (width, height, radius) = (width as i64, height as i64, radius as i64);
+    let clamp_x = |x: i64| x.clamp(0, width - 1) as usize;
+    let clamp_y = |y: i64| y.clamp(0, height - 1) as usize;
+    let value = |x: usize, y: usize| plane[y * width as usize + x] as usize;
+
+    let mut coarse = [0u32; 256];
+    let mut fine = vec![0u32; 1 << 16];
+    let median_rank = ((2 * radius + 1) * (2 * radius + 1) / 2) as u32;
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0..height {
+        let rows: Vec<usize> = (y - radius..=y + radius).map(clamp_y).collect();
+        coarse.fill(0);
+        fine.fill(0);
+        for x in -radius..=radius {
+            for row in &rows {
+                let added = value(clamp_x(x), *row);
+                coarse[added >> 8] += 1;
+                fine[added] += 1;
+            }
+        }
+
+        for x in 0..width {
+            if x > 0 {
+                let (removed, added) = (clamp_x(x - radius - 1), clamp_x(x + radius));
+                for row in &rows {
+                    let (removed, added) = (value(removed, *row), value(added, *row));
+                    coarse[removed >> 8] -= 1;
+                    fine[removed] -= 1;
+                    coarse[added >> 8] += 1;
+                    fine[added] += 1;
+                }
+            }
+
+            let mut count = 0;
+            let coarse_bin = coarse
+                .iter()
+                .position(|bin| {
+                    if count + bin > median_rank {
+                        return true;
+                    }
+                    count += bin;
+                    false
+                })
+                .unwrap_or(255);
+            let fine_bin = fine[coarse_bin << 8..][..256]
+                .iter()
+                .position(|bin| {
+                    count += bin;
+                    count > median_rank
+                })
+                .unwrap_or(255);
+            result.push(((coarse_bin << 8) + fine_bin) as f32);
+        }
+        if !row_done() {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+fn median_sort(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    radius: u32,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    let (width, height, radius) = (width as i64, height as i64, radius as i64);
+    let mut window = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0..height {
+        for x in 0..width {
+            window.clear();
+            for sy in y - radius..=y + radius {
+                let row = sy.clamp(0, height - 1) * width;
+                for sx in x - radius..=x + radius {
+                    window.push(plane[(row + sx.clamp(0, width - 1)) as usize]);
+                }
+            }
+            let middle = window.len() / 2;
+            let (_, median, _) = window.select_nth_unstable_by(middle, f32::total_cmp);
+            result.push(*median);
+        }
+        if !row_done() {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+/// Averages every pixel with its neighbours, weighted by their distance (`spatial_sigma` in
+/// pixels) and by their colour difference (`range_sigma` in 8 bit units, for every pixel type).
+/// Colour images use the distance between the colours, so edges in any channel are kept.
+pub fn bilateral(
+    image: &DynamicImage,
+    spatial_sigma: f32,
+    range_sigma: f32,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let (width, height) = (planes.width as i64, planes.height as i64);
+
+    let radius = (2.0 * spatial_sigma).ceil().max(1.0) as i64;
+    let spatial_weights: Vec<f32> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            (-((dx * dx + dy * dy) as f32) / (2.0 * spatial_sigma * spatial_sigma)).exp()
+        })
+        .collect();
+    let range_sigma = range_sigma.max(0.01) * planes.max_value() / u8::MAX as f32;
+    let range_factor = -1.0 / (2.0 * range_sigma * range_sigma);
+
+    let sources: Vec<&[f32]> = color_planes
+        .iter()
+        .map(|index| planes.planes[*index].as_slice())
+        .collect();
+    let mut results = vec![Vec::with_capacity((width * height) as usize); sources.len()];
+    let mut sums = vec![0.0; sources.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let center = (y * width + x) as usize;
+            sums.iter_mut().for_each(|sum| *sum = 0.0);
+            let mut weight_sum = 0.0;
+
+            let mut spatial_weights = spatial_weights.iter();
+            for sy in y - radius..=y + radius {
+                for sx in x - radius..=x + radius {
+                    let spatial_weight = spatial_weights.next().copied().unwrap_or(0.0);
+                    if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                        continue;
+                    }
+                    let index = (sy * width + sx) as usize;
+                    let distance: f32 = sources
+                        .iter()
+                        .map(|plane| (plane[index] - plane[center]).powi(2))
+                        .sum();
+                    let weight = spatial_weight * (distance * range_factor).exp();
+                    for (sum, plane) in sums.iter_mut().zip(&sources) {
+                        *sum += weight * plane[index];
+                    }
+                    weight_sum += weight;
+                }
+            }
+
+            for (result, sum) in results.iter_mut().zip(&sums) {
+                result.push(sum / weight_sum);
+            }
+        }
+        if !progress.row_done(y as u32, height as u32) {
+            return None;
+        }
+    }
+
+    for (index, result) in color_planes.into_iter().zip(results) {
+        planes.planes[index] = result;
+    }
+    Some(planes.into_image())
+}
+
+/// Non-local means denoising: every pixel becomes the weighted average of the pixels in the
+/// `search_radius` window around it, weighted by how similar the `patch_radius` patches around
+/// both pixels are. `strength` (in 8 bit units) controls how quickly the weights fall off with
+/// the mean squared difference of the patches.
+/// Patch distances are computed for one offset at a time with summed area tables, so the cost
+/// does not depend on the patch size.
+pub fn non_local_means(
+    image: &DynamicImage,
+    strength: f32,
+    patch_radius: u32,
+    search_radius: u32,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let (width, height) = (planes.width as i64, planes.height as i64);
+    let pixel_count = (width * height) as usize;
+
+    let strength = strength.max(0.01) * planes.max_value() / u8::MAX as f32;
+    let factor = -1.0 / (strength * strength);
+    let (patch_radius, search_radius) = (patch_radius as i64, search_radius as i64);
+
+    let sources: Vec<&[f32]> = color_planes
+        .iter()
+        .map(|index| planes.planes[*index].as_slice())
+        .collect();
+    let mut weight_sums = vec![0.0f32; pixel_count];
+    let mut sums = vec![vec![0.0f32; pixel_count]; sources.len()];
+    let mut integral = vec![0.0f64; ((width + 1) * (height + 1)) as usize];
+
+    let offsets = (2 * search_radius + 1).pow(2);
+    for (offset_index, (dx, dy)) in (-search_radius..=search_radius)
+        .flat_map(|dy| (-search_radius..=search_radius).map(move |dx| (dx, dy)))
+        .enumerate()
+    {
+        // summed area table of the squared differences between the image and its shifted copy,
+        // with the shifted copy repeating its edge pixels
+        let shifted = |x: i64, y: i64| {
+            ((y + dy).clamp(0, height - 1) * width + (x + dx).clamp(0, width - 1)) as usize
+        };
+        for y in 0..height {
+            let mut row_sum = 0.0;
+            for x in 0..width {
+                let (index, shifted) = ((y * width + x) as usize, shifted(x, y));
+                let difference: f32 = sources
+                    .iter()
+                    .map(|plane| (plane[index] - plane[shifted]).powi(2))
+                    .sum();
+                row_sum += difference as f64;
+                integral[((y + 1) * (width + 1) + x + 1) as usize] =
+                    integral[(y * (width + 1) + x + 1) as usize] + row_sum;
+            }
+        }
+
+        for y in 0..height {
+            let (y0, y1) = (
+                (y - patch_radius).max(0),
+                (y + patch_radius + 1).min(height),
+            );
+            for x in 0..width {
+                let (x0, x1) = ((x - patch_radius).max(0), (x + patch_radius + 1).min(width));
+                let sum = integral[(y1 * (width + 1) + x1) as usize]
+                    + integral[(y0 * (width + 1) + x0) as usize]
+                    - integral[(y0 * (width + 1) + x1) as usize]
+                    - integral[(y1 * (width + 1) + x0) as usize];
+                let count = ((x1 - x0) * (y1 - y0)) as usize * sources.len();
+                let distance = (sum / count as f64) as f32;
+
+                let weight = (distance * factor).exp();
+                let (index, shifted) = ((y * width + x) as usize, shifted(x, y));
+                weight_sums[index] += weight;
+                for (sum, plane) in sums.iter_mut().zip(&sources) {
+                    sum[index] += weight * plane[shifted];
+                }
+            }
+        }
+
+        if progress.is_cancelled() {
+            return None;
+        }
+        progress.set((offset_index + 1) as f32 / offsets as f32);
+    }
+
+    for (index, sum) in color_planes.into_iter().zip(sums) {
+        planes.planes[index] = sum
+            .iter()
+            .zip(&weight_sums)
+            .map(|(sum, weight_sum)| sum / weight_sum)
+            .collect();
+    }
+    Some(planes.into_image())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plane of `width` x `height` pseudo random values below `levels`.
+    fn noise(width: u32, height: u32, levels: u32) -> Vec<f32> {
+        let mut state = 12345u32;
+        (0..width * height)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((state >> 8) % levels) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn median_histogram_matches_sorting() {
+        let (width, height) = (23, 17);
+        let plane = noise(width, height, 256);
+        for radius in [0, 1, 4, 12] {
+            assert_eq!(
+                median_histogram(&plane, width, height, radius, &mut || true),
+                median_sort(&plane, width, height, radius, &mut || true),
+                "radius {}",
+                radius
+            );
+        }
+    }
+
+    #[test]
+    fn median_histogram_16_matches_sorting() {
+        let (width, height) = (19, 13);
+        let plane = noise(width, height, 1 << 16);
+        for radius in [0, 1, 3, 9] {
+            assert_eq!(
+                median_histogram_16(&plane, width, height, radius, &mut || true),
+                median_sort(&plane, width, height, radius, &mut || true),
+                "radius {}",
+                radius
+            );
+        }
+    }
+}
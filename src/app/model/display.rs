@@ -0,0 +1,149 @@
+use crate::app::model::planes::Planes;
+use image::{DynamicImage, RgbaImage};
+use std::sync::Arc;
+
+/// How pixel values are mapped to screen brightness. The mapping is only used for rendering,
+/// images keep their full range and precision everywhere else.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DisplayMapping {
+    /// The full value range of the pixel type, `0.0..=1.0` for floating point images.
+    #[default]
+    FullRange,
+    /// Shows `level - width / 2..=level + width / 2` as black to white. Both are fractions of
+    /// the full range.
+    Window { level: f32, width: f32 },
+    /// Stretches the values between the `clip` and `1 - clip` quantiles to black to white.
+    AutoStretch { clip: f32 },
+}
+
+impl DisplayMapping {
+    pub const DEFAULT_AUTO_STRETCH: DisplayMapping = DisplayMapping::AutoStretch { clip: 0.005 };
+}
+
+/// Renders one image with changing display mappings. The planes and the sorted samples for the
+/// quantiles are prepared when a mapping first needs them and kept, so that changing the mapping
+/// only maps the values to 8 bit again.
+pub struct Renderer {
+    image: Arc<Option<DynamicImage>>,
+    planes: Option<Planes>,
+    /// Sorted finite colour values, sampled for large images.
+    samples: Option<Vec<f32>>,
+}
+
+impl Renderer {
+    pub fn new(image: Arc<Option<DynamicImage>>) -> Self {
+        Self {
+            image,
+            planes: None,
+            samples: None,
+        }
+    }
+
+    /// Renders the image as 8 bit RGBA for display, `None` without an image. Alpha is always
+    /// shown in its full range. Also returns the values shown as black and white, as fractions
+    /// of the full range.
+    pub fn render(&mut self, mapping: DisplayMapping) -> Option<(RgbaImage, (f32, f32))> {
+        let image = (*self.image).as_ref()?;
+        if mapping == DisplayMapping::FullRange {
+            return Some((image.to_rgba8(), (0.0, 1.0)));
+        }
+
+        let planes = self.planes.get_or_insert_with(|| Planes::from_image(image));
+        let (low, high) = match mapping {
+            DisplayMapping::FullRange => (0.0, 1.0),
+            DisplayMapping::Window { level, width } => (level - width / 2.0, level + width / 2.0),
+            DisplayMapping::AutoStretch { clip } => {
+                let samples = self.samples.get_or_insert_with(|| sorted_samples(planes));
+                quantiles(samples, clip, planes.max_value())
+            }
+        };
+
+        Some((map_to_u8(planes, low, high), (low, high)))
+    }
+}
+
+/// Maps the colour values between `low` and `high`, fractions of the full range, to black to
+/// white.
+fn map_to_u8(planes: &Planes, low: f32, high: f32) -> RgbaImage {
+    let max_value = planes.max_value();
+    let scale = 255.0 / ((high - low) * max_value).max(f32::EPSILON);
+    let offset = low * max_value;
+    let to_u8 = |value: f32, scale: f32, offset: f32| {
+        ((value - offset) * scale).round().clamp(0.0, 255.0) as u8
+    };
+
+    let color_planes = planes.color_planes();
+    let alpha = planes.alpha();
+    RgbaImage::from_fn(planes.width, planes.height, |x, y| {
+        let index = (y * planes.width + x) as usize;
+        let color = |channel: usize| {
+            let plane = color_planes[channel.min(color_planes.len() - 1)];
+            to_u8(planes.planes[plane][index], scale, offset)
+        };
+        let alpha = alpha.map_or(255, |alpha| {
+            to_u8(planes.planes[alpha][index], 255.0 / max_value, 0.0)
+        });
+        image::Rgba([color(0), color(1), color(2), alpha])
+    })
+}
+
+/// The finite colour values of `planes`, sorted. Large images are sampled.
+fn sorted_samples(planes: &Planes) -> Vec<f32> {
+    const MAX_SAMPLES: usize = 1 << 18;
+
+    let color_planes = planes.color_planes();
+    let count: usize = color_planes
+        .iter()
+        .map(|index| planes.planes[*index].len())
+        .sum();
+    let step = (count / MAX_SAMPLES).max(1);
+    let mut values: Vec<f32> = color_planes
+        .iter()
+        .flat_map(|index| planes.planes[*index].iter().copied())
+        .step_by(step)
+        .filter(|value| value.is_finite())
+        .collect();
+    values.sort_unstable_by(f32::total_cmp);
+    values
+}
+
+/// The `clip` and `1 - clip` quantiles of the sorted `samples`, as fractions of the full range.
+fn quantiles(samples: &[f32], clip: f32, max_value: f32) -> (f32, f32) {
+    if samples.is_empty() {
+        return (0.0, 1.0);
+    }
+
+    let last = samples.len() - 1;
+    let clip = clip.clamp(0.0, 0.5);
+    let low = samples[(clip * last as f32).round() as usize];
+    let high = samples[((1.0 - clip) * last as f32).round() as usize];
+    if high > low {
+        (low / max_value, high / max_value)
+    } else {
+        // a flat image, keep its value visible in the middle of the range
+        (low / max_value - 0.5, low / max_value + 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+
+    #[test]
+    fn auto_stretch_maps_the_16_bit_quantiles_to_black_and_white() {
+        let image = ImageBuffer::from_fn(100, 1, |x, _| Luma([1000 + x as u16 * 10]));
+        let mut renderer = Renderer::new(Arc::new(Some(DynamicImage::ImageLuma16(image))));
+
+        for clip in [0.0, 0.1] {
+            let (rendered, (low, high)) = renderer
+                .render(DisplayMapping::AutoStretch { clip })
+                .unwrap();
+            let low_x = (clip * 99.0).round() as u32;
+            assert_eq!(low, (1000 + low_x * 10) as f32 / 65535.0);
+            assert_eq!(high, (1000 + (99 - low_x) * 10) as f32 / 65535.0);
+            assert_eq!(rendered.get_pixel(low_x, 0)[0], 0);
+            assert_eq!(rendered.get_pixel(99 - low_x, 0)[0], 255);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::app::model::color_space;
+use crate::app::model::convolution::{self, Border, Kernel};
+use crate::app::model::task::Progress;
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientOperator {
+    Sobel,
+    Scharr,
+    Prewitt,
+}
+
+impl GradientOperator {
+    /// Derivative and smoothing parts of the separable x kernel. The y kernel is the transpose.
+    fn kernels(&self) -> ([f32; 3], [f32; 3]) {
+        match self {
+            GradientOperator::Sobel => ([-1.0, 0.0, 1.0], [1.0, 2.0, 1.0]),
+            GradientOperator::Scharr => ([-1.0, 0.0, 1.0], [3.0, 10.0, 3.0]),
+            GradientOperator::Prewitt => ([-1.0, 0.0, 1.0], [1.0, 1.0, 1.0]),
+        }
+    }
+
+    /// The response to a step of height 1, used to bring the outputs into the input range.
+    fn gain(&self) -> f32 {
+        self.kernels().1.iter().sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientOutput {
+    X,
+    Y,
+    Magnitude,
+    /// Direction as hue, magnitude as brightness.
+    Orientation,
+}
+
+impl GradientOutput {
+    pub const ALL: [GradientOutput; 4] = [
+        GradientOutput::X,
+        GradientOutput::Y,
+        GradientOutput::Magnitude,
+        GradientOutput::Orientation,
+    ];
+
+    pub const NAMES: [&'static str; 4] = ["x", "y", "magnitude", "orientation"];
+}
+
+/// A grayscale image as `f32` values in `0.0..=255.0`, whatever the bit depth of the input.
+/// Colour images are converted to luma first.
+pub struct LumaPlane {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl LumaPlane {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let luma = image.to_luma32f();
+        Self {
+            width: luma.width(),
+            height: luma.height(),
+            values: luma
+                .into_raw()
+                .into_iter()
+                .map(|value| value * 255.0)
+                .collect(),
+        }
+    }
+
+    fn correlate(&self, kernel: &Kernel, row_done: &mut impl FnMut() -> bool) -> Option<Vec<f32>> {
+        convolution::correlate_plane(
+            &self.values,
+            self.width,
+            self.height,
+            kernel,
+            Border::Reflect,
+            row_done,
+        )
+    }
+
+    fn to_gray_image(&self, values: impl Iterator<Item = f32>) -> GrayImage {
+        GrayImage::from_raw(
+            self.width,
+            self.height,
+            values
+                .map(|value| value.round().clamp(0.0, 255.0) as u8)
+                .collect(),
+        )
+        .expect("values have the image size")
+    }
+}
+
+/// Horizontal and vertical derivatives, scaled so that a step of height 1 has a response of 1.
+fn gradients(
+    plane: &LumaPlane,
+    operator: GradientOperator,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<(Vec<f32>, Vec<f32>)> {
+    let (derivative, smoothing) = operator.kernels();
+    let gain = operator.gain();
+    let smoothing: Vec<f32> = smoothing.iter().map(|weight| weight / gain).collect();
+    let derivative: Vec<f32> = derivative.iter().map(|weight| weight / 2.0).collect();
+
+    let gx = plane.correlate(
+        &Kernel::Separable {
+            horizontal: derivative.clone(),
+            vertical: smoothing.clone(),
+        },
+        row_done,
+    )?;
+    let gy = plane.correlate(
+        &Kernel::Separable {
+            horizontal: smoothing,
+            vertical: derivative,
+        },
+        row_done,
+    )?;
+
+    Some((gx, gy))
+}
+
+/// Applies a gradient operator. X, y and magnitude are absolute values multiplied by `scale`.
+pub fn gradient(
+    image: &DynamicImage,
+    operator: GradientOperator,
+    output: GradientOutput,
+    scale: f32,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let plane = LumaPlane::from_image(image);
+    let total_rows = 4 * plane.height;
+    let mut rows_done = 0;
+    let mut row_done = || {
+        rows_done += 1;
+        progress.row_done(rows_done - 1, total_rows)
+    };
+    let (gx, gy) = gradients(&plane, operator, &mut row_done)?;
+
+    // the kernels measure the slope per pixel, a step from 0 to 255 spreads over two pixels
+    let scale = scale * 2.0;
+    let magnitude = |index: usize| gx[index].hypot(gy[index]);
+    let result = match output {
+        GradientOutput::X => DynamicImage::ImageLuma8(
+            plane.to_gray_image(gx.iter().map(|value| value.abs() * scale)),
+        ),
+        GradientOutput::Y => DynamicImage::ImageLuma8(
+            plane.to_gray_image(gy.iter().map(|value| value.abs() * scale)),
+        ),
+        GradientOutput::Magnitude => DynamicImage::ImageLuma8(
+            plane.to_gray_image((0..gx.len()).map(|index| magnitude(index) * scale)),
+        ),
+        GradientOutput::Orientation => {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(plane.width, plane.height, |x, y| {
+                let index = (y * plane.width + x) as usize;
+                let angle = gy[index].atan2(gx[index]).to_degrees().rem_euclid(360.0);
+                let value = (magnitude(index) * scale / 255.0).clamp(0.0, 1.0);
+                hsv_to_rgb(angle, 1.0, value)
+            }))
+        }
+    };
+
+    Some(result)
+}
+
+/// Absolute value of the Laplacian with the 4- or 8-neighbourhood kernel.
+pub fn laplacian(
+    image: &DynamicImage,
+    eight_neighbours: bool,
+    scale: f32,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let plane = LumaPlane::from_image(image);
+    let kernel = if eight_neighbours {
+        Kernel::Full {
+            width: 3,
+            height: 3,
+            values: vec![1.0, 1.0, 1.0, 1.0, -8.0, 1.0, 1.0, 1.0, 1.0],
+        }
+    } else {
+        Kernel::Full {
+            width: 3,
+            height: 3,
+            values: vec![0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0],
+        }
+    };
+    let height = plane.height;
+    let mut row = 0;
+    let laplacian = plane.correlate(&kernel, &mut || {
+        row += 1;
+        progress.row_done(row - 1, height)
+    })?;
+
+    Some(DynamicImage::ImageLuma8(plane.to_gray_image(
+        laplacian.iter().map(|value| value.abs() * scale),
+    )))
+}
+
+/// The Canny edge detector. `sigma` is the Gaussian pre-smoothing (0 disables it).
+/// The thresholds refer to the Sobel gradient magnitude of an 8 bit image, like in OpenCV:
+/// pixels above `high` are edges, pixels above `low` are edges if connected to one.
+pub fn canny(
+    image: &DynamicImage,
+    sigma: f32,
+    low: f32,
+    high: f32,
+    progress: &Progress,
+) -> Option<GrayImage> {
+    let mut plane = LumaPlane::from_image(image);
+    let (width, height) = (plane.width as usize, plane.height as usize);
+    let passes = if sigma > 0.0 { 6 } else { 4 };
+    let total_rows = passes * plane.height;
+    let mut rows_done = 0;
+    let mut row_done = || {
+        rows_done += 1;
+        progress.row_done(rows_done - 1, total_rows)
+    };
+
+    if sigma > 0.0 {
+        plane.values = plane.correlate(&Kernel::gaussian(sigma), &mut row_done)?;
+    }
+
+    // back to the unnormalized Sobel response the thresholds refer to
+    let (gx, gy) = gradients(&plane, GradientOperator::Sobel, &mut row_done)?;
+    let sobel_gain = 2.0 * GradientOperator::Sobel.gain();
+    let magnitude: Vec<f32> = gx
+        .iter()
+        .zip(&gy)
+        .map(|(gx, gy)| gx.hypot(*gy) * sobel_gain)
+        .collect();
+
+    // non-maximum suppression along the gradient direction, quantized to 45° steps
+    let mut suppressed = vec![0.0; width * height];
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let index = y * width + x;
+            let value = magnitude[index];
+            if value < low {
+                continue;
+            }
+            let angle = gy[index].atan2(gx[index]).to_degrees().rem_euclid(180.0);
+            let (dx, dy): (isize, isize) = if !(22.5..157.5).contains(&angle) {
+                (1, 0)
+            } else if angle < 67.5 {
+                (1, 1)
+            } else if angle < 112.5 {
+                (0, 1)
+            } else {
+                (-1, 1)
+            };
+            let neighbour = |sign: isize| {
+                let nx = (x as isize + sign * dx) as usize;
+                let ny = (y as isize + sign * dy) as usize;
+                magnitude[ny * width + nx]
+            };
+            // plateaus across an edge keep one side only, the tolerance stops rounding errors
+            // from alternating between both sides
+            let tolerance = 1e-4 * value;
+            if value + tolerance >= neighbour(1) && value > neighbour(-1) + tolerance {
+                suppressed[index] = value;
+            }
+        }
+        if progress.is_cancelled() {
+            return None;
+        }
+    }
+
+    // hysteresis: grow the strong edges through the weak ones
+    let mut edges = GrayImage::new(plane.width, plane.height);
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    for y in 0..height {
+        for x in 0..width {
+            if suppressed[y * width + x] >= high {
+                edges.put_pixel(x as u32, y as u32, Luma([255]));
+                queue.push_back((x, y));
+            }
+        }
+    }
+    while let Some((x, y)) = queue.pop_front() {
+        for ny in y.saturating_sub(1)..(y + 2).min(height) {
+            for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                let index = ny * width + nx;
+                if suppressed[index] >= low && edges.as_raw()[index] == 0 {
+                    edges.put_pixel(nx as u32, ny as u32, Luma([255]));
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+    progress.set(1.0);
+
+    Some(edges)
+}
+
+/// `hue` in degrees, `saturation` and `value` in `0.0..=1.0`.
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> Rgb<u8> {
+    let chroma = value * saturation;
+    let rgb = color_space::hue_to_rgb(hue, chroma, value - chroma);
+    Rgb(rgb.map(|channel| (channel * 255.0).round().clamp(0.0, 255.0) as u8))
+}
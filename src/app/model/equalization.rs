@@ -0,0 +1,199 @@
+use crate::app::model::luminance::Luminance;
+use crate::app::model::task::Progress;
+
+/// Global histogram equalization: maps the levels so that their cumulative distribution
+/// becomes (approximately) linear. Returns the new values in the range of `luma`.
+pub fn equalize(luma: &Luminance, progress: &Progress) -> Option<Vec<f32>> {
+    let lut = equalization_lut(&luma.histogram(), luma.values.len() as u64);
+
+    let width = (luma.width as usize).max(1);
+    let mut result = Vec::with_capacity(luma.values.len());
+    for (y, row) in luma.values.chunks(width).enumerate() {
+        result.extend(
+            row.iter()
+                .map(|value| lookup(&lut, luma.position(*value)) * luma.max_value()),
+        );
+        if !progress.row_done(y as u32, luma.height) {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+/// Contrast limited adaptive histogram equalization.
+/// The image is divided into `grid_size` x `grid_size` tiles that are equalized separately,
+/// after clipping each tile's histogram at `clip_limit` times the average bin count. The
+/// lookup tables of the four nearest tiles are interpolated bilinearly to avoid visible seams.
+/// Returns the new values in the range of `luma`.
+pub fn clahe(
+    luma: &Luminance,
+    grid_size: u32,
+    clip_limit: f32,
+    progress: &Progress,
+) -> Option<Vec<f32>> {
+    let (width, height) = (luma.width, luma.height);
+    if width == 0 || height == 0 {
+        return Some(luma.values.clone());
+    }
+    let tiles_x = grid_size.clamp(1, width);
+    let tiles_y = grid_size.clamp(1, height);
+    let tile_width = (width as f32 / tiles_x as f32).ceil() as u32;
+    let tile_height = (height as f32 / tiles_y as f32).ceil() as u32;
+
+    let mut luts = Vec::with_capacity((tiles_x * tiles_y) as usize);
+    for tile_y in 0..tiles_y {
+        for tile_x in 0..tiles_x {
+            let x0 = tile_x * tile_width;
+            let y0 = tile_y * tile_height;
+            let x1 = (x0 + tile_width).min(width);
+            let y1 = (y0 + tile_height).min(height);
+
+            let mut histogram = vec![0; luma.bin_count()];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    histogram[luma.bin(luma.values[(y * width + x) as usize])] += 1;
+                }
+            }
+            let count = (x1.saturating_sub(x0) * y1.saturating_sub(y0)) as u64;
+            clip_histogram(&mut histogram, clip_limit, count);
+            luts.push(equalization_lut(&histogram, count));
+        }
+        if progress.is_cancelled() {
+            return None;
+        }
+    }
+
+    let mut result = Vec::with_capacity(luma.values.len());
+    for (y, row) in luma.values.chunks(width as usize).enumerate() {
+        // position relative to the tile centers, clamped so that border pixels use the outer
+        // tiles only
+        let ty = ((y as f32 + 0.5) / tile_height as f32 - 0.5).clamp(0.0, (tiles_y - 1) as f32);
+        let ty0 = ty.floor() as u32;
+        let ty1 = (ty0 + 1).min(tiles_y - 1);
+        let fy = ty - ty0 as f32;
+
+        for (x, value) in row.iter().enumerate() {
+            let tx = ((x as f32 + 0.5) / tile_width as f32 - 0.5).clamp(0.0, (tiles_x - 1) as f32);
+            let tx0 = tx.floor() as u32;
+            let tx1 = (tx0 + 1).min(tiles_x - 1);
+            let fx = tx - tx0 as f32;
+
+            let position = luma.position(*value);
+            let lut = |tile_x: u32, tile_y: u32| {
+                lookup(&luts[(tile_y * tiles_x + tile_x) as usize], position)
+            };
+            let top = lut(tx0, ty0) * (1.0 - fx) + lut(tx1, ty0) * fx;
+            let bottom = lut(tx0, ty1) * (1.0 - fx) + lut(tx1, ty1) * fx;
+            result.push((top * (1.0 - fy) + bottom * fy) * luma.max_value());
+        }
+        if !progress.row_done(y as u32, height) {
+            return None;
+        }
+    }
+
+    Some(result)
+}
+
+/// Limits every bin to `clip_limit` times the average bin count and spreads the excess evenly
+/// over all bins.
+fn clip_histogram(histogram: &mut [u64], clip_limit: f32, count: u64) {
+    let bins = histogram.len() as u64;
+    let limit = ((clip_limit as f64 * count as f64 / bins as f64).ceil() as u64).max(1);
+
+    let mut excess = 0;
+    for bin in histogram.iter_mut() {
+        if *bin > limit {
+            excess += *bin - limit;
+            *bin = limit;
+        }
+    }
+
+    let increment = excess / bins;
+    let remainder = (excess % bins) as usize;
+    for (index, bin) in histogram.iter_mut().enumerate() {
+        *bin += increment;
+        if index < remainder {
+            *bin += 1;
+        }
+    }
+}
+
+/// Maps every bin to the fraction of the full range it is equalized to.
+fn equalization_lut(histogram: &[u64], count: u64) -> Vec<f32> {
+    let last = (histogram.len() - 1) as f32;
+    let cdf_min = histogram.iter().copied().find(|bin| *bin > 0).unwrap_or(0);
+    let range = count.saturating_sub(cdf_min);
+
+    let mut cdf = 0;
+    histogram
+        .iter()
+        .enumerate()
+        .map(|(bin, count)| {
+            cdf += count;
+            if range == 0 {
+                bin as f32 / last
+            } else {
+                (cdf.saturating_sub(cdf_min)) as f32 / range as f32
+            }
+        })
+        .collect()
+}
+
+/// Interpolates `lut` linearly between the bins, so that floating point values keep the detail
+/// within a bin. Integer values fall exactly on their bins.
+fn lookup(lut: &[f32], position: f32) -> f32 {
+    let below = (position.floor() as usize).min(lut.len() - 1);
+    let above = (below + 1).min(lut.len() - 1);
+    let fraction = position - below as f32;
+    lut[below] * (1.0 - fraction) + lut[above] * fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::model::planes::Depth;
+
+    #[test]
+    fn equalize_spreads_close_16_bit_levels_over_the_range() {
+        // all four levels share one 8 bit level
+        let luma = Luminance {
+            width: 4,
+            height: 1,
+            values: vec![1000.0, 1001.0, 1002.0, 1003.0],
+            depth: Depth::U16,
+        };
+        let result: Vec<f32> = equalize(&luma, &Progress::none())
+            .unwrap()
+            .iter()
+            .map(|value| value.round())
+            .collect();
+        assert_eq!(result, vec![0.0, 21845.0, 43690.0, 65535.0]);
+    }
+
+    #[test]
+    fn equalize_maps_8_bit_levels_by_their_cumulative_share() {
+        let luma = Luminance {
+            width: 4,
+            height: 1,
+            values: vec![10.0, 10.0, 20.0, 30.0],
+            depth: Depth::U8,
+        };
+        // the values stay unrounded, converting them back to an image rounds them to levels
+        let result = equalize(&luma, &Progress::none()).unwrap();
+        assert_eq!(result, vec![0.0, 0.0, 127.5, 255.0]);
+    }
+
+    #[test]
+    fn clahe_with_one_tile_stretches_16_bit_levels_in_order() {
+        let luma = Luminance {
+            width: 16,
+            height: 16,
+            values: (0..256).map(|value| 20000.0 + value as f32).collect(),
+            depth: Depth::U16,
+        };
+        let result = clahe(&luma, 1, 2.0, &Progress::none()).unwrap();
+        assert!(result.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(result[255] - result[0] > 255.0);
+    }
+}
@@ -0,0 +1,139 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ColorType, DynamicImage, ImageOutputFormat, ImageResult};
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Tiff,
+    WebP,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Png,
+        ExportFormat::Jpeg,
+        ExportFormat::Bmp,
+        ExportFormat::Tiff,
+        ExportFormat::WebP,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG",
+            ExportFormat::Jpeg => "JPEG",
+            ExportFormat::Bmp => "BMP",
+            ExportFormat::Tiff => "TIFF",
+            ExportFormat::WebP => "WebP (lossless)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Bmp => "bmp",
+            ExportFormat::Tiff => "tiff",
+            ExportFormat::WebP => "webp",
+        }
+    }
+
+    /// The name to save an image as: `source`, the name of the file it was loaded from, with this
+    /// format's extension, or `image` with it if the source is unknown.
+    pub fn file_name(&self, source: Option<&str>) -> String {
+        let stem = source
+            .and_then(|source| Path::new(source).file_stem())
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or("image");
+        format!("{}.{}", stem, self.extension())
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Jpeg => "image/jpeg",
+            ExportFormat::Bmp => "image/bmp",
+            ExportFormat::Tiff => "image/tiff",
+            ExportFormat::WebP => "image/webp",
+        }
+    }
+}
+
+/// Encodes `image` into `format`. Pixel types the encoder cannot store are converted to the
+/// closest supported 8 bit type first. `jpeg_quality` (1-100) is ignored for other formats.
+pub fn encode(
+    image: &DynamicImage,
+    format: ExportFormat,
+    jpeg_quality: u8,
+) -> ImageResult<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+
+    match format {
+        ExportFormat::Png => to_integer(image).write_to(&mut buffer, ImageOutputFormat::Png)?,
+        ExportFormat::Jpeg => {
+            let image = to_8bit(image, false);
+            JpegEncoder::new_with_quality(&mut buffer, jpeg_quality.clamp(1, 100))
+                .encode_image(&image)?;
+        }
+        ExportFormat::Bmp => to_8bit(image, true).write_to(&mut buffer, ImageOutputFormat::Bmp)?,
+        ExportFormat::Tiff => match image {
+            DynamicImage::ImageRgb32F(_)
+            | DynamicImage::ImageRgba32F(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageLumaA16(_) => DynamicImage::ImageRgba16(image.to_rgba16())
+                .write_to(&mut buffer, ImageOutputFormat::Tiff)?,
+            _ => image.write_to(&mut buffer, ImageOutputFormat::Tiff)?,
+        },
+        ExportFormat::WebP => {
+            let image = to_8bit(image, true);
+            WebPEncoder::new_lossless(&mut buffer).encode(
+                image.as_bytes(),
+                image.width(),
+                image.height(),
+                image.color(),
+            )?;
+        }
+    }
+
+    Ok(buffer.into_inner())
+}
+
+/// Converts floating point images to 16 bit, leaving integer images untouched.
+fn to_integer(image: &DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(image.to_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(image.to_rgba16()),
+        _ => image.clone(),
+    }
+}
+
+/// Converts to Luma8, Rgb8 or (if `keep_alpha` is set) Rgba8, leaving 8 bit images untouched.
+fn to_8bit(image: &DynamicImage, keep_alpha: bool) -> DynamicImage {
+    match image.color() {
+        ColorType::L8 | ColorType::Rgb8 => image.clone(),
+        ColorType::Rgba8 if keep_alpha => image.clone(),
+        ColorType::L16 => DynamicImage::ImageLuma8(image.to_luma8()),
+        color if keep_alpha && color.has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+        _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_swaps_the_extension_of_the_source() {
+        assert_eq!(
+            ExportFormat::Png.file_name(Some("holiday.photo.jpeg")),
+            "holiday.photo.png"
+        );
+        assert_eq!(ExportFormat::Jpeg.file_name(Some("scan")), "scan.jpg");
+        assert_eq!(ExportFormat::Tiff.file_name(None), "image.tiff");
+    }
+}
@@ -0,0 +1,484 @@
+use crate::app::model::planes::Planes;
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+use std::f32::consts::PI;
+
+/// How pixel values between the pixel centers of the source image are estimated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Bilinear,
+    /// Keys' cubic convolution with a = -0.5 (Catmull-Rom).
+    Bicubic,
+    /// Three lobed Lanczos window.
+    Lanczos,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 4] = [
+        Interpolation::Nearest,
+        Interpolation::Bilinear,
+        Interpolation::Bicubic,
+        Interpolation::Lanczos,
+    ];
+
+    pub const NAMES: [&'static str; 4] = ["nearest", "bilinear", "bicubic", "Lanczos"];
+
+    /// Radius of the filter in source pixels.
+    fn support(&self) -> f32 {
+        match self {
+            Interpolation::Nearest => 0.5,
+            Interpolation::Bilinear => 1.0,
+            Interpolation::Bicubic => 2.0,
+            Interpolation::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Interpolation::Nearest => {
+                if x < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Interpolation::Bilinear => (1.0 - x).max(0.0),
+            Interpolation::Bicubic => {
+                const A: f32 = -0.5;
+                if x < 1.0 {
+                    ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((A * x - 5.0 * A) * x + 8.0 * A) * x - 4.0 * A
+                } else {
+                    0.0
+                }
+            }
+            Interpolation::Lanczos => {
+                if x < f32::EPSILON {
+                    1.0
+                } else if x < 3.0 {
+                    let pi_x = PI * x;
+                    3.0 * pi_x.sin() * (pi_x / 3.0).sin() / (pi_x * pi_x)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// A 2x3 matrix mapping source to destination coordinates:
+/// `x' = m[0] x + m[1] y + m[2]` and `y' = m[3] x + m[4] y + m[5]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine(pub [f32; 6]);
+
+impl Affine {
+    /// Rotation by `degrees` clockwise on screen (the y axis points down) around the origin.
+    pub fn rotation(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Affine([cos, -sin, 0.0, sin, cos, 0.0])
+    }
+
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let m = &self.0;
+        [m[0] * x + m[1] * y + m[2], m[3] * x + m[4] * y + m[5]]
+    }
+
+    /// `None` if the matrix collapses the image to a line or a point.
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.0;
+        let determinant = m[0] * m[4] - m[1] * m[3];
+        if determinant.abs() < 1e-9 {
+            return None;
+        }
+        let (a, b, d, e) = (
+            m[4] / determinant,
+            -m[1] / determinant,
+            -m[3] / determinant,
+            m[0] / determinant,
+        );
+        Some(Affine([
+            a,
+            b,
+            -(a * m[2] + b * m[5]),
+            d,
+            e,
+            -(d * m[2] + e * m[5]),
+        ]))
+    }
+
+    /// Moves the result by `dx`, `dy`.
+    pub fn translated(&self, dx: f32, dy: f32) -> Self {
+        let m = &self.0;
+        Affine([m[0], m[1], m[2] + dx, m[3], m[4], m[5] + dy])
+    }
+
+    /// Adds a translation so that a `width` x `height` image lands entirely in positive
+    /// coordinates, and returns the size of the bounding box it then covers.
+    pub fn fitted(&self, width: u32, height: u32) -> (Self, u32, u32) {
+        let (width, height) = (width as f32, height as f32);
+        let corners = [[0.0, 0.0], [width, 0.0], [0.0, height], [width, height]]
+            .map(|corner| self.apply(corner));
+        let min_x = corners.iter().map(|c| c[0]).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c[0])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|c| c[1]).fold(f32::INFINITY, f32::min);
+        let max_y = corners
+            .iter()
+            .map(|c| c[1])
+            .fold(f32::NEG_INFINITY, f32::max);
+        // tolerate rounding errors, e.g. of 90° rotations
+        let size = |extent: f32| ((extent - 1e-3).ceil() as u32).max(1);
+        (
+            self.translated(-min_x, -min_y),
+            size(max_x - min_x),
+            size(max_y - min_y),
+        )
+    }
+}
+
+/// A 3x3 projective transformation in row-major order, `[x', y', w'] = H [x, y, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Homography(pub [f64; 9]);
+
+impl Homography {
+    /// The transformation mapping each of `from` to the point at the same index of `to`.
+    /// `None` if three of the points are on a line.
+    pub fn from_points(from: [[f32; 2]; 4], to: [[f32; 2]; 4]) -> Option<Self> {
+        if has_collinear(&from) || has_collinear(&to) {
+            return None;
+        }
+
+        // two equations per point pair for the eight unknowns, h[8] is fixed to 1
+        let mut rows = [[0.0f64; 9]; 8];
+        for (index, ([x, y], [u, v])) in from.iter().zip(&to).enumerate() {
+            let (x, y, u, v) = (*x as f64, *y as f64, *u as f64, *v as f64);
+            rows[2 * index] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            rows[2 * index + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+
+        // Gaussian elimination with partial pivoting
+        for column in 0..8 {
+            let pivot = (column..8)
+                .max_by(|a, b| rows[*a][column].abs().total_cmp(&rows[*b][column].abs()))?;
+            if rows[pivot][column].abs() < 1e-10 {
+                return None;
+            }
+            rows.swap(column, pivot);
+            let pivot_row = rows[column];
+            for (index, row) in rows.iter_mut().enumerate() {
+                if index != column {
+                    let factor = row[column] / pivot_row[column];
+                    for (value, pivot_value) in row.iter_mut().zip(&pivot_row).skip(column) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+
+        let mut h = [1.0; 9];
+        for (index, row) in rows.iter().enumerate() {
+            h[index] = row[8] / row[index];
+        }
+        Some(Homography(h))
+    }
+
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let h = &self.0;
+        let (x, y) = (x as f64, y as f64);
+        let w = h[6] * x + h[7] * y + h[8];
+        [
+            ((h[0] * x + h[1] * y + h[2]) / w) as f32,
+            ((h[3] * x + h[4] * y + h[5]) / w) as f32,
+        ]
+    }
+}
+
+/// Whether any three of `points` are (almost) on a line.
+fn has_collinear(points: &[[f32; 2]; 4]) -> bool {
+    [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]]
+        .iter()
+        .any(|[a, b, c]| {
+            let ([ax, ay], [bx, by], [cx, cy]) = (points[*a], points[*b], points[*c]);
+            let twice_area = ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)).abs();
+            let longest = [(ax, ay, bx, by), (bx, by, cx, cy), (cx, cy, ax, ay)]
+                .iter()
+                .map(|(x0, y0, x1, y1)| (x1 - x0).hypot(y1 - y0))
+                .fold(0.0, f32::max);
+            // the height of the triangle over its longest side is below a hundredth of a pixel
+            twice_area <= 0.01 * longest
+        })
+}
+
+/// Axis to mirror an image at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlipAxis {
+    Horizontal,
+    Vertical,
+    /// The main diagonal, i.e. transposing rows and columns.
+    Diagonal,
+}
+
+pub fn flip(image: &DynamicImage, axis: FlipAxis) -> DynamicImage {
+    match axis {
+        FlipAxis::Horizontal => image.fliph(),
+        FlipAxis::Vertical => image.flipv(),
+        FlipAxis::Diagonal => image.rotate90().fliph(),
+    }
+}
+
+/// Resamples `image` to `width` x `height`. Separable, and the filters are widened when
+/// shrinking so that every source pixel contributes.
+pub fn resize(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let source = Planes::from_image(image);
+    let (source_width, source_height) = (source.width as usize, source.height as usize);
+    let horizontal = axis_weights(source.width, width, interpolation);
+    let vertical = axis_weights(source.height, height, interpolation);
+    let total_rows = source.height + height;
+
+    // rows first, the intermediate image has the new width and the old height
+    let mut rows = Vec::with_capacity(source.planes.len());
+    for plane in &source.planes {
+        let mut resized = Vec::with_capacity(width as usize * source_height);
+        for y in 0..source_height {
+            let row = &plane[y * source_width..(y + 1) * source_width];
+            resized.extend(horizontal.iter().map(|taps| apply_taps(taps, |x| row[x])));
+        }
+        rows.push(resized);
+    }
+    progress.set(source.height as f32 / total_rows as f32);
+    if progress.is_cancelled() {
+        return None;
+    }
+
+    let mut planes = vec![Vec::with_capacity((width * height) as usize); rows.len()];
+    for (y, taps) in vertical.iter().enumerate() {
+        for (plane, rows) in planes.iter_mut().zip(&rows) {
+            for x in 0..width as usize {
+                plane.push(apply_taps(taps, |y| rows[y * width as usize + x]));
+            }
+        }
+        if !progress.row_done(source.height + y as u32, total_rows) {
+            return None;
+        }
+    }
+
+    Some(with_planes(&source, width, height, planes))
+}
+
+/// Maps `image` with `affine` onto a `width` x `height` canvas. Canvas pixels that no source
+/// pixel maps to are black, and transparent if the image has alpha.
+pub fn warp(
+    image: &DynamicImage,
+    affine: &Affine,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let inverse = affine.inverse()?;
+    resample(image, width, height, interpolation, progress, |point| {
+        inverse.apply(point)
+    })
+}
+
+/// Fills a `width` x `height` canvas through `homography`, which maps canvas coordinates to
+/// coordinates in `image`. Canvas pixels outside of `image` are handled like in `warp`.
+pub fn warp_perspective(
+    image: &DynamicImage,
+    homography: &Homography,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    resample(image, width, height, interpolation, progress, |point| {
+        homography.apply(point)
+    })
+}
+
+/// Builds a `width` x `height` image by sampling `image` at `source_position` of every pixel
+/// center.
+fn resample(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    progress: &Progress,
+    source_position: impl Fn([f32; 2]) -> [f32; 2],
+) -> Option<DynamicImage> {
+    let source = Planes::from_image(image);
+    let (source_width, source_height) = (source.width as i64, source.height as i64);
+    let support = interpolation.support();
+    let mut planes = vec![Vec::with_capacity((width * height) as usize); source.planes.len()];
+
+    let mut weights_x = Vec::new();
+    let mut weights_y = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            // pixel centers are at half integer coordinates
+            let [source_x, source_y] = source_position([x as f32 + 0.5, y as f32 + 0.5]);
+            // also catches points a homography maps to infinity
+            if !(0.0..=source.width as f32).contains(&source_x)
+                || !(0.0..=source.height as f32).contains(&source_y)
+            {
+                for plane in planes.iter_mut() {
+                    plane.push(0.0);
+                }
+                continue;
+            }
+
+            let (center_x, center_y) = (source_x - 0.5, source_y - 0.5);
+            let taps = |center: f32, size: i64, weights: &mut Vec<(usize, f32)>| {
+                weights.clear();
+                if interpolation == Interpolation::Nearest {
+                    weights.push((center.round().clamp(0.0, (size - 1) as f32) as usize, 1.0));
+                    return;
+                }
+                let first = (center - support).ceil() as i64;
+                let last = (center + support).floor() as i64;
+                for index in first..=last {
+                    let weight = interpolation.weight(center - index as f32);
+                    if weight != 0.0 {
+                        weights.push((index.clamp(0, size - 1) as usize, weight));
+                    }
+                }
+                normalize(weights);
+            };
+            taps(center_x, source_width, &mut weights_x);
+            taps(center_y, source_height, &mut weights_y);
+
+            for (plane, values) in planes.iter_mut().zip(&source.planes) {
+                let mut sum = 0.0;
+                for (row, weight_y) in &weights_y {
+                    let row = &values[row * source.width as usize..];
+                    for (column, weight_x) in &weights_x {
+                        sum += row[*column] * weight_x * weight_y;
+                    }
+                }
+                plane.push(sum);
+            }
+        }
+        if !progress.row_done(y, height) {
+            return None;
+        }
+    }
+
+    Some(with_planes(&source, width, height, planes))
+}
+
+/// Converts `planes` back to an image with the pixel type of `source`.
+fn with_planes(source: &Planes, width: u32, height: u32, planes: Vec<Vec<f32>>) -> DynamicImage {
+    Planes::from_planes(width, height, planes, source.depth())
+        .expect("the plane count of an image is a valid layout")
+        .into_image()
+}
+
+/// Source indices and weights for every destination index along one axis.
+fn axis_weights(
+    source: u32,
+    destination: u32,
+    interpolation: Interpolation,
+) -> Vec<Vec<(usize, f32)>> {
+    let scale = source as f32 / destination as f32;
+    let stretch = scale.max(1.0);
+    let support = interpolation.support() * stretch;
+    (0..destination)
+        .map(|index| {
+            let center = (index as f32 + 0.5) * scale - 0.5;
+            if interpolation == Interpolation::Nearest {
+                let nearest = ((index as f32 + 0.5) * scale).floor();
+                return vec![(nearest.clamp(0.0, source as f32 - 1.0) as usize, 1.0)];
+            }
+            let first = (center - support).ceil() as i64;
+            let last = (center + support).floor() as i64;
+            let mut weights: Vec<(usize, f32)> = (first..=last)
+                .map(|tap| {
+                    let weight = interpolation.weight((center - tap as f32) / stretch);
+                    (tap.clamp(0, source as i64 - 1) as usize, weight)
+                })
+                .filter(|(_, weight)| *weight != 0.0)
+                .collect();
+            normalize(&mut weights);
+            weights
+        })
+        .collect()
+}
+
+fn normalize(weights: &mut [(usize, f32)]) {
+    let sum: f32 = weights.iter().map(|(_, weight)| weight).sum();
+    if sum.abs() > f32::EPSILON {
+        for (_, weight) in weights.iter_mut() {
+            *weight /= sum;
+        }
+    }
+}
+
+fn apply_taps(taps: &[(usize, f32)], value: impl Fn(usize) -> f32) -> f32 {
+    taps.iter()
+        .map(|(index, weight)| value(*index) * weight)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 2], expected: [f32; 2]) {
+        assert!(
+            (actual[0] - expected[0]).abs() < 1e-3 && (actual[1] - expected[1]).abs() < 1e-3,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn homography_maps_the_points_onto_their_targets() {
+        let from = [[0.0, 0.0], [100.0, 0.0], [100.0, 80.0], [0.0, 80.0]];
+        let to = [[12.0, 5.0], [90.0, 20.0], [110.0, 95.0], [3.0, 70.0]];
+        let homography = Homography::from_points(from, to).unwrap();
+        for (from, to) in from.iter().zip(&to) {
+            assert_close(homography.apply(*from), *to);
+        }
+    }
+
+    #[test]
+    fn homography_of_collinear_points_is_none() {
+        let from = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [0.0, 5.0]];
+        let to = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        assert_eq!(Homography::from_points(from, to), None);
+    }
+
+    #[test]
+    fn inverse_undoes_the_affine() {
+        let affine = Affine::rotation(30.0).translated(7.0, -3.0);
+        let inverse = affine.inverse().unwrap();
+        for point in [[0.0, 0.0], [10.0, 0.0], [-4.0, 25.0]] {
+            assert_close(inverse.apply(affine.apply(point)), point);
+        }
+    }
+
+    #[test]
+    fn inverse_of_a_collapsing_affine_is_none() {
+        assert_eq!(Affine([1.0, 2.0, 0.0, 2.0, 4.0, 0.0]).inverse(), None);
+    }
+
+    #[test]
+    fn fitted_quarter_rotation_swaps_width_and_height() {
+        let (affine, width, height) = Affine::rotation(90.0).fitted(40, 30);
+        assert_eq!((width, height), (30, 40));
+        // the top left corner lands in the top right corner
+        assert_close(affine.apply([0.0, 0.0]), [30.0, 0.0]);
+    }
+}
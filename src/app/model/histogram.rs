@@ -0,0 +1,285 @@
+use image::{ColorType, DynamicImage, ImageBuffer, Pixel, Primitive};
+
+pub const BINS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Luma,
+    Alpha,
+}
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Red => "red",
+            Channel::Green => "green",
+            Channel::Blue => "blue",
+            Channel::Luma => "luma",
+            Channel::Alpha => "alpha",
+        }
+    }
+}
+
+/// Statistics of one channel, in the value range of the image's pixel type.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub std_dev: f32,
+    pub median: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelHistogram {
+    pub channel: Channel,
+    /// `BINS` counts covering the full value range of the pixel type.
+    pub bins: Vec<u64>,
+    pub statistics: Statistics,
+}
+
+impl ChannelHistogram {
+    /// Running sum of `bins`, i.e. the number of pixels up to and including each bin.
+    pub fn cumulative(&self) -> Vec<u64> {
+        self.bins
+            .iter()
+            .scan(0, |sum, count| {
+                *sum += count;
+                Some(*sum)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub channels: Vec<ChannelHistogram>,
+    pub pixel_count: u64,
+    /// The largest value of the pixel type, e.g. 255 for 8 bit images and 1 for float images.
+    pub max_value: f32,
+}
+
+/// The value range the histogram bins and statistics refer to.
+pub fn max_value(color_type: ColorType) -> f32 {
+    match color_type {
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => 65535.0,
+        ColorType::Rgb32F | ColorType::Rgba32F => 1.0,
+        _ => 255.0,
+    }
+}
+
+/// Computes histograms for the channels the image actually has, plus luma for colour images.
+/// Values outside of the nominal range (possible in float images) are counted in the outermost bins.
+pub fn histogram(image: &DynamicImage) -> Histogram {
+    histogram_where(image, |_| true)
+}
+
+/// Like `histogram`, but only counts the pixels where `mask` is at least one half, e.g. the
+/// pixels of a region of interest.
+pub fn masked_histogram(image: &DynamicImage, mask: &[f32]) -> Histogram {
+    histogram_where(image, |index| {
+        mask.get(index).map_or(false, |value| *value >= 0.5)
+    })
+}
+
+/// Computes the histogram of the pixels whose index in row-major order passes `include`.
+fn histogram_where(image: &DynamicImage, include: impl Fn(usize) -> bool) -> Histogram {
+    let color_type = image.color();
+    let max_value = max_value(color_type);
+
+    let mut channels = Vec::new();
+    if color_type.has_color() {
+        channels.push(Channel::Red);
+        channels.push(Channel::Green);
+        channels.push(Channel::Blue);
+    }
+    channels.push(Channel::Luma);
+    if color_type.has_alpha() {
+        channels.push(Channel::Alpha);
+    }
+
+    let mut accumulators: Vec<Accumulator> = channels.iter().map(|_| Accumulator::new()).collect();
+    let mut pixel_count = 0;
+    let mut add = |index: usize, [red, green, blue, alpha]: [f32; 4]| {
+        if !include(index) {
+            return;
+        }
+        pixel_count += 1;
+        for (accumulator, channel) in accumulators.iter_mut().zip(&channels) {
+            accumulator.add(match channel {
+                Channel::Red => red,
+                Channel::Green => green,
+                Channel::Blue => blue,
+                // same weights as `DynamicImage::to_luma8`
+                Channel::Luma => 0.2126 * red + 0.7152 * green + 0.0722 * blue,
+                Channel::Alpha => alpha,
+            });
+        }
+    };
+    match image {
+        DynamicImage::ImageLuma8(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageLumaA8(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgb8(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgba8(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageLuma16(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageLumaA16(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgb16(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgba16(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgb32F(buffer) => for_each_rgba(buffer, &mut add),
+        DynamicImage::ImageRgba32F(buffer) => for_each_rgba(buffer, &mut add),
+        _ => for_each_rgba(&image.to_rgba32f(), &mut add),
+    }
+
+    Histogram {
+        channels: channels
+            .into_iter()
+            .zip(accumulators)
+            .map(|(channel, accumulator)| accumulator.finish(channel, max_value))
+            .collect(),
+        pixel_count,
+        max_value,
+    }
+}
+
+/// Calls `f` with the index and the RGBA values, normalized to `0.0..=1.0`, of every pixel.
+/// Pixels are converted one at a time, the image is not copied.
+fn for_each_rgba<P>(buffer: &ImageBuffer<P, Vec<P::Subpixel>>, mut f: impl FnMut(usize, [f32; 4]))
+where
+    P: Pixel,
+    P::Subpixel: Into<f32>,
+{
+    let max: f32 = P::Subpixel::DEFAULT_MAX_VALUE.into();
+    for (index, pixel) in buffer.pixels().enumerate() {
+        f(index, pixel.to_rgba().0.map(|value| value.into() / max));
+    }
+}
+
+/// Bins the statistics are derived from, one per value of a 16 bit image. Float values are
+/// rounded to the nearest bin.
+const FINE_BINS: usize = 1 << 16;
+
+/// Collects the values of one channel, normalized to `0.0..=1.0`.
+struct Accumulator {
+    fine_bins: Vec<u64>,
+    min: f32,
+    max: f32,
+    sum: f64,
+    sum_of_squares: f64,
+    count: u64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            fine_bins: vec![0; FINE_BINS],
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            sum: 0.0,
+            sum_of_squares: 0.0,
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, value: f32) {
+        self.fine_bins[fine_bin(value)] += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+        self.sum_of_squares += value as f64 * value as f64;
+        self.count += 1;
+    }
+
+    fn finish(self, channel: Channel, max_value: f32) -> ChannelHistogram {
+        let mut bins = vec![0; BINS];
+        for (fine_bin, count) in self.fine_bins.iter().enumerate() {
+            bins[fine_bin * BINS / FINE_BINS] += count;
+        }
+
+        let statistics = if self.count == 0 {
+            Statistics::default()
+        } else {
+            let count = self.count as f64;
+            let mean = self.sum / count;
+            let variance = (self.sum_of_squares / count - mean * mean).max(0.0);
+
+            Statistics {
+                min: self.min * max_value,
+                max: self.max * max_value,
+                mean: mean as f32 * max_value,
+                std_dev: variance.sqrt() as f32 * max_value,
+                median: self.median().clamp(self.min, self.max) * max_value,
+            }
+        };
+
+        ChannelHistogram {
+            channel,
+            bins,
+            statistics,
+        }
+    }
+
+    /// The value in the middle of the sorted values, the upper one for an even count.
+    fn median(&self) -> f32 {
+        let middle = self.count / 2;
+        let mut below = 0;
+        for (fine_bin, count) in self.fine_bins.iter().enumerate() {
+            below += count;
+            if below > middle {
+                return fine_bin as f32 / (FINE_BINS - 1) as f32;
+            }
+        }
+        1.0
+    }
+}
+
+/// Values outside of `0.0..=1.0` (possible in float images) fall into the outermost bins.
+fn fine_bin(value: f32) -> usize {
+    (value * (FINE_BINS - 1) as f32)
+        .round()
+        .clamp(0.0, (FINE_BINS - 1) as f32) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma};
+
+    #[test]
+    fn median_of_8_bit_values_is_exact() {
+        let image = GrayImage::from_raw(5, 1, vec![3, 200, 7, 90, 11]).unwrap();
+        let histogram = histogram(&DynamicImage::ImageLuma8(image));
+
+        let luma = &histogram.channels[0];
+        assert_eq!(luma.channel, Channel::Luma);
+        assert_eq!(luma.statistics.median, 11.0);
+        assert_eq!(luma.statistics.min, 3.0);
+        assert_eq!(luma.statistics.max, 200.0);
+        assert_eq!(luma.bins[200], 1);
+        assert_eq!(luma.bins.iter().sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn median_of_16_bit_values_keeps_full_precision() {
+        let image: ImageBuffer<Luma<u16>, Vec<u16>> =
+            ImageBuffer::from_raw(4, 1, vec![1000, 1001, 1002, 60000]).unwrap();
+        let histogram = histogram(&DynamicImage::ImageLuma16(image));
+
+        let luma = &histogram.channels[0];
+        assert_eq!(histogram.max_value, 65535.0);
+        assert_eq!(luma.statistics.median, 1002.0);
+        // 1000 to 1002 share one of the 256 display bins
+        assert_eq!(luma.bins[1000 * BINS / 65536], 3);
+    }
+
+    #[test]
+    fn masked_histogram_counts_only_masked_pixels() {
+        let image = GrayImage::from_raw(4, 1, vec![10, 20, 30, 40]).unwrap();
+        let histogram = masked_histogram(&DynamicImage::ImageLuma8(image), &[0.0, 1.0, 0.7, 0.2]);
+
+        assert_eq!(histogram.pixel_count, 2);
+        assert_eq!(histogram.channels[0].statistics.mean, 25.0);
+    }
+}
@@ -0,0 +1,175 @@
+use image::DynamicImage;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+pub type Snapshot = Arc<Option<DynamicImage>>;
+
+/// Bounded undo/redo stacks of accepted images.
+///
+/// Snapshots are shared with `model::Image`, so recording a state never copies pixel data.
+/// The oldest undo entries are dropped once either `max_entries` or `max_bytes` is exceeded.
+pub struct History {
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl History {
+    pub const DEFAULT_MAX_ENTRIES: usize = 64;
+    pub const DEFAULT_MAX_BYTES: usize = 512 * 1024 * 1024;
+
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_entries,
+            max_bytes,
+        }
+    }
+
+    /// Records the state that is about to be replaced. Invalidates all redo entries.
+    pub fn push(&mut self, previous: Snapshot) {
+        self.redo_stack.clear();
+        self.undo_stack.push_back(previous);
+        self.enforce_limits();
+    }
+
+    /// Returns the state to restore and remembers `current` for `redo`.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous = self.undo_stack.pop_back()?;
+        self.redo_stack.push(current);
+        Some(previous)
+    }
+
+    /// Returns the state to restore and remembers `current` for `undo`.
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push_back(current);
+        self.enforce_limits();
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Approximate memory held by all snapshots, counting shared images once per entry.
+    pub fn size_in_bytes(&self) -> usize {
+        self.undo_stack
+            .iter()
+            .chain(self.redo_stack.iter())
+            .map(snapshot_size)
+            .sum()
+    }
+
+    fn enforce_limits(&mut self) {
+        while self.undo_stack.len() > self.max_entries
+            || (self.undo_stack.len() > 1 && self.size_in_bytes() > self.max_bytes)
+        {
+            self.undo_stack.pop_front();
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ENTRIES, Self::DEFAULT_MAX_BYTES)
+    }
+}
+
+fn snapshot_size(snapshot: &Snapshot) -> usize {
+    match &**snapshot {
+        Some(image) => image.as_bytes().len(),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    /// A grayscale snapshot of `width` x 1 pixels, so its width tells snapshots apart and is its
+    /// size in bytes.
+    fn snapshot(width: u32) -> Snapshot {
+        Arc::new(Some(DynamicImage::ImageLuma8(GrayImage::new(width, 1))))
+    }
+
+    fn width(snapshot: &Snapshot) -> u32 {
+        (**snapshot).as_ref().map_or(0, |image| image.width())
+    }
+
+    /// Undoes until the stack is empty and returns the widths of the restored snapshots.
+    fn undo_all(history: &mut History) -> Vec<u32> {
+        let mut widths = Vec::new();
+        while let Some(previous) = history.undo(snapshot(0)) {
+            widths.push(width(&previous));
+        }
+        widths
+    }
+
+    #[test]
+    fn entry_limit_drops_the_oldest_entries() {
+        let mut history = History::default();
+        for index in 1..=History::DEFAULT_MAX_ENTRIES as u32 + 6 {
+            history.push(snapshot(index));
+        }
+
+        let widths = undo_all(&mut history);
+        assert_eq!(widths.len(), History::DEFAULT_MAX_ENTRIES);
+        assert_eq!(widths.first(), Some(&70));
+        assert_eq!(widths.last(), Some(&7));
+    }
+
+    #[test]
+    fn byte_limit_drops_the_oldest_entries() {
+        assert_eq!(History::DEFAULT_MAX_BYTES, 512 * 1024 * 1024);
+
+        let mut history = History::new(History::DEFAULT_MAX_ENTRIES, 1000);
+        for width in [400, 300, 200, 350] {
+            history.push(snapshot(width));
+        }
+
+        // 300 + 200 + 350 fit, 400 more do not
+        assert_eq!(history.size_in_bytes(), 850);
+        assert_eq!(undo_all(&mut history), vec![350, 200, 300]);
+    }
+
+    #[test]
+    fn byte_limit_keeps_the_newest_entry() {
+        let mut history = History::new(History::DEFAULT_MAX_ENTRIES, 100);
+        history.push(snapshot(500));
+        assert_eq!(undo_all(&mut history), vec![500]);
+    }
+
+    #[test]
+    fn push_clears_the_redo_stack() {
+        let mut history = History::default();
+        history.push(snapshot(1));
+        history.undo(snapshot(2));
+        assert!(history.can_redo());
+
+        history.push(snapshot(3));
+        assert!(!history.can_redo());
+        assert!(history.redo(snapshot(4)).is_none());
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip_the_same_snapshots() {
+        let mut history = History::default();
+        let (previous, current) = (snapshot(1), snapshot(2));
+        history.push(Arc::clone(&previous));
+
+        let undone = history.undo(Arc::clone(&current)).unwrap();
+        assert!(Arc::ptr_eq(&undone, &previous));
+        let redone = history.redo(undone).unwrap();
+        assert!(Arc::ptr_eq(&redone, &current));
+        let undone = history.undo(redone).unwrap();
+        assert!(Arc::ptr_eq(&undone, &previous));
+    }
+}
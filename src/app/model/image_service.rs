@@ -1,89 +1,499 @@
+use crate::app::model::channels::{self, Channel, MergeLayout};
+use crate::app::model::error::LoadError;
+use crate::app::model::export::{self, ExportFormat};
+use crate::app::model::history::History;
 use crate::app::model::image::Image;
+use crate::app::model::log::{Log, Severity};
+use crate::app::model::observable::Observable;
+use crate::app::model::operation::{ActiveOperation, Operation, Registry};
 use crate::app::model::operations;
-use image::{DynamicImage, GrayImage};
+use crate::app::model::parameter::Value;
+use crate::app::model::roi::{self, Region, Roi};
+use crate::app::model::task::{Progress, Task, TaskStatus};
+use image::{DynamicImage, ImageResult};
 use rfd::FileHandle;
-use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use tokio::sync::oneshot;
 
 enum Message {
-    ImageLoaded(DynamicImage),
+    ImageLoaded(String, DynamicImage),
+    LoadFailed(LoadError),
+    OperationProgress(u64, f32),
+    OperationFinished(u64, Option<DynamicImage>, Vec<String>),
+}
+
+/// The operation that is computed in the background at the moment.
+struct RunningOperation {
+    id: u64,
+    name: &'static str,
+    cancelled: Arc<AtomicBool>,
 }
 
 pub struct ImageService {
-    message_channel: (mpsc::Sender<Message>, mpsc::Receiver<Message>), // TODO: turn into promise
+    message_channel: (mpsc::Sender<Message>, Mutex<mpsc::Receiver<Message>>), // TODO: turn into promise
     current_image: Arc<Image>,
+    /// Name of the file the current image was loaded from.
+    file_name: Mutex<Option<String>>,
     preview_image: Arc<Image>,
+    active_operation: Arc<Observable<Option<ActiveOperation>>>,
+    channels: Arc<Observable<Vec<Channel>>>,
+    history: Mutex<History>,
+    log: Arc<Log>,
+    registry: Registry,
+    roi: Arc<Roi>,
+    running_operation: Mutex<Option<RunningOperation>>,
+    next_operation_id: Mutex<u64>,
+    task: Arc<Task>,
 }
 
 impl ImageService {
     pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+
         Self {
-            message_channel: mpsc::channel(),
-            current_image: Arc::new(Image::new()),
-            preview_image: Arc::new(Image::new()),
+            message_channel: (tx, Mutex::new(rx)),
+            current_image: Arc::new(Image::default()),
+            file_name: Mutex::new(None),
+            preview_image: Arc::new(Image::default()),
+            active_operation: Arc::new(Observable::default()),
+            channels: Arc::new(Observable::default()),
+            history: Mutex::new(History::default()),
+            log: Arc::new(Log::new()),
+            registry: operations::registry(),
+            roi: Arc::new(Roi::default()),
+            running_operation: Mutex::new(None),
+            next_operation_id: Mutex::new(0),
+            task: Arc::new(Task::default()),
         }
     }
 
     pub fn update(&self) {
-        while let Ok(message) = self.message_channel.1.try_recv() {
+        let rx = self.message_channel.1.lock().unwrap();
+        while let Ok(message) = rx.try_recv() {
             match message {
-                Message::ImageLoaded(new_image) => self.reset(Some(new_image)),
+                Message::ImageLoaded(file_name, new_image) => {
+                    self.log.push(
+                        Severity::Info,
+                        format!(
+                            "opened {} ({}x{}, {:?})",
+                            file_name,
+                            new_image.width(),
+                            new_image.height(),
+                            new_image.color()
+                        ),
+                    );
+                    // the region of interest belongs to the previous image
+                    self.roi.set(None);
+                    *self.file_name.lock().unwrap() = Some(file_name);
+                    self.reset(Some(new_image));
+                }
+                Message::LoadFailed(error) => self.log.push(Severity::Error, error.to_string()),
+                Message::OperationProgress(id, fraction) => {
+                    if let Some(name) = self.running_operation_name(id) {
+                        self.task.set(Some(TaskStatus {
+                            name,
+                            progress: fraction,
+                        }));
+                    }
+                }
+                Message::OperationFinished(id, result, notes) => {
+                    if let Some(name) = self.running_operation_name(id) {
+                        self.running_operation.lock().unwrap().take();
+                        self.task.set(None);
+                        match &*self.active_operation.get() {
+                            Some(active_operation) if active_operation.notes != notes => {
+                                self.active_operation.set(Some(ActiveOperation {
+                                    notes,
+                                    ..active_operation.clone()
+                                }))
+                            }
+                            _ => {}
+                        }
+                        match result {
+                            Some(transformed) => self.preview_image.set(Some(transformed)),
+                            None => self
+                                .log
+                                .push(Severity::Error, format!("{} produced no result", name)),
+                        }
+                    }
+                }
             }
         }
-        while self.message_channel.1.try_recv().is_ok() {}
     }
 
     pub fn get_current_image(&self) -> Arc<Image> {
         Arc::clone(&self.current_image)
     }
 
+    /// Name of the file the current image was loaded from, `None` before a file was opened.
+    pub fn get_file_name(&self) -> Option<String> {
+        self.file_name.lock().unwrap().clone()
+    }
+
     pub fn get_preview_image(&self) -> Arc<Image> {
         Arc::clone(&self.preview_image)
     }
 
+    /// The channels of the last split image.
+    pub fn get_channels(&self) -> Arc<Observable<Vec<Channel>>> {
+        Arc::clone(&self.channels)
+    }
+
+    pub fn get_log(&self) -> Arc<Log> {
+        Arc::clone(&self.log)
+    }
+
+    pub fn get_roi(&self) -> Arc<Roi> {
+        Arc::clone(&self.roi)
+    }
+
+    /// Restricts operations to `region`, or lifts the restriction. The preview of the active
+    /// operation is updated.
+    pub fn set_roi(&self, region: Option<Region>) {
+        self.roi.set(region);
+        if let Some(active_operation) = &*self.active_operation.get() {
+            self.preview_operation(active_operation);
+        }
+    }
+
+    /// Crops the current image to the bounding box of the region of interest, which is then
+    /// removed because its coordinates no longer fit.
+    pub fn crop_to_roi(&self) {
+        let current_image = self.current_image.get();
+        let cropped = match (&*current_image, &*self.roi.get()) {
+            (Some(image), Some(region)) => roi::crop(image, region),
+            _ => return,
+        };
+        match cropped {
+            Some(cropped) => {
+                self.roi.set(None);
+                self.reset(Some(cropped));
+            }
+            None => self.log.push(
+                Severity::Error,
+                "the region of interest is outside of the image".to_string(),
+            ),
+        }
+    }
+
+    pub fn get_task(&self) -> Arc<Task> {
+        Arc::clone(&self.task)
+    }
+
     pub fn load_new_image(&self, file: Option<FileHandle>) {
         let tx = self.message_channel.0.clone();
         crate::app::execute(async move {
             if let Some(file) = file {
                 let data = file.read().await;
-                if let Ok(image) = image::load_from_memory(&*data) {
-                    tx.send(Message::ImageLoaded(image)).ok();
-                }
+                let message = match image::load_from_memory(&data) {
+                    Ok(image) => Message::ImageLoaded(file.file_name(), image),
+                    Err(error) => Message::LoadFailed(LoadError {
+                        file_name: file.file_name(),
+                        size: data.len(),
+                        error,
+                    }),
+                };
+                tx.send(message).ok();
+            }
+        });
+    }
+
+    /// Encodes the current image in the background. `None` without a current image.
+    pub fn encode_current_image(
+        &self,
+        format: ExportFormat,
+        jpeg_quality: u8,
+    ) -> Option<oneshot::Receiver<ImageResult<Vec<u8>>>> {
+        let current_image = self.current_image.get();
+        current_image.as_ref().as_ref()?;
+        let (tx, rx) = oneshot::channel();
+        crate::app::execute(async move {
+            if let Some(image) = &*current_image {
+                tx.send(export::encode(image, format, jpeg_quality)).ok();
             }
         });
+        Some(rx)
+    }
+
+    pub fn get_active_operation(&self) -> Arc<Observable<Option<ActiveOperation>>> {
+        Arc::clone(&self.active_operation)
+    }
+
+    pub fn get_operations(&self) -> Vec<Arc<dyn Operation>> {
+        self.registry.iter().cloned().collect()
+    }
+
+    /// Makes `name` the active operation with default parameters and previews it.
+    pub fn apply_operation(&self, name: &str) {
+        if let Some(operation) = self.registry.get(name) {
+            let active_operation = ActiveOperation::new(operation);
+            self.preview_operation(&active_operation);
+            self.active_operation.set(Some(active_operation));
+        }
     }
 
-    pub fn apply_grayscale(&self) {
-        self.preview_operation(operations::grayscale);
+    /// Changes a parameter of the active operation and re-runs the preview.
+    pub fn set_parameter(&self, name: &str, value: Value) {
+        let active_operation = match &*self.active_operation.get() {
+            Some(active_operation) => active_operation.with_value(name, value),
+            None => None,
+        };
+        if let Some(active_operation) = active_operation {
+            self.preview_operation(&active_operation);
+            self.active_operation.set(Some(active_operation));
+        }
     }
 
-    pub fn apply_invert(&self) {
-        self.preview_operation(operations::invert);
+    /// Stops the operation that is computed in the background, if any.
+    pub fn cancel_operation(&self) {
+        if self.cancel_running_operation() {
+            self.preview_image.set(None);
+        }
     }
 
     pub fn accept_operation(&self) {
+        if self.running_operation.lock().unwrap().is_some() {
+            // the preview does not match the parameters yet
+            return;
+        }
+
         let preview_image = self.preview_image.get();
         if preview_image.is_some() {
-            self.preview_image.set(None);
-            self.current_image.set_arc(preview_image);
+            if let Some(active_operation) = &*self.active_operation.get() {
+                for note in &active_operation.notes {
+                    self.log.push(
+                        Severity::Info,
+                        format!("{}: {}", active_operation.operation.name(), note),
+                    );
+                }
+            }
+            self.clear_preview();
+            self.commit(preview_image);
         }
     }
 
     pub fn discard_operation(&self) {
-        self.preview_image.set(None);
+        self.clear_preview();
     }
 
+    /// Replaces the current image. The replaced image stays reachable through `undo`.
     pub fn reset(&self, new_image: Option<DynamicImage>) {
-        self.current_image.set(new_image);
+        self.clear_preview();
+        self.commit(Arc::new(new_image));
+    }
+
+    /// Splits the current image into its channels, replacing the channels of an earlier split.
+    pub fn split_channels(&self) {
+        let current_image = self.current_image.get();
+        if let Some(image) = &*current_image {
+            let channels = channels::split(image)
+                .into_iter()
+                .map(|(name, image)| Channel {
+                    name: name.to_string(),
+                    image: Arc::new(Observable::new(Some(image))),
+                })
+                .collect();
+            self.channels.set(channels);
+        }
+    }
+
+    /// Makes the channel at `index` the current image, e.g. to edit it on its own.
+    pub fn load_channel(&self, index: usize) {
+        let image = match self.channels.get().get(index) {
+            Some(channel) => channel.image.get(),
+            None => return,
+        };
+        if image.is_some() {
+            self.clear_preview();
+            self.commit(image);
+        }
+    }
+
+    /// Replaces the channel at `index` with the luma of the current image.
+    pub fn store_channel(&self, index: usize) {
+        let current_image = self.current_image.get();
+        let image = match &*current_image {
+            Some(image) => image,
+            None => return,
+        };
+        if let Some(channel) = self.channels.get().get(index) {
+            match channels::merge(&[image], MergeLayout::Luma) {
+                Ok(stored) => channel.image.set(Some(stored)),
+                Err(error) => self.log.push(Severity::Error, error),
+            }
+        }
+    }
+
+    /// Builds a new current image from the channels at `indices`, one per channel of `layout`.
+    pub fn merge_channels(&self, indices: &[usize], layout: MergeLayout) {
+        let channels = self.channels.get();
+        let images: Vec<Arc<Option<DynamicImage>>> = indices
+            .iter()
+            .filter_map(|index| channels.get(*index))
+            .map(|channel| channel.image.get())
+            .collect();
+        let images: Vec<&DynamicImage> = images
+            .iter()
+            .filter_map(|image| image.as_ref().as_ref())
+            .collect();
+        match channels::merge(&images, layout) {
+            Ok(merged) => {
+                let names: Vec<&str> = indices
+                    .iter()
+                    .filter_map(|index| channels.get(*index))
+                    .map(|channel| channel.name.as_str())
+                    .collect();
+                self.log.push(
+                    Severity::Info,
+                    format!(
+                        "merged {} into {}",
+                        names.join(", "),
+                        MergeLayout::NAMES[layout as usize]
+                    ),
+                );
+                self.reset(Some(merged));
+            }
+            Err(error) => self.log.push(Severity::Error, error),
+        }
+    }
+
+    pub fn undo(&self) {
+        let restored = self.history.lock().unwrap().undo(self.current_image.get());
+        if let Some(restored) = restored {
+            self.clear_preview();
+            self.current_image.set_arc(restored);
+        }
+    }
+
+    pub fn redo(&self) {
+        let restored = self.history.lock().unwrap().redo(self.current_image.get());
+        if let Some(restored) = restored {
+            self.clear_preview();
+            self.current_image.set_arc(restored);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.lock().unwrap().can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.lock().unwrap().can_redo()
+    }
+
+    fn commit(&self, new_image: Arc<Option<DynamicImage>>) {
+        let previous = self.current_image.get();
+        if previous.is_some() {
+            self.history.lock().unwrap().push(previous);
+        }
+        self.current_image.set_arc(new_image);
+    }
+
+    fn clear_preview(&self) {
+        self.cancel_running_operation();
         self.preview_image.set(None);
+        if self.active_operation.get().is_some() {
+            self.active_operation.set(None);
+        }
+    }
+
+    fn running_operation_name(&self, id: u64) -> Option<&'static str> {
+        match &*self.running_operation.lock().unwrap() {
+            Some(running_operation) if running_operation.id == id => Some(running_operation.name),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if an operation was running.
+    fn cancel_running_operation(&self) -> bool {
+        match self.running_operation.lock().unwrap().take() {
+            Some(running_operation) => {
+                running_operation.cancelled.store(true, Ordering::Relaxed);
+                self.task.set(None);
+                true
+            }
+            None => false,
+        }
     }
 
-    fn preview_operation(&self, func: fn(image: &DynamicImage) -> Option<GrayImage>) {
-        let current_image = &*self.current_image.get();
-        if let Some(current_image) = current_image {
-            if let Some(transformed) = func(current_image) {
-                let transformed = DynamicImage::ImageLuma8(transformed);
-                self.preview_image.set(Some(transformed));
+    /// Starts computing the preview in the background, replacing any operation still running.
+    fn preview_operation(&self, active_operation: &ActiveOperation) {
+        self.cancel_running_operation();
+
+        let current_image = self.current_image.get();
+        match &*current_image {
+            Some(image) if active_operation.operation.input_type().accepts(image) => {}
+            Some(image) => {
+                self.log.push(
+                    Severity::Error,
+                    format!(
+                        "{} needs {} input, the image is {:?}",
+                        active_operation.operation.name(),
+                        active_operation.operation.input_type().name(),
+                        image.color()
+                    ),
+                );
+                return;
             }
+            None => return,
         }
+
+        let id = {
+            let mut next_operation_id = self.next_operation_id.lock().unwrap();
+            *next_operation_id += 1;
+            *next_operation_id
+        };
+        let name = active_operation.operation.name();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        *self.running_operation.lock().unwrap() = Some(RunningOperation {
+            id,
+            name,
+            cancelled: Arc::clone(&cancelled),
+        });
+        self.task.set(Some(TaskStatus {
+            name,
+            progress: 0.0,
+        }));
+
+        let operation = Arc::clone(&active_operation.operation);
+        let parameters = active_operation.parameters.clone();
+        let region = self.roi.get();
+        let tx = self.message_channel.0.clone();
+        // `Progress` must be `Sync`, which `mpsc::Sender` is not on our minimum Rust version
+        let progress_tx = Mutex::new(self.message_channel.0.clone());
+        let progress = Progress::new(cancelled, move |fraction| {
+            let progress_tx = progress_tx.lock().unwrap();
+            progress_tx
+                .send(Message::OperationProgress(id, fraction))
+                .ok();
+        });
+        let image = Arc::clone(&current_image);
+        crate::app::execute_operation(
+            operation,
+            parameters,
+            current_image,
+            progress,
+            move |mut result, progress| {
+                if let (Some(image), Some(processed), Some(region)) = (&*image, &result, &*region) {
+                    let mask = region.mask(image.width(), image.height());
+                    match roi::blend(image, processed, &mask) {
+                        Some(blended) => result = Some(blended),
+                        None => progress.note(
+                            "the region of interest is ignored because the size changes"
+                                .to_string(),
+                        ),
+                    }
+                }
+                if !progress.is_cancelled() {
+                    tx.send(Message::OperationFinished(
+                        id,
+                        result,
+                        progress.take_notes(),
+                    ))
+                    .ok();
+                }
+            },
+        );
     }
 }
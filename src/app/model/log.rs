@@ -0,0 +1,84 @@
+use crate::app::model::observable::Observable;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Older entries are dropped once the log holds this many.
+const MAX_ENTRIES: usize = 1000;
+
+/// The entries of a `Log`. Entries are shared, so snapshots only copy pointers.
+pub type LogEntries = VecDeque<Arc<LogEntry>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Increases with every entry, so views can tell which entries they have already seen.
+    pub id: u64,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Messages for the user, most recent last. Keeps the last `MAX_ENTRIES` messages.
+pub struct Log {
+    entries: Observable<LogEntries>,
+    next_id: Mutex<u64>,
+}
+
+impl Log {
+    pub fn new() -> Self {
+        Self {
+            entries: Observable::default(),
+            next_id: Mutex::new(0),
+        }
+    }
+
+    pub fn push(&self, severity: Severity, message: String) {
+        let mut next_id = self.next_id.lock().unwrap();
+
+        // the snapshot handed to the views stays unchanged, the new one shares its entries
+        let mut entries = (*self.entries.get()).clone();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(Arc::new(LogEntry {
+            id: *next_id,
+            severity,
+            message,
+        }));
+        *next_id += 1;
+
+        self.entries.set(entries);
+    }
+
+    /// Reports the outcome of saving `file_name` with a save dialog. `None` means the dialog was
+    /// cancelled, which is not reported.
+    pub fn report_saved(&self, file_name: &str, result: Option<io::Result<()>>) {
+        match result {
+            Some(Ok(())) => self.push(Severity::Info, format!("saved {}", file_name)),
+            Some(Err(error)) => self.push(
+                Severity::Error,
+                format!("could not save {}: {}", file_name, error),
+            ),
+            None => {}
+        }
+    }
+
+    pub fn clear(&self) {
+        let _next_id = self.next_id.lock().unwrap();
+        self.entries.set(VecDeque::new());
+    }
+
+    pub fn get(&self) -> Arc<LogEntries> {
+        self.entries.get()
+    }
+
+    pub fn get_property_changed_rx(&self) -> broadcast::Receiver<()> {
+        self.entries.get_property_changed_rx()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::app::model::planes::{Depth, Planes};
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+
+/// The luminance of an image in the value range of its pixel type, e.g. `0.0..=65535.0` for
+/// 16 bit images, so that it can be processed without losing precision.
+#[derive(Clone, Debug)]
+pub struct Luminance {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major values.
+    pub values: Vec<f32>,
+    pub depth: Depth,
+}
+
+impl Luminance {
+    /// Grayscale images are taken as they are, colour images are weighted like
+    /// `DynamicImage::to_luma8`.
+    pub fn of(image: &DynamicImage) -> Self {
+        let mut planes = Planes::from_image(image);
+        let values = match planes.color_planes().as_slice() {
+            [r, g, b] => planes.planes[*r]
+                .iter()
+                .zip(&planes.planes[*g])
+                .zip(&planes.planes[*b])
+                .map(|((r, g), b)| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+                .collect(),
+            [luma, ..] => planes.planes.swap_remove(*luma),
+            [] => Vec::new(),
+        };
+
+        Self {
+            width: planes.width,
+            height: planes.height,
+            values,
+            depth: planes.depth(),
+        }
+    }
+
+    pub fn max_value(&self) -> f32 {
+        self.depth.max_value()
+    }
+
+    /// One bin per value for 8 and 16 bit images. Floating point values are spread over as many
+    /// bins as 16 bit values.
+    pub fn bin_count(&self) -> usize {
+        match self.depth {
+            Depth::U8 => 1 << 8,
+            Depth::U16 | Depth::F32 => 1 << 16,
+        }
+    }
+
+    /// Where `value` lies between the bins, e.g. 2.5 half way between the third and fourth bin.
+    /// Values outside of the range are clamped to the outermost bins.
+    pub fn position(&self, value: f32) -> f32 {
+        let last = (self.bin_count() - 1) as f32;
+        (value / self.max_value() * last).clamp(0.0, last)
+    }
+
+    pub fn bin(&self, value: f32) -> usize {
+        self.position(value).round() as usize
+    }
+
+    /// The value at the center of `bin`.
+    pub fn bin_value(&self, bin: usize) -> f32 {
+        bin as f32 / (self.bin_count() - 1) as f32 * self.max_value()
+    }
+
+    pub fn histogram(&self) -> Vec<u64> {
+        let mut histogram = vec![0; self.bin_count()];
+        for value in &self.values {
+            histogram[self.bin(*value)] += 1;
+        }
+        histogram
+    }
+
+    /// `value` with as many decimals as the pixel type has, for notes about chosen levels.
+    pub fn format_value(&self, value: f32) -> String {
+        match self.depth {
+            Depth::U8 | Depth::U16 => format!("{:.0}", value),
+            Depth::F32 => format!("{:.4}", value),
+        }
+    }
+
+    /// A grayscale image of the values with the bit depth they came from, see
+    /// `Planes::from_planes`.
+    pub fn into_image(self) -> DynamicImage {
+        Planes::from_planes(self.width, self.height, vec![self.values], self.depth)
+            .expect("one plane is a valid layout")
+            .into_image()
+    }
+}
+
+/// Applies `f` to the luminance of `image` and keeps its chroma and alpha.
+/// Grayscale images are passed to `f` directly. Colour images are split into luma (Y) and
+/// chroma (Cb, Cr) of the BT.601 YCbCr space, and recombined with the luma `f` returns.
+/// `f` works in the value range and precision of the pixel type, the result keeps the pixel type.
+pub fn map_luminance(
+    image: &DynamicImage,
+    progress: &Progress,
+    f: impl FnOnce(&Luminance) -> Option<Vec<f32>>,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+
+    let luma: Vec<f32> = match color_planes.as_slice() {
+        [r, g, b] => planes.planes[*r]
+            .iter()
+            .zip(&planes.planes[*g])
+            .zip(&planes.planes[*b])
+            .map(|((r, g), b)| 0.299 * r + 0.587 * g + 0.114 * b)
+            .collect(),
+        _ => planes.planes[color_planes[0]].clone(),
+    };
+    let luminance = Luminance {
+        width: planes.width,
+        height: planes.height,
+        values: luma,
+        depth: planes.depth(),
+    };
+    let mapped = f(&luminance)?;
+
+    let width = (planes.width as usize).max(1);
+    for (index, (old_luma, new_luma)) in luminance.values.iter().zip(mapped).enumerate() {
+        match color_planes.as_slice() {
+            [r, g, b] => {
+                let red = new_luma + planes.planes[*r][index] - old_luma;
+                let blue = new_luma + planes.planes[*b][index] - old_luma;
+                planes.planes[*r][index] = red;
+                planes.planes[*g][index] = (new_luma - 0.299 * red - 0.114 * blue) / 0.587;
+                planes.planes[*b][index] = blue;
+            }
+            _ => planes.planes[color_planes[0]][index] = new_luma,
+        }
+
+        // `f` has already reported the progress, only check for cancellation after every row
+        if (index + 1) % width == 0 && progress.is_cancelled() {
+            return None;
+        }
+    }
+
+    Some(planes.into_image())
+}
@@ -1,6 +1,35 @@
+pub mod channels;
+pub mod color_space;
+pub mod compare;
+pub mod components;
+pub mod contours;
+pub mod convolution;
+pub mod denoise;
+pub mod display;
+pub mod edges;
+pub mod equalization;
+pub mod error;
+pub mod export;
+pub mod geometry;
+pub mod histogram;
+pub mod history;
 pub mod image;
 pub mod image_service;
+pub mod log;
+pub mod luminance;
+pub mod morphology;
+pub mod observable;
+pub mod operation;
 pub mod operations;
+pub mod parameter;
+pub mod pixel;
+pub mod planes;
+pub mod roi;
+pub mod task;
+pub mod threshold;
+pub mod viewport;
 
 pub use self::image::Image;
 pub use image_service::ImageService;
+pub use log::Log;
+pub use roi::Roi;
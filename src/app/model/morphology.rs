@@ -0,0 +1,258 @@
+use crate::app::model::parameter::Matrix;
+use crate::app::model::planes::Planes;
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Rectangle,
+    Ellipse,
+    Cross,
+    /// Drawn by the user.
+    Custom,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [
+        Shape::Rectangle,
+        Shape::Ellipse,
+        Shape::Cross,
+        Shape::Custom,
+    ];
+
+    pub const NAMES: [&'static str; 4] = ["rectangle", "ellipse", "cross", "custom"];
+}
+
+/// The neighbourhood a morphological operation looks at, with odd width and height and
+/// anchored at its center.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructuringElement {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, `true` for the cells that belong to the element.
+    pub cells: Vec<bool>,
+}
+
+impl StructuringElement {
+    /// Creates a `width` x `height` element of `shape`. Even sizes are rounded up.
+    /// `Shape::Custom` has no size of its own and yields a rectangle, use `from_mask` instead.
+    pub fn new(shape: Shape, width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(1) | 1, height.max(1) | 1);
+        let (radius_x, radius_y) = ((width / 2) as f32, (height / 2) as f32);
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (dx, dy) = (x as f32 - radius_x, y as f32 - radius_y);
+                match shape {
+                    Shape::Rectangle | Shape::Custom => true,
+                    Shape::Ellipse => {
+                        (dx / (radius_x + 0.5)).powi(2) + (dy / (radius_y + 0.5)).powi(2) <= 1.0
+                    }
+                    Shape::Cross => dx == 0.0 || dy == 0.0,
+                }
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Uses the non-zero cells of `mask`. An empty mask becomes the single center pixel, which
+    /// leaves images unchanged.
+    pub fn from_mask(mask: &Matrix) -> Self {
+        let mut cells: Vec<bool> = mask.values.iter().map(|value| *value != 0.0).collect();
+        if !cells.contains(&true) {
+            cells[(mask.height / 2 * mask.width + mask.width / 2) as usize] = true;
+        }
+
+        Self {
+            width: mask.width,
+            height: mask.height,
+            cells,
+        }
+    }
+
+    fn is_rectangle(&self) -> bool {
+        self.cells.iter().all(|cell| *cell)
+    }
+
+    /// Offsets of the cells relative to the anchor.
+    fn offsets(&self) -> Vec<(i64, i64)> {
+        let (radius_x, radius_y) = ((self.width / 2) as i64, (self.height / 2) as i64);
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell)
+            .map(|(index, _)| {
+                let x = (index as u32 % self.width) as i64;
+                let y = (index as u32 / self.width) as i64;
+                (x - radius_x, y - radius_y)
+            })
+            .collect()
+    }
+
+    /// The element rotated by 180°, as needed for dilation.
+    fn reflected(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().rev().copied().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MorphologyOperation {
+    Erode,
+    Dilate,
+    /// Erosion followed by dilation, removes small bright spots.
+    Open,
+    /// Dilation followed by erosion, fills small dark holes.
+    Close,
+    /// Dilation minus erosion, the outline of objects.
+    Gradient,
+    /// The image minus its opening, the bright details smaller than the element.
+    TopHat,
+    /// The closing minus the image, the dark details smaller than the element.
+    BlackHat,
+}
+
+impl MorphologyOperation {
+    /// Number of erosions and dilations per iteration.
+    fn passes(&self) -> u32 {
+        match self {
+            MorphologyOperation::Erode | MorphologyOperation::Dilate => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// Applies `operation` to every colour channel. Alpha is left unchanged.
+/// With `iterations` > 1 the erosions and dilations are repeated, e.g. an opening with two
+/// iterations erodes twice and then dilates twice.
+/// Pixels outside of the image are ignored, so borders neither grow nor shrink objects.
+pub fn morphology(
+    image: &DynamicImage,
+    operation: MorphologyOperation,
+    element: &StructuringElement,
+    iterations: u32,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let (width, height) = (planes.width, planes.height);
+
+    let rows_per_pass = if element.is_rectangle() { 2 } else { 1 } * height;
+    let total_rows =
+        color_planes.len() as u32 * operation.passes() * iterations.max(1) * rows_per_pass;
+    let mut rows_done = 0;
+    let mut row_done = || {
+        rows_done += 1;
+        progress.row_done(rows_done - 1, total_rows)
+    };
+
+    let reflected = element.reflected();
+    for index in color_planes {
+        let source = &planes.planes[index];
+        // erosion with the element, or dilation with its reflection, `iterations` times
+        let mut apply = |plane: &[f32], dilate: bool| {
+            let element = if dilate { &reflected } else { element };
+            let mut result = plane.to_vec();
+            for _ in 0..iterations.max(1) {
+                result = extremum(&result, width, height, element, dilate, &mut row_done)?;
+            }
+            Some(result)
+        };
+
+        let result = match operation {
+            MorphologyOperation::Erode => apply(source, false)?,
+            MorphologyOperation::Dilate => apply(source, true)?,
+            MorphologyOperation::Open => {
+                let eroded = apply(source, false)?;
+                apply(&eroded, true)?
+            }
+            MorphologyOperation::Close => {
+                let dilated = apply(source, true)?;
+                apply(&dilated, false)?
+            }
+            MorphologyOperation::Gradient => {
+                subtract(&apply(source, true)?, &apply(source, false)?)
+            }
+            MorphologyOperation::TopHat => {
+                let eroded = apply(source, false)?;
+                subtract(source, &apply(&eroded, true)?)
+            }
+            MorphologyOperation::BlackHat => {
+                let dilated = apply(source, true)?;
+                subtract(&apply(&dilated, false)?, source)
+            }
+        };
+        planes.planes[index] = result;
+    }
+
+    Some(planes.into_image())
+}
+
+fn subtract(a: &[f32], b: &[f32]) -> Vec<f32> {
+    a.iter().zip(b).map(|(a, b)| a - b).collect()
+}
+
+/// Minimum (erosion) or maximum (dilation) over the element placed at every pixel.
+/// Rectangles are split into a horizontal and a vertical pass.
+fn extremum(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    element: &StructuringElement,
+    take_max: bool,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    if element.is_rectangle() {
+        let horizontal = StructuringElement::new(Shape::Rectangle, element.width, 1);
+        let vertical = StructuringElement::new(Shape::Rectangle, 1, element.height);
+        let plane = extremum_offsets(plane, width, height, &horizontal, take_max, row_done)?;
+        extremum_offsets(&plane, width, height, &vertical, take_max, row_done)
+    } else {
+        extremum_offsets(plane, width, height, element, take_max, row_done)
+    }
+}
+
+fn extremum_offsets(
+    plane: &[f32],
+    width: u32,
+    height: u32,
+    element: &StructuringElement,
+    take_max: bool,
+    row_done: &mut impl FnMut() -> bool,
+) -> Option<Vec<f32>> {
+    let offsets = element.offsets();
+    let (width, height) = (width as i64, height as i64);
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mut value: Option<f32> = None;
+            for (dx, dy) in &offsets {
+                let (sx, sy) = (x + dx, y + dy);
+                if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                    continue;
+                }
+                let sample = plane[(sy * width + sx) as usize];
+                value = Some(match value {
+                    Some(value) if take_max => value.max(sample),
+                    Some(value) => value.min(sample),
+                    None => sample,
+                });
+            }
+            // elements without a cell inside the image keep the pixel
+            result.push(value.unwrap_or(plane[(y * width + x) as usize]));
+        }
+        if !row_done() {
+            return None;
+        }
+    }
+
+    Some(result)
+}
@@ -0,0 +1,59 @@
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// A value that can be shared between threads and notifies subscribers whenever it is replaced.
+pub struct Observable<T> {
+    data: ArcSwap<T>,
+    channel: (broadcast::Sender<()>, broadcast::Receiver<()>),
+}
+
+impl<T> Observable<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            data: ArcSwap::from(Arc::new(value)),
+            channel: broadcast::channel(32),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self) -> Arc<T> {
+        self.data.load_full()
+    }
+
+    #[allow(dead_code)]
+    pub fn set(&self, data: T) {
+        self.data.swap(Arc::new(data));
+        self.notify_property_changed();
+    }
+
+    #[allow(dead_code)]
+    pub fn set_arc(&self, data: Arc<T>) {
+        self.data.swap(data);
+        self.notify_property_changed();
+    }
+
+    #[allow(dead_code)]
+    pub fn get_property_changed_rx(&self) -> broadcast::Receiver<()> {
+        self.channel.0.subscribe()
+    }
+
+    fn notify_property_changed(&self) {
+        self.channel.0.send(()).ok();
+    }
+}
+
+impl<T: Default> Observable<T> {
+    #[allow(dead_code)]
+    pub fn take(&self) -> Arc<T> {
+        let new_data = self.data.swap(Arc::new(T::default()));
+        self.notify_property_changed();
+        new_data
+    }
+}
+
+impl<T: Default> Default for Observable<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
@@ -0,0 +1,149 @@
+use crate::app::model::parameter::{Parameter, Parameters, Value};
+use crate::app::model::task::Progress;
+use image::DynamicImage;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Color,
+    Intensity,
+    Threshold,
+    Filter,
+    Edges,
+    Morphology,
+    Geometry,
+    Analysis,
+}
+
+impl Category {
+    pub const ALL: [Category; 8] = [
+        Category::Color,
+        Category::Intensity,
+        Category::Threshold,
+        Category::Filter,
+        Category::Edges,
+        Category::Morphology,
+        Category::Geometry,
+        Category::Analysis,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Color => "Color",
+            Category::Intensity => "Intensity",
+            Category::Threshold => "Threshold",
+            Category::Filter => "Filter",
+            Category::Edges => "Edges",
+            Category::Morphology => "Morphology",
+            Category::Geometry => "Geometry",
+            Category::Analysis => "Analysis",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelType {
+    Any,
+    Luma8,
+}
+
+impl PixelType {
+    pub fn accepts(&self, image: &DynamicImage) -> bool {
+        match self {
+            PixelType::Any => true,
+            PixelType::Luma8 => image.as_luma8().is_some(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PixelType::Any => "any",
+            PixelType::Luma8 => "Luma8",
+        }
+    }
+}
+
+pub trait Operation: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn category(&self) -> Category;
+
+    fn parameters(&self) -> Vec<Parameter> {
+        Vec::new()
+    }
+
+    fn input_type(&self) -> PixelType;
+
+    fn output_type(&self) -> PixelType;
+
+    /// Runs on a background thread. Returns `None` if the operation cannot be applied to `image`
+    /// or if it was cancelled through `progress`.
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage>;
+}
+
+/// The operation currently shown in the preview, together with the parameters it was run with.
+#[derive(Clone)]
+pub struct ActiveOperation {
+    pub operation: Arc<dyn Operation>,
+    pub parameters: Parameters,
+    /// Notes the operation left while computing the current preview.
+    pub notes: Vec<String>,
+}
+
+impl ActiveOperation {
+    pub fn new(operation: Arc<dyn Operation>) -> Self {
+        let parameters = Parameters::from_defaults(&operation.parameters());
+        Self {
+            operation,
+            parameters,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Returns a copy with `name` set to `value`, or `None` if the operation has no such parameter.
+    pub fn with_value(&self, name: &str, value: Value) -> Option<Self> {
+        let parameter = self
+            .operation
+            .parameters()
+            .into_iter()
+            .find(|parameter| parameter.name == name)?;
+        let value = parameter.validate(value)?;
+
+        let mut result = self.clone();
+        result.parameters.set(parameter.name, value);
+        result.notes.clear();
+        Some(result)
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    operations: Vec<Arc<dyn Operation>>,
+}
+
+impl Registry {
+    pub fn register<T: Operation + 'static>(&mut self, operation: T) {
+        debug_assert!(
+            self.get(operation.name()).is_none(),
+            "operation {} registered twice",
+            operation.name()
+        );
+        self.operations.push(Arc::new(operation));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Operation>> {
+        self.operations
+            .iter()
+            .find(|operation| operation.name() == name)
+            .cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Operation>> {
+        self.operations.iter()
+    }
+}
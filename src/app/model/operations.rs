@@ -1,27 +1,1470 @@
-use image::{DynamicImage, GrayImage};
+use crate::app::model::color_space::{self, ColorSpace, LumaStandard};
+use crate::app::model::components::{self, Component, Connectivity};
+use crate::app::model::convolution::{self, Border, Kernel};
+use crate::app::model::denoise;
+use crate::app::model::edges::{self, GradientOperator, GradientOutput};
+use crate::app::model::equalization;
+use crate::app::model::geometry::{self, Affine, FlipAxis, Homography, Interpolation};
+use crate::app::model::luminance::{map_luminance, Luminance};
+use crate::app::model::morphology::{self, MorphologyOperation, Shape, StructuringElement};
+use crate::app::model::operation::{Category, Operation, PixelType, Registry};
+use crate::app::model::parameter::{Parameter, Parameters};
+use crate::app::model::planes::Planes;
+use crate::app::model::task::Progress;
+use crate::app::model::threshold::{self, AdaptiveMethod, ThresholdType};
+use image::DynamicImage;
 
-pub fn grayscale(image: &DynamicImage) -> Option<GrayImage> {
-    let buf_size = image.width() * image.height();
-    let mut buf = Vec::with_capacity(buf_size as usize);
-    for pixel in image.to_rgb8().pixels() {
-        let grayscale = 0.3 * pixel[0] as f32 + 0.59 * pixel[1] as f32 + 0.11 * pixel[2] as f32;
-        let v = grayscale.round() as u8;
-        buf.push(v);
+/// Builds the registry of all operations offered in the UI.
+pub fn registry() -> Registry {
+    let mut registry = Registry::default();
+    registry.register(Grayscale);
+    registry.register(ConvertColorSpace);
+    registry.register(Gradient(GradientOperator::Sobel));
+    registry.register(Gradient(GradientOperator::Scharr));
+    registry.register(Gradient(GradientOperator::Prewitt));
+    registry.register(Laplacian);
+    registry.register(Canny);
+    registry.register(Morphology(MorphologyOperation::Erode));
+    registry.register(Morphology(MorphologyOperation::Dilate));
+    registry.register(Morphology(MorphologyOperation::Open));
+    registry.register(Morphology(MorphologyOperation::Close));
+    registry.register(Morphology(MorphologyOperation::Gradient));
+    registry.register(Morphology(MorphologyOperation::TopHat));
+    registry.register(Morphology(MorphologyOperation::BlackHat));
+    registry.register(Invert);
+    registry.register(BrightnessContrast);
+    registry.register(Gamma);
+    registry.register(Equalize);
+    registry.register(Clahe);
+    registry.register(Threshold);
+    registry.register(OtsuThreshold);
+    registry.register(TriangleThreshold);
+    registry.register(AdaptiveThreshold);
+    registry.register(GaussianBlur);
+    registry.register(BoxBlur);
+    registry.register(CustomKernel);
+    registry.register(Median);
+    registry.register(Bilateral);
+    registry.register(NonLocalMeans);
+    registry.register(Resize);
+    registry.register(Rotate);
+    registry.register(Flip(FlipAxis::Horizontal));
+    registry.register(Flip(FlipAxis::Vertical));
+    registry.register(Flip(FlipAxis::Diagonal));
+    registry.register(Crop);
+    registry.register(AffineWarp);
+    registry.register(PerspectiveCorrection);
+    registry.register(ConnectedComponents);
+    registry
+}
+
+pub struct Grayscale;
+
+impl Operation for Grayscale {
+    fn name(&self) -> &'static str {
+        "grayscale"
+    }
+
+    fn category(&self) -> Category {
+        Category::Color
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::choice("standard", &LumaStandard::NAMES, 0)]
     }
 
-    GrayImage::from_vec(image.width(), image.height(), buf)
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let standard = *LumaStandard::ALL.get(parameters.get_choice("standard")?)?;
+        grayscale(image, standard, progress)
+    }
 }
 
-pub fn invert(image: &DynamicImage) -> Option<GrayImage> {
-    if let Some(image) = image.as_luma8() {
-        let mut result = image.clone();
+/// Reinterprets the colour channels, e.g. to work on the hue of an image. Converted images are
+/// stored in RGB images, the note tells how the channels are scaled.
+pub struct ConvertColorSpace;
 
-        for pixel in result.pixels_mut() {
-            pixel[0] = 255u8 - pixel[0];
-        }
+impl Operation for ConvertColorSpace {
+    fn name(&self) -> &'static str {
+        "convert color space"
+    }
+
+    fn category(&self) -> Category {
+        Category::Color
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("from", &ColorSpace::NAMES, 0),
+            Parameter::choice("to", &ColorSpace::NAMES, 1),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let from = *ColorSpace::ALL.get(parameters.get_choice("from")?)?;
+        let to = *ColorSpace::ALL.get(parameters.get_choice("to")?)?;
+        progress.note(format!("channels: {}", to.channels()));
+        color_space::convert(image, from, to, progress)
+    }
+}
+
+pub struct Invert;
+
+impl Operation for Invert {
+    fn name(&self) -> &'static str {
+        "invert"
+    }
+
+    fn category(&self) -> Category {
+        Category::Intensity
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        _parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        invert(image, progress)
+    }
+}
+
+/// Brightness is added as a fraction of full intensity, contrast stretches around mid grey.
+pub struct BrightnessContrast;
+
+impl Operation for BrightnessContrast {
+    fn name(&self) -> &'static str {
+        "brightness/contrast"
+    }
+
+    fn category(&self) -> Category {
+        Category::Intensity
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("brightness", -1.0, 1.0, 0.0),
+            Parameter::float("contrast", 0.0, 4.0, 1.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let brightness = parameters.get_float("brightness")?;
+        let contrast = parameters.get_float("contrast")?;
+        map_channels(image, progress, |value| {
+            (value - 0.5) * contrast + 0.5 + brightness
+        })
+    }
+}
+
+pub struct Gamma;
 
-        Some(result)
+impl Operation for Gamma {
+    fn name(&self) -> &'static str {
+        "gamma"
+    }
+
+    fn category(&self) -> Category {
+        Category::Intensity
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::float("gamma", 0.1, 5.0, 1.0)]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let exponent = 1.0 / parameters.get_float("gamma")?;
+        // negative values of floating point images keep their sign
+        map_channels(image, progress, |value| {
+            value.signum() * value.abs().powf(exponent)
+        })
+    }
+}
+
+/// Colour images are equalized on their luminance, see `map_luminance`.
+pub struct Equalize;
+
+impl Operation for Equalize {
+    fn name(&self) -> &'static str {
+        "histogram equalization"
+    }
+
+    fn category(&self) -> Category {
+        Category::Intensity
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        _parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        map_luminance(image, progress, |luma| {
+            equalization::equalize(luma, progress)
+        })
+    }
+}
+
+pub struct Clahe;
+
+impl Operation for Clahe {
+    fn name(&self) -> &'static str {
+        "CLAHE"
+    }
+
+    fn category(&self) -> Category {
+        Category::Intensity
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::int("grid size", 1, 32, 8),
+            Parameter::float("clip limit", 1.0, 16.0, 2.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let grid_size = parameters.get_int("grid size")? as u32;
+        let clip_limit = parameters.get_float("clip limit")?;
+        map_luminance(image, progress, |luma| {
+            equalization::clahe(luma, grid_size, clip_limit, progress)
+        })
+    }
+}
+
+/// The threshold is a fraction of the full value range, so it means the same for every pixel
+/// type. Colour images are converted to grayscale first.
+pub struct Threshold;
+
+impl Operation for Threshold {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn category(&self) -> Category {
+        Category::Threshold
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("threshold", 0.0, 1.0, 0.5),
+            Parameter::int("max value", 0, 255, 255),
+            Parameter::choice("type", &ThresholdType::NAMES, 0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    /// Luma8 masks for the binary types, the others keep the bit depth.
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let threshold_type = *ThresholdType::ALL.get(parameters.get_choice("type")?)?;
+        let luma = Luminance::of(image);
+        let value = parameters.get_float("threshold")? * luma.max_value();
+        progress.note(format!("threshold {}", luma.format_value(value)));
+        threshold::threshold(
+            &luma,
+            value,
+            parameters.get_int("max value")? as u8,
+            threshold_type,
+            progress,
+        )
+    }
+}
+
+/// Picks the threshold with Otsu's method and reports it as a note.
+pub struct OtsuThreshold;
+
+impl Operation for OtsuThreshold {
+    fn name(&self) -> &'static str {
+        "Otsu threshold"
+    }
+
+    fn category(&self) -> Category {
+        Category::Threshold
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::int("max value", 0, 255, 255),
+            Parameter::bool("inverted", false),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let luma = Luminance::of(image);
+        let bin = threshold::otsu_threshold(&luma.histogram());
+        automatic_threshold(&luma, bin, parameters, progress)
+    }
+}
+
+/// Picks the threshold with the triangle method and reports it as a note.
+pub struct TriangleThreshold;
+
+impl Operation for TriangleThreshold {
+    fn name(&self) -> &'static str {
+        "triangle threshold"
+    }
+
+    fn category(&self) -> Category {
+        Category::Threshold
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::int("max value", 0, 255, 255),
+            Parameter::bool("inverted", false),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let luma = Luminance::of(image);
+        let bin = threshold::triangle_threshold(&luma.histogram());
+        automatic_threshold(&luma, bin, parameters, progress)
+    }
+}
+
+/// Thresholds at the value of `bin` of the histogram of `luma` and reports that value.
+fn automatic_threshold(
+    luma: &Luminance,
+    bin: usize,
+    parameters: &Parameters,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let value = luma.bin_value(bin);
+    progress.note(format!("chose threshold {}", luma.format_value(value)));
+    let threshold_type = if parameters.get_bool("inverted")? {
+        ThresholdType::BinaryInverted
     } else {
-        None
+        ThresholdType::Binary
+    };
+    threshold::threshold(
+        luma,
+        value,
+        parameters.get_int("max value")? as u8,
+        threshold_type,
+        progress,
+    )
+}
+
+/// The offset is given in 8 bit levels and scaled to the range of the pixel type.
+pub struct AdaptiveThreshold;
+
+impl Operation for AdaptiveThreshold {
+    fn name(&self) -> &'static str {
+        "adaptive threshold"
+    }
+
+    fn category(&self) -> Category {
+        Category::Threshold
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("method", &AdaptiveMethod::NAMES, 0),
+            Parameter::kernel_size("block size", 3, 101, 11),
+            Parameter::float("offset", -64.0, 64.0, 2.0),
+            Parameter::int("max value", 0, 255, 255),
+            Parameter::bool("inverted", false),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let luma = Luminance::of(image);
+        let offset = parameters.get_float("offset")? / u8::MAX as f32 * luma.max_value();
+        threshold::adaptive_threshold(
+            &luma,
+            *AdaptiveMethod::ALL.get(parameters.get_choice("method")?)?,
+            parameters.get_kernel_size("block size")?,
+            offset,
+            parameters.get_int("max value")? as u8,
+            parameters.get_bool("inverted")?,
+            progress,
+        )
+        .map(DynamicImage::ImageLuma8)
+    }
+}
+
+fn border_parameter() -> Parameter {
+    Parameter::choice("border", &Border::NAMES, 2)
+}
+
+pub struct GaussianBlur;
+
+impl Operation for GaussianBlur {
+    fn name(&self) -> &'static str {
+        "gaussian blur"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("sigma", 0.1, 32.0, 2.0),
+            border_parameter(),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        convolution::correlate(
+            image,
+            &Kernel::gaussian(parameters.get_float("sigma")?),
+            Border::from_choice(parameters.get_choice("border")?)?,
+            progress,
+        )
+    }
+}
+
+pub struct BoxBlur;
+
+impl Operation for BoxBlur {
+    fn name(&self) -> &'static str {
+        "box blur"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::kernel_size("size", 3, 99, 5), border_parameter()]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        convolution::correlate(
+            image,
+            &Kernel::box_filter(parameters.get_kernel_size("size")?),
+            Border::from_choice(parameters.get_choice("border")?)?,
+            progress,
+        )
+    }
+}
+
+/// Applies a kernel entered by the user, by default a sharpening kernel.
+pub struct CustomKernel;
+
+impl Operation for CustomKernel {
+    fn name(&self) -> &'static str {
+        "custom kernel"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::matrix(
+                "kernel",
+                15,
+                3,
+                &[0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0],
+            ),
+            Parameter::bool("normalize", false),
+            Parameter::choice("mode", &["correlation", "convolution"], 0),
+            border_parameter(),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let matrix = parameters.get_matrix("kernel")?;
+        let mut values = matrix.values;
+        if parameters.get_bool("normalize")? {
+            // kernels summing up to zero (e.g. edge detectors) cannot be normalized
+            let sum: f32 = values.iter().sum();
+            if sum.abs() > f32::EPSILON {
+                values.iter_mut().for_each(|value| *value /= sum);
+            }
+        }
+
+        let kernel = Kernel::Full {
+            width: matrix.width,
+            height: matrix.height,
+            values,
+        };
+        let kernel = match parameters.get_choice("mode")? {
+            0 => kernel,
+            _ => kernel.flipped(),
+        };
+        convolution::correlate(
+            image,
+            &kernel,
+            Border::from_choice(parameters.get_choice("border")?)?,
+            progress,
+        )
+    }
+}
+
+pub struct Median;
+
+impl Operation for Median {
+    fn name(&self) -> &'static str {
+        "median"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::kernel_size("size", 3, 99, 5)]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        denoise::median(image, parameters.get_kernel_size("size")? / 2, progress)
+    }
+}
+
+pub struct Bilateral;
+
+impl Operation for Bilateral {
+    fn name(&self) -> &'static str {
+        "bilateral filter"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("spatial sigma", 0.5, 16.0, 3.0),
+            Parameter::float("range sigma", 1.0, 255.0, 30.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        denoise::bilateral(
+            image,
+            parameters.get_float("spatial sigma")?,
+            parameters.get_float("range sigma")?,
+            progress,
+        )
+    }
+}
+
+pub struct NonLocalMeans;
+
+impl Operation for NonLocalMeans {
+    fn name(&self) -> &'static str {
+        "non-local means"
+    }
+
+    fn category(&self) -> Category {
+        Category::Filter
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("strength", 1.0, 100.0, 10.0),
+            Parameter::kernel_size("patch size", 3, 15, 7),
+            Parameter::kernel_size("search size", 3, 41, 21),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        denoise::non_local_means(
+            image,
+            parameters.get_float("strength")?,
+            parameters.get_kernel_size("patch size")? / 2,
+            parameters.get_kernel_size("search size")? / 2,
+            progress,
+        )
+    }
+}
+
+/// A first derivative operator. Colour images are converted to grayscale first.
+pub struct Gradient(pub GradientOperator);
+
+impl Operation for Gradient {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            GradientOperator::Sobel => "Sobel",
+            GradientOperator::Scharr => "Scharr",
+            GradientOperator::Prewitt => "Prewitt",
+        }
+    }
+
+    fn category(&self) -> Category {
+        Category::Edges
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("output", &GradientOutput::NAMES, 2),
+            Parameter::float("scale", 0.1, 16.0, 1.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let output = *GradientOutput::ALL.get(parameters.get_choice("output")?)?;
+        edges::gradient(
+            image,
+            self.0,
+            output,
+            parameters.get_float("scale")?,
+            progress,
+        )
+    }
+}
+
+pub struct Laplacian;
+
+impl Operation for Laplacian {
+    fn name(&self) -> &'static str {
+        "Laplacian"
+    }
+
+    fn category(&self) -> Category {
+        Category::Edges
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("neighbourhood", &["4", "8"], 0),
+            Parameter::float("scale", 0.1, 16.0, 1.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        edges::laplacian(
+            image,
+            parameters.get_choice("neighbourhood")? == 1,
+            parameters.get_float("scale")?,
+            progress,
+        )
+    }
+}
+
+pub struct Canny;
+
+impl Operation for Canny {
+    fn name(&self) -> &'static str {
+        "Canny"
+    }
+
+    fn category(&self) -> Category {
+        Category::Edges
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("sigma", 0.0, 8.0, 1.4),
+            Parameter::float("low threshold", 0.0, 1000.0, 100.0),
+            Parameter::float("high threshold", 0.0, 1000.0, 200.0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let low = parameters.get_float("low threshold")?;
+        let high = parameters.get_float("high threshold")?;
+        // a low threshold above the high one would only suppress edges
+        edges::canny(
+            image,
+            parameters.get_float("sigma")?,
+            low.min(high),
+            high,
+            progress,
+        )
+        .map(DynamicImage::ImageLuma8)
+    }
+}
+
+/// A morphological operation. The size parameters apply to the predefined shapes, the
+/// "custom" shape uses the drawn element with its own size.
+pub struct Morphology(pub MorphologyOperation);
+
+impl Operation for Morphology {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            MorphologyOperation::Erode => "erode",
+            MorphologyOperation::Dilate => "dilate",
+            MorphologyOperation::Open => "open",
+            MorphologyOperation::Close => "close",
+            MorphologyOperation::Gradient => "morphological gradient",
+            MorphologyOperation::TopHat => "top-hat",
+            MorphologyOperation::BlackHat => "black-hat",
+        }
+    }
+
+    fn category(&self) -> Category {
+        Category::Morphology
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("shape", &Shape::NAMES, 0),
+            Parameter::kernel_size("width", 1, 99, 3),
+            Parameter::kernel_size("height", 1, 99, 3),
+            Parameter::mask("element", 15, 3),
+            Parameter::int("iterations", 1, 20, 1),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let element = match Shape::ALL.get(parameters.get_choice("shape")?)? {
+            Shape::Custom => StructuringElement::from_mask(&parameters.get_matrix("element")?),
+            shape => StructuringElement::new(
+                *shape,
+                parameters.get_kernel_size("width")?,
+                parameters.get_kernel_size("height")?,
+            ),
+        };
+        morphology::morphology(
+            image,
+            self.0,
+            &element,
+            parameters.get_int("iterations")? as u32,
+            progress,
+        )
+    }
+}
+
+/// Largest edge length of images created by geometric operations.
+const MAX_EDGE: u32 = 16384;
+
+fn interpolation_parameter() -> Parameter {
+    Parameter::choice("interpolation", &Interpolation::NAMES, 1)
+}
+
+/// Resizes to "width" x "height". A size of 0 follows from the other size with the aspect
+/// ratio kept, or from "scale" if both are 0.
+pub struct Resize;
+
+impl Operation for Resize {
+    fn name(&self) -> &'static str {
+        "resize"
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("scale", 0.01, 8.0, 0.5),
+            Parameter::int("width", 0, MAX_EDGE as i32, 0),
+            Parameter::int("height", 0, MAX_EDGE as i32, 0),
+            interpolation_parameter(),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let (new_width, new_height) = match (
+            parameters.get_int("width")? as f32,
+            parameters.get_int("height")? as f32,
+        ) {
+            (w, h) if w > 0.0 && h > 0.0 => (w, h),
+            (w, _) if w > 0.0 => (w, height * w / width),
+            (_, h) if h > 0.0 => (width * h / height, h),
+            _ => {
+                let scale = parameters.get_float("scale")?;
+                (width * scale, height * scale)
+            }
+        };
+        let size = |value: f32| (value.round() as u32).clamp(1, MAX_EDGE);
+        let (new_width, new_height) = (size(new_width), size(new_height));
+        progress.note(format!("{}x{}", new_width, new_height));
+        let interpolation = *Interpolation::ALL.get(parameters.get_choice("interpolation")?)?;
+        geometry::resize(image, new_width, new_height, interpolation, progress)
+    }
+}
+
+/// Rotates clockwise around the image center, optionally enlarging the canvas so that no
+/// corners are cut off.
+pub struct Rotate;
+
+impl Operation for Rotate {
+    fn name(&self) -> &'static str {
+        "rotate"
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("angle", -180.0, 180.0, 15.0),
+            Parameter::bool("expand canvas", true),
+            interpolation_parameter(),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let (width, height) = (image.width(), image.height());
+        let rotation = Affine::rotation(parameters.get_float("angle")?);
+        let interpolation = *Interpolation::ALL.get(parameters.get_choice("interpolation")?)?;
+        let (affine, new_width, new_height) = if parameters.get_bool("expand canvas")? {
+            rotation.fitted(width, height)
+        } else {
+            let center = [width as f32 / 2.0, height as f32 / 2.0];
+            let [x, y] = rotation.apply(center);
+            (
+                rotation.translated(center[0] - x, center[1] - y),
+                width,
+                height,
+            )
+        };
+        if new_width > MAX_EDGE || new_height > MAX_EDGE {
+            progress.note(format!("the result would be {}x{}", new_width, new_height));
+            return None;
+        }
+        geometry::warp(
+            image,
+            &affine,
+            new_width,
+            new_height,
+            interpolation,
+            progress,
+        )
+    }
+}
+
+/// Mirrors the image, or transposes it for the diagonal.
+pub struct Flip(pub FlipAxis);
+
+impl Operation for Flip {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            FlipAxis::Horizontal => "flip horizontally",
+            FlipAxis::Vertical => "flip vertically",
+            FlipAxis::Diagonal => "transpose",
+        }
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        _parameters: &Parameters,
+        _progress: &Progress,
+    ) -> Option<DynamicImage> {
+        Some(geometry::flip(image, self.0))
+    }
+}
+
+/// Cuts out a rectangle. A width or height of 0 extends it to the right or bottom edge.
+pub struct Crop;
+
+impl Operation for Crop {
+    fn name(&self) -> &'static str {
+        "crop"
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::int("x", 0, MAX_EDGE as i32, 0),
+            Parameter::int("y", 0, MAX_EDGE as i32, 0),
+            Parameter::int("width", 0, MAX_EDGE as i32, 0),
+            Parameter::int("height", 0, MAX_EDGE as i32, 0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let x = parameters.get_int("x")? as u32;
+        let y = parameters.get_int("y")? as u32;
+        if x >= image.width() || y >= image.height() {
+            progress.note(format!(
+                "the corner is outside of the {}x{} image",
+                image.width(),
+                image.height()
+            ));
+            return None;
+        }
+        let extent = |value: i32, available: u32| match value as u32 {
+            0 => available,
+            value => value.min(available),
+        };
+        let width = extent(parameters.get_int("width")?, image.width() - x);
+        let height = extent(parameters.get_int("height")?, image.height() - y);
+        progress.note(format!("{}x{} at {}, {}", width, height, x, y));
+        Some(image.crop_imm(x, y, width, height))
+    }
+}
+
+/// Maps the image with a 2x3 matrix, `x' = a x + b y + c` and `y' = d x + e y + f` in pixels.
+/// With "fit canvas" the canvas is the bounding box of the result, otherwise it keeps the size
+/// of the image.
+pub struct AffineWarp;
+
+impl Operation for AffineWarp {
+    fn name(&self) -> &'static str {
+        "affine warp"
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::float("a", -8.0, 8.0, 1.0),
+            Parameter::float("b", -8.0, 8.0, 0.2),
+            Parameter::float("c", -4096.0, 4096.0, 0.0),
+            Parameter::float("d", -8.0, 8.0, 0.0),
+            Parameter::float("e", -8.0, 8.0, 1.0),
+            Parameter::float("f", -4096.0, 4096.0, 0.0),
+            Parameter::bool("fit canvas", true),
+            interpolation_parameter(),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let mut matrix = [0.0; 6];
+        for (value, name) in matrix.iter_mut().zip(["a", "b", "c", "d", "e", "f"]) {
+            *value = parameters.get_float(name)?;
+        }
+        let affine = Affine(matrix);
+        if affine.inverse().is_none() {
+            progress.note("the matrix is not invertible".to_string());
+            return None;
+        }
+        let interpolation = *Interpolation::ALL.get(parameters.get_choice("interpolation")?)?;
+        let (affine, width, height) = if parameters.get_bool("fit canvas")? {
+            affine.fitted(image.width(), image.height())
+        } else {
+            (affine, image.width(), image.height())
+        };
+        if width > MAX_EDGE || height > MAX_EDGE {
+            progress.note(format!("the result would be {}x{}", width, height));
+            return None;
+        }
+        geometry::warp(image, &affine, width, height, interpolation, progress)
+    }
+}
+
+/// Names of the corner parameters of `PerspectiveCorrection`, clockwise from the top left.
+pub const CORNERS: [&str; 4] = ["top left", "top right", "bottom right", "bottom left"];
+
+/// Rectifies a quadrilateral, e.g. a document photographed at an angle, whose corners are
+/// picked on the current image. A width or height of 0 follows from the picked edge lengths.
+pub struct PerspectiveCorrection;
+
+impl Operation for PerspectiveCorrection {
+    fn name(&self) -> &'static str {
+        "perspective correction"
+    }
+
+    fn category(&self) -> Category {
+        Category::Geometry
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        let mut parameters: Vec<Parameter> =
+            CORNERS.iter().map(|name| Parameter::point(name)).collect();
+        parameters.push(Parameter::int("width", 0, MAX_EDGE as i32, 0));
+        parameters.push(Parameter::int("height", 0, MAX_EDGE as i32, 0));
+        parameters.push(interpolation_parameter());
+        parameters
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let picked: Vec<[f32; 2]> = CORNERS
+            .iter()
+            .filter_map(|name| parameters.get_point(name))
+            .collect();
+        let corners: [[f32; 2]; 4] = match picked.try_into() {
+            Ok(corners) => corners,
+            Err(picked) => {
+                // show the image as it is until all corners are there
+                progress.note(format!(
+                    "click the {} corner on the current image ({} of 4 picked)",
+                    CORNERS[picked.len()],
+                    picked.len()
+                ));
+                return Some(image.clone());
+            }
+        };
+
+        let distance = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+        let [top_left, top_right, bottom_right, bottom_left] = corners;
+        let size = |value: i32, a: f32, b: f32| match value {
+            0 => (a.max(b).round() as u32).clamp(1, MAX_EDGE),
+            value => value as u32,
+        };
+        let width = size(
+            parameters.get_int("width")?,
+            distance(top_left, top_right),
+            distance(bottom_left, bottom_right),
+        );
+        let height = size(
+            parameters.get_int("height")?,
+            distance(top_left, bottom_left),
+            distance(top_right, bottom_right),
+        );
+        progress.note(format!("{}x{}", width, height));
+
+        let (w, h) = (width as f32, height as f32);
+        let homography =
+            match Homography::from_points([[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]], corners) {
+                Some(homography) => homography,
+                None => {
+                    progress.note("three of the corners are on a line".to_string());
+                    return None;
+                }
+            };
+        let interpolation = *Interpolation::ALL.get(parameters.get_choice("interpolation")?)?;
+        geometry::warp_perspective(image, &homography, width, height, interpolation, progress)
+    }
+}
+
+/// Reduces the colour channels to one with `standard`, keeping the bit depth of `image`.
+pub fn grayscale(
+    image: &DynamicImage,
+    standard: LumaStandard,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let width = planes.width as usize;
+    let mut values = Vec::with_capacity(width * planes.height as usize);
+    for y in 0..planes.height as usize {
+        for index in y * width..(y + 1) * width {
+            values.push(match color_planes.as_slice() {
+                [r, g, b] => standard.luma([
+                    planes.planes[*r][index],
+                    planes.planes[*g][index],
+                    planes.planes[*b][index],
+                ]),
+                _ => planes.planes[color_planes[0]][index],
+            });
+        }
+        if !progress.row_done(y as u32, planes.height) {
+            return None;
+        }
+    }
+
+    Some(planes.luma_image(values))
+}
+
+/// Applies `f` to every colour value, leaving alpha unchanged. `f` works on values normalized to
+/// `0.0..=1.0` whatever the pixel type; results are clamped again for integer pixel types.
+pub fn map_channels(
+    image: &DynamicImage,
+    progress: &Progress,
+    f: impl Fn(f32) -> f32,
+) -> Option<DynamicImage> {
+    let mut planes = Planes::from_image(image);
+    let color_planes = planes.color_planes();
+    let max_value = planes.max_value();
+    let width = planes.width as usize;
+
+    let total_rows = color_planes.len() as u32 * planes.height;
+    for (plane_number, index) in color_planes.into_iter().enumerate() {
+        for (y, row) in planes.planes[index].chunks_mut(width.max(1)).enumerate() {
+            for value in row {
+                *value = f(*value / max_value) * max_value;
+            }
+            if !progress.row_done(plane_number as u32 * planes.height + y as u32, total_rows) {
+                return None;
+            }
+        }
+    }
+
+    Some(planes.into_image())
+}
+
+pub fn invert(image: &DynamicImage, progress: &Progress) -> Option<DynamicImage> {
+    map_channels(image, progress, |value| 1.0 - value)
+}
+
+/// Colours the components of the non-zero pixels, leaving out those smaller than "min area" or
+/// larger than "max area". A "max area" of 0 is no limit.
+pub struct ConnectedComponents;
+
+impl Operation for ConnectedComponents {
+    fn name(&self) -> &'static str {
+        "connected components"
+    }
+
+    fn category(&self) -> Category {
+        Category::Analysis
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::choice("connectivity", &Connectivity::NAMES, 1),
+            Parameter::int("min area", 1, 10000, 1),
+            Parameter::int("max area", 0, 1_000_000, 0),
+        ]
+    }
+
+    fn input_type(&self) -> PixelType {
+        PixelType::Luma8
+    }
+
+    fn output_type(&self) -> PixelType {
+        PixelType::Any
+    }
+
+    fn apply(
+        &self,
+        image: &DynamicImage,
+        parameters: &Parameters,
+        progress: &Progress,
+    ) -> Option<DynamicImage> {
+        let connectivity = *Connectivity::ALL.get(parameters.get_choice("connectivity")?)?;
+        let min_area = parameters.get_int("min area")? as u32;
+        let max_area = parameters.get_int("max area")? as u32;
+        let visible = |component: &Component| {
+            component.area >= min_area && (max_area == 0 || component.area <= max_area)
+        };
+        let labeling = components::label(image.as_luma8()?, connectivity, progress)?;
+        let shown = labeling
+            .components
+            .iter()
+            .filter(|component| visible(component))
+            .count();
+        progress.note(format!(
+            "{} of {} components",
+            shown,
+            labeling.components.len()
+        ));
+        Some(DynamicImage::ImageRgb8(components::render(
+            &labeling, visible,
+        )))
     }
 }
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum ParameterKind {
+    Bool {
+        default: bool,
+    },
+    Int {
+        min: i32,
+        max: i32,
+        default: i32,
+    },
+    Float {
+        min: f32,
+        max: f32,
+        default: f32,
+    },
+    /// One of a fixed set of named options, stored as the index into `options`.
+    Choice {
+        options: &'static [&'static str],
+        default: usize,
+    },
+    /// An odd kernel edge length, e.g. 3 for a 3x3 neighbourhood.
+    KernelSize {
+        min: u32,
+        max: u32,
+        default: u32,
+    },
+    /// A matrix of weights with odd width and height of at most `max_size`.
+    /// `default` holds `default_size`^2 values in row-major order.
+    Matrix {
+        max_size: u32,
+        default_size: u32,
+        default: &'static [f32],
+    },
+    /// A drawable on/off mask, stored as a `Matrix` of 0 and 1 with the same size limits as
+    /// `Matrix`. All cells are set by default.
+    Mask {
+        max_size: u32,
+        default_size: u32,
+    },
+    /// A position in image pixel coordinates, picked on the current image. Unset by default.
+    Point,
+}
+
+/// The value of a `ParameterKind::Matrix`, in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl Matrix {
+    /// Changes the size, keeping the values that are still inside and filling new cells with 0.
+    /// The matrix stays centered, so odd sizes grow and shrink on all sides.
+    pub fn resized(&self, width: u32, height: u32) -> Self {
+        let offset_x = (width as i64 - self.width as i64) / 2;
+        let offset_y = (height as i64 - self.height as i64) / 2;
+        let mut values = vec![0.0; (width * height) as usize];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let (source_x, source_y) = (x - offset_x, y - offset_y);
+                if (0..self.width as i64).contains(&source_x)
+                    && (0..self.height as i64).contains(&source_y)
+                {
+                    values[(y * width as i64 + x) as usize] =
+                        self.values[(source_y * self.width as i64 + source_x) as usize];
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub kind: ParameterKind,
+}
+
+impl Parameter {
+    pub fn bool(name: &'static str, default: bool) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Bool { default },
+        }
+    }
+
+    pub fn int(name: &'static str, min: i32, max: i32, default: i32) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Int { min, max, default },
+        }
+    }
+
+    pub fn float(name: &'static str, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Float { min, max, default },
+        }
+    }
+
+    pub fn choice(name: &'static str, options: &'static [&'static str], default: usize) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Choice { options, default },
+        }
+    }
+
+    pub fn kernel_size(name: &'static str, min: u32, max: u32, default: u32) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::KernelSize { min, max, default },
+        }
+    }
+
+    pub fn matrix(
+        name: &'static str,
+        max_size: u32,
+        default_size: u32,
+        default: &'static [f32],
+    ) -> Self {
+        debug_assert_eq!(default.len() as u32, default_size * default_size);
+        Self {
+            name,
+            kind: ParameterKind::Matrix {
+                max_size,
+                default_size,
+                default,
+            },
+        }
+    }
+
+    pub fn mask(name: &'static str, max_size: u32, default_size: u32) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Mask {
+                max_size,
+                default_size,
+            },
+        }
+    }
+
+    pub fn point(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ParameterKind::Point,
+        }
+    }
+
+    pub fn default_value(&self) -> Value {
+        match self.kind {
+            ParameterKind::Bool { default } => Value::Bool(default),
+            ParameterKind::Int { default, .. } => Value::Int(default),
+            ParameterKind::Float { default, .. } => Value::Float(default),
+            ParameterKind::Choice { default, .. } => Value::Choice(default),
+            ParameterKind::KernelSize { default, .. } => Value::KernelSize(default),
+            ParameterKind::Matrix {
+                default_size,
+                default,
+                ..
+            } => Value::Matrix(Matrix {
+                width: default_size,
+                height: default_size,
+                values: default.to_vec(),
+            }),
+            ParameterKind::Mask { default_size, .. } => Value::Matrix(Matrix {
+                width: default_size,
+                height: default_size,
+                values: vec![1.0; (default_size * default_size) as usize],
+            }),
+            ParameterKind::Point => Value::Point(None),
+        }
+    }
+
+    /// Clamps `value` into the range of this parameter. Returns `None` if the value has the wrong type.
+    pub fn validate(&self, value: Value) -> Option<Value> {
+        match (&self.kind, value) {
+            (ParameterKind::Bool { .. }, Value::Bool(value)) => Some(Value::Bool(value)),
+            (ParameterKind::Int { min, max, .. }, Value::Int(value)) => {
+                Some(Value::Int(value.clamp(*min, *max)))
+            }
+            (ParameterKind::Float { min, max, .. }, Value::Float(value)) => {
+                Some(Value::Float(value.clamp(*min, *max)))
+            }
+            (ParameterKind::Choice { options, .. }, Value::Choice(value)) => {
+                let last = options.len().checked_sub(1)?;
+                Some(Value::Choice(value.min(last)))
+            }
+            (ParameterKind::KernelSize { min, max, .. }, Value::KernelSize(value)) => {
+                Some(Value::KernelSize(odd_within(value, *min, *max)))
+            }
+            (ParameterKind::Matrix { max_size, .. }, Value::Matrix(value)) => {
+                let width = odd_within(value.width, 1, *max_size);
+                let height = odd_within(value.height, 1, *max_size);
+                if value.values.len() as u32 != value.width * value.height {
+                    return None;
+                }
+                Some(Value::Matrix(value.resized(width, height)))
+            }
+            (ParameterKind::Mask { max_size, .. }, Value::Matrix(value)) => {
+                let width = odd_within(value.width, 1, *max_size);
+                let height = odd_within(value.height, 1, *max_size);
+                if value.values.len() as u32 != value.width * value.height {
+                    return None;
+                }
+                let mut value = value.resized(width, height);
+                for cell in &mut value.values {
+                    *cell = if *cell != 0.0 { 1.0 } else { 0.0 };
+                }
+                Some(Value::Matrix(value))
+            }
+            (ParameterKind::Point, Value::Point(value)) => Some(Value::Point(value)),
+            _ => None,
+        }
+    }
+}
+
+/// Rounds `value` up to the next odd number and clamps it to the odd numbers in `min..=max`.
+/// An even `max` steps down to the odd number below it. Ranges without an odd number give
+/// their lowest odd bound, but never less than 1.
+fn odd_within(value: u32, min: u32, max: u32) -> u32 {
+    let highest_odd = if max % 2 == 1 {
+        max
+    } else {
+        max.saturating_sub(1)
+    };
+    let lowest_odd = (min | 1).min(highest_odd.max(1));
+    (value | 1).clamp(lowest_odd, highest_odd.max(lowest_odd))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Choice(usize),
+    KernelSize(u32),
+    Matrix(Matrix),
+    Point(Option<[f32; 2]>),
+}
+
+/// Values for an operation's parameters, keyed by parameter name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Parameters {
+    values: HashMap<&'static str, Value>,
+}
+
+impl Parameters {
+    pub fn from_defaults(parameters: &[Parameter]) -> Self {
+        Self {
+            values: parameters
+                .iter()
+                .map(|parameter| (parameter.name, parameter.default_value()))
+                .collect(),
+        }
+    }
+
+    pub fn set(&mut self, name: &'static str, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            Value::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_choice(&self, name: &str) -> Option<usize> {
+        match self.get(name)? {
+            Value::Choice(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_kernel_size(&self, name: &str) -> Option<u32> {
+        match self.get(name)? {
+            Value::KernelSize(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_matrix(&self, name: &str) -> Option<Matrix> {
+        match self.get(name)? {
+            Value::Matrix(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// `None` if the point is unset.
+    pub fn get_point(&self, name: &str) -> Option<[f32; 2]> {
+        match self.get(name)? {
+            Value::Point(value) => value,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_size_stays_odd_and_in_range() {
+        let parameter = Parameter::kernel_size("size", 1, 10, 3);
+        for (value, expected) in [(0, 1), (4, 5), (9, 9), (10, 9), (200, 9)] {
+            assert_eq!(
+                parameter.validate(Value::KernelSize(value)),
+                Some(Value::KernelSize(expected))
+            );
+        }
+    }
+
+    #[test]
+    fn matrix_size_stays_odd_and_in_range() {
+        let parameter = Parameter::matrix("kernel", 4, 1, &[1.0]);
+        let value = Value::Matrix(Matrix {
+            width: 4,
+            height: 2,
+            values: vec![1.0; 8],
+        });
+        match parameter.validate(value) {
+            Some(Value::Matrix(matrix)) => assert_eq!((matrix.width, matrix.height), (3, 3)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn choice_without_options_is_rejected() {
+        let parameter = Parameter::choice("mode", &[], 0);
+        assert_eq!(parameter.validate(Value::Choice(0)), None);
+        let parameter = Parameter::choice("mode", &["a", "b"], 0);
+        assert_eq!(parameter.validate(Value::Choice(5)), Some(Value::Choice(1)));
+    }
+}
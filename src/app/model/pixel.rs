@@ -0,0 +1,74 @@
+use image::{ColorType, DynamicImage, GenericImageView, Pixel};
+
+/// Raw channel values of a single pixel, formatted for display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PixelInfo {
+    pub x: u32,
+    pub y: u32,
+    pub color_type: ColorType,
+    pub channels: Vec<(&'static str, String)>,
+}
+
+impl PixelInfo {
+    /// Formats the channels on a single line, e.g. `R 255 G 0 B 12`.
+    pub fn values_text(&self) -> String {
+        self.channels
+            .iter()
+            .map(|(name, value)| format!("{} {}", name, value))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+}
+
+/// Reads the pixel at `x`, `y` without converting the image to another pixel type.
+pub fn pixel_info(image: &DynamicImage, x: u32, y: u32) -> Option<PixelInfo> {
+    if x >= image.width() || y >= image.height() {
+        return None;
+    }
+
+    let values = match image {
+        DynamicImage::ImageLuma8(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageLumaA8(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgb8(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgba8(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageLuma16(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageLumaA16(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgb16(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgba16(buffer) => integers(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgb32F(buffer) => floats(buffer.get_pixel(x, y).channels()),
+        DynamicImage::ImageRgba32F(buffer) => floats(buffer.get_pixel(x, y).channels()),
+        _ => integers(image.get_pixel(x, y).channels()),
+    };
+    let color_type = image.color();
+
+    Some(PixelInfo {
+        x,
+        y,
+        color_type,
+        channels: channel_names(color_type)
+            .iter()
+            .copied()
+            .zip(values)
+            .collect(),
+    })
+}
+
+pub fn channel_names(color_type: ColorType) -> &'static [&'static str] {
+    match color_type.channel_count() {
+        1 => &["L"],
+        2 => &["L", "A"],
+        3 => &["R", "G", "B"],
+        _ => &["R", "G", "B", "A"],
+    }
+}
+
+fn integers<T: ToString>(channels: &[T]) -> Vec<String> {
+    channels.iter().map(|value| value.to_string()).collect()
+}
+
+fn floats(channels: &[f32]) -> Vec<String> {
+    channels
+        .iter()
+        .map(|value| format!("{:.3}", value))
+        .collect()
+}
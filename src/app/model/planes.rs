@@ -0,0 +1,237 @@
+use image::{DynamicImage, ImageBuffer, Luma, LumaA, Pixel, Primitive, Rgb, Rgba};
+
+/// An image split into one `f32` plane per channel, so that filters can be written once for
+/// every pixel type. Values keep the range of the original pixel type, e.g. `0.0..=255.0` for
+/// 8 bit images, and are rounded and clamped again by `into_image`.
+#[derive(Clone, Debug)]
+pub struct Planes {
+    pub width: u32,
+    pub height: u32,
+    pub planes: Vec<Vec<f32>>,
+    layout: Layout,
+}
+
+/// The subpixel type of an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Depth {
+    U8,
+    U16,
+    F32,
+}
+
+impl Depth {
+    /// The value of full intensity, e.g. 255 for 8 bit images and 1 for floating point images.
+    pub fn max_value(&self) -> f32 {
+        match self {
+            Depth::U8 => u8::MAX as f32,
+            Depth::U16 => u16::MAX as f32,
+            Depth::F32 => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Layout {
+    L8,
+    La8,
+    Rgb8,
+    Rgba8,
+    L16,
+    La16,
+    Rgb16,
+    Rgba16,
+    Rgb32F,
+    Rgba32F,
+}
+
+impl Planes {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(buffer) => split(buffer, Layout::L8),
+            DynamicImage::ImageLumaA8(buffer) => split(buffer, Layout::La8),
+            DynamicImage::ImageRgb8(buffer) => split(buffer, Layout::Rgb8),
+            DynamicImage::ImageRgba8(buffer) => split(buffer, Layout::Rgba8),
+            DynamicImage::ImageLuma16(buffer) => split(buffer, Layout::L16),
+            DynamicImage::ImageLumaA16(buffer) => split(buffer, Layout::La16),
+            DynamicImage::ImageRgb16(buffer) => split(buffer, Layout::Rgb16),
+            DynamicImage::ImageRgba16(buffer) => split(buffer, Layout::Rgba16),
+            DynamicImage::ImageRgb32F(buffer) => split(buffer, Layout::Rgb32F),
+            DynamicImage::ImageRgba32F(buffer) => split(buffer, Layout::Rgba32F),
+            _ => split(&image.to_rgba32f(), Layout::Rgba32F),
+        }
+    }
+
+    /// Converts back to the pixel type the planes were created from.
+    pub fn into_image(self) -> DynamicImage {
+        let (width, height) = (self.width, self.height);
+        match self.layout {
+            Layout::L8 => DynamicImage::ImageLuma8(merge::<Luma<u8>>(width, height, &self.planes)),
+            Layout::La8 => {
+                DynamicImage::ImageLumaA8(merge::<LumaA<u8>>(width, height, &self.planes))
+            }
+            Layout::Rgb8 => DynamicImage::ImageRgb8(merge::<Rgb<u8>>(width, height, &self.planes)),
+            Layout::Rgba8 => {
+                DynamicImage::ImageRgba8(merge::<Rgba<u8>>(width, height, &self.planes))
+            }
+            Layout::L16 => {
+                DynamicImage::ImageLuma16(merge::<Luma<u16>>(width, height, &self.planes))
+            }
+            Layout::La16 => {
+                DynamicImage::ImageLumaA16(merge::<LumaA<u16>>(width, height, &self.planes))
+            }
+            Layout::Rgb16 => {
+                DynamicImage::ImageRgb16(merge::<Rgb<u16>>(width, height, &self.planes))
+            }
+            Layout::Rgba16 => {
+                DynamicImage::ImageRgba16(merge::<Rgba<u16>>(width, height, &self.planes))
+            }
+            Layout::Rgb32F => {
+                DynamicImage::ImageRgb32F(merge::<Rgb<f32>>(width, height, &self.planes))
+            }
+            Layout::Rgba32F => {
+                DynamicImage::ImageRgba32F(merge::<Rgba<f32>>(width, height, &self.planes))
+            }
+        }
+    }
+
+    /// Creates planes that `into_image` turns into an image of `depth` with one (luma), two
+    /// (luma and alpha), three (RGB) or four (RGBA) channels. There are no floating point luma
+    /// types, so one or two floating point planes become RGB(A) with three equal channels.
+    /// Returns `None` for other plane counts.
+    pub fn from_planes(
+        width: u32,
+        height: u32,
+        mut planes: Vec<Vec<f32>>,
+        depth: Depth,
+    ) -> Option<Self> {
+        let layout = match (depth, planes.len()) {
+            (Depth::U8, 1) => Layout::L8,
+            (Depth::U8, 2) => Layout::La8,
+            (Depth::U8, 3) => Layout::Rgb8,
+            (Depth::U8, 4) => Layout::Rgba8,
+            (Depth::U16, 1) => Layout::L16,
+            (Depth::U16, 2) => Layout::La16,
+            (Depth::U16, 3) => Layout::Rgb16,
+            (Depth::U16, 4) => Layout::Rgba16,
+            (Depth::F32, 1) => {
+                planes = vec![planes[0].clone(), planes[0].clone(), planes.remove(0)];
+                Layout::Rgb32F
+            }
+            (Depth::F32, 2) => {
+                let alpha = planes.remove(1);
+                planes = vec![
+                    planes[0].clone(),
+                    planes[0].clone(),
+                    planes.remove(0),
+                    alpha,
+                ];
+                Layout::Rgba32F
+            }
+            (Depth::F32, 3) => Layout::Rgb32F,
+            (Depth::F32, 4) => Layout::Rgba32F,
+            _ => return None,
+        };
+
+        Some(Self {
+            width,
+            height,
+            planes,
+            layout,
+        })
+    }
+
+    /// Builds a single channel image with the bit depth of these planes from `values`, see
+    /// `from_planes`.
+    pub fn luma_image(&self, values: Vec<f32>) -> DynamicImage {
+        Self::from_planes(self.width, self.height, vec![values], self.depth())
+            .expect("one plane is a valid layout")
+            .into_image()
+    }
+
+    pub fn depth(&self) -> Depth {
+        match self.layout {
+            Layout::L8 | Layout::La8 | Layout::Rgb8 | Layout::Rgba8 => Depth::U8,
+            Layout::L16 | Layout::La16 | Layout::Rgb16 | Layout::Rgba16 => Depth::U16,
+            Layout::Rgb32F | Layout::Rgba32F => Depth::F32,
+        }
+    }
+
+    /// The value of full intensity, e.g. 255 for 8 bit images and 1 for floating point images.
+    pub fn max_value(&self) -> f32 {
+        self.depth().max_value()
+    }
+
+    /// Index of the alpha plane, if the image has one.
+    pub fn alpha(&self) -> Option<usize> {
+        match self.layout {
+            Layout::La8 | Layout::La16 => Some(1),
+            Layout::Rgba8 | Layout::Rgba16 | Layout::Rgba32F => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Indices of all planes except alpha.
+    pub fn color_planes(&self) -> Vec<usize> {
+        (0..self.planes.len())
+            .filter(|index| Some(*index) != self.alpha())
+            .collect()
+    }
+}
+
+fn split<P>(buffer: &ImageBuffer<P, Vec<P::Subpixel>>, layout: Layout) -> Planes
+where
+    P: Pixel,
+    P::Subpixel: Into<f32>,
+{
+    let channels = P::CHANNEL_COUNT as usize;
+    let mut planes = vec![Vec::with_capacity(buffer.len() / channels); channels];
+    for pixel in buffer.pixels() {
+        for (plane, value) in planes.iter_mut().zip(pixel.channels()) {
+            plane.push((*value).into());
+        }
+    }
+
+    Planes {
+        width: buffer.width(),
+        height: buffer.height(),
+        planes,
+        layout,
+    }
+}
+
+fn merge<P>(width: u32, height: u32, planes: &[Vec<f32>]) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel,
+    P::Subpixel: Primitive + FromF32,
+{
+    let mut data = Vec::with_capacity(planes.len() * planes.first().map_or(0, Vec::len));
+    for index in 0..(width * height) as usize {
+        for plane in planes {
+            data.push(P::Subpixel::from_f32(plane[index]));
+        }
+    }
+    ImageBuffer::from_raw(width, height, data).expect("planes have the image size")
+}
+
+/// Rounding and clamping conversion back to a subpixel type. Floats are left as they are.
+trait FromF32 {
+    fn from_f32(value: f32) -> Self;
+}
+
+impl FromF32 for u8 {
+    fn from_f32(value: f32) -> Self {
+        value.round().clamp(0.0, u8::MAX as f32) as u8
+    }
+}
+
+impl FromF32 for u16 {
+    fn from_f32(value: f32) -> Self {
+        value.round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+impl FromF32 for f32 {
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
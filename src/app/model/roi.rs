@@ -0,0 +1,286 @@
+use crate::app::model::observable::Observable;
+use crate::app::model::planes::Planes;
+use image::{ColorType, DynamicImage};
+use std::f32::consts::TAU;
+
+/// The region operations are restricted to, if any.
+pub type Roi = Observable<Option<Region>>;
+
+/// Number of segments the outline of an ellipse is drawn with.
+const ELLIPSE_SEGMENTS: usize = 96;
+
+/// The outline of a region in image pixel coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Two opposite corners, in any order.
+    Rectangle([[f32; 2]; 2]),
+    /// The ellipse inside the rectangle of two opposite corners.
+    Ellipse([[f32; 2]; 2]),
+    /// A closed polygon, also used for freehand outlines. Self-intersecting polygons follow the
+    /// even-odd rule.
+    Polygon(Vec<[f32; 2]>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub shape: Shape,
+    /// Softness of the edge in pixels, 0 for a hard edge.
+    pub feather: f32,
+}
+
+impl Region {
+    /// Minimum and maximum corner of the bounding box.
+    pub fn bounds(&self) -> [[f32; 2]; 2] {
+        let points = match &self.shape {
+            Shape::Rectangle(corners) | Shape::Ellipse(corners) => corners.as_slice(),
+            Shape::Polygon(points) => points.as_slice(),
+        };
+        points.iter().fold(
+            [[f32::INFINITY; 2], [f32::NEG_INFINITY; 2]],
+            |[min, max], [x, y]| {
+                [
+                    [min[0].min(*x), min[1].min(*y)],
+                    [max[0].max(*x), max[1].max(*y)],
+                ]
+            },
+        )
+    }
+
+    /// The pixels covered by the bounding box within a `width` x `height` image, as x, y, width
+    /// and height. `None` if they do not overlap.
+    pub fn pixel_bounds(&self, width: u32, height: u32) -> Option<[u32; 4]> {
+        let [[min_x, min_y], [max_x, max_y]] = self.bounds();
+        let x0 = min_x.floor().clamp(0.0, width as f32) as u32;
+        let y0 = min_y.floor().clamp(0.0, height as f32) as u32;
+        let x1 = max_x.ceil().clamp(0.0, width as f32) as u32;
+        let y1 = max_y.ceil().clamp(0.0, height as f32) as u32;
+        if x1 > x0 && y1 > y0 {
+            Some([x0, y0, x1 - x0, y1 - y0])
+        } else {
+            None
+        }
+    }
+
+    /// Points along the outline, for drawing it.
+    pub fn outline(&self) -> Vec<[f32; 2]> {
+        match &self.shape {
+            Shape::Rectangle([[x0, y0], [x1, y1]]) => {
+                vec![[*x0, *y0], [*x1, *y0], [*x1, *y1], [*x0, *y1]]
+            }
+            Shape::Ellipse(corners) => {
+                let ([cx, cy], [rx, ry]) = ellipse(corners);
+                (0..ELLIPSE_SEGMENTS)
+                    .map(|index| {
+                        let angle = index as f32 / ELLIPSE_SEGMENTS as f32 * TAU;
+                        [cx + rx * angle.cos(), cy + ry * angle.sin()]
+                    })
+                    .collect()
+            }
+            Shape::Polygon(points) => points.clone(),
+        }
+    }
+
+    /// How much of each pixel of a `width` x `height` image belongs to the region, from 0 to 1.
+    /// A pixel belongs to the region if its center is inside, feathering then fades the edge out
+    /// over about `feather` pixels on either side.
+    pub fn mask(&self, width: u32, height: u32) -> Vec<f32> {
+        let (width, height) = (width as usize, height as usize);
+        let mut mask = vec![0.0; width * height];
+        for y in 0..height {
+            let row = &mut mask[y * width..(y + 1) * width];
+            for (start, end) in self.spans(y as f32 + 0.5) {
+                // pixels whose center x + 0.5 lies in start..end
+                let first = (start - 0.5).ceil().clamp(0.0, width as f32) as usize;
+                let last = (end - 0.5).ceil().clamp(0.0, width as f32) as usize;
+                for value in &mut row[first..last.max(first)] {
+                    *value = 1.0;
+                }
+            }
+        }
+
+        let radius = (self.feather / 2.0).round() as usize;
+        if radius > 0 {
+            // two box blurs make a triangular falloff of 2 * (2 * radius + 1) pixels
+            for _ in 0..2 {
+                box_blur(&mut mask, width, height, radius);
+            }
+        }
+        mask
+    }
+
+    /// Intervals of x inside the region on the horizontal line at `y`.
+    fn spans(&self, y: f32) -> Vec<(f32, f32)> {
+        match &self.shape {
+            Shape::Rectangle(corners) => {
+                let [[x0, y0], [x1, y1]] = sorted(corners);
+                if (y0..y1).contains(&y) {
+                    vec![(x0, x1)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Shape::Ellipse(corners) => {
+                let ([cx, cy], [rx, ry]) = ellipse(corners);
+                let dy = (y - cy) / ry;
+                if ry > 0.0 && dy.abs() < 1.0 {
+                    let half_width = rx * (1.0 - dy * dy).sqrt();
+                    vec![(cx - half_width, cx + half_width)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Shape::Polygon(points) => {
+                let mut crossings: Vec<f32> = points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .filter(|([_, ay], [_, by])| (*ay <= y) != (*by <= y))
+                    .map(|([ax, ay], [bx, by])| ax + (y - ay) / (by - ay) * (bx - ax))
+                    .collect();
+                crossings.sort_unstable_by(f32::total_cmp);
+                crossings
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Mixes `processed` into `original` by `mask`, e.g. from `Region::mask`. The result has the
+/// channels of both images and the larger of their bit depths, so that e.g. a grayscale result
+/// keeps the colour outside of the region. `None` if the sizes differ.
+pub fn blend(
+    original: &DynamicImage,
+    processed: &DynamicImage,
+    mask: &[f32],
+) -> Option<DynamicImage> {
+    if original.width() != processed.width() || original.height() != processed.height() {
+        return None;
+    }
+
+    let color_type = blended_color_type(original.color(), processed.color());
+    let original = planes_as(original, color_type);
+    let mut planes = planes_as(processed, color_type);
+    for (plane, original) in planes.planes.iter_mut().zip(&original.planes) {
+        for ((value, original), amount) in plane.iter_mut().zip(original).zip(mask) {
+            *value = original + (*value - original) * amount;
+        }
+    }
+    Some(planes.into_image())
+}
+
+/// Cuts out the bounding box of `region`. `None` if it is outside of `image`.
+pub fn crop(image: &DynamicImage, region: &Region) -> Option<DynamicImage> {
+    let [x, y, width, height] = region.pixel_bounds(image.width(), image.height())?;
+    Some(image.crop_imm(x, y, width, height))
+}
+
+/// The pixel type that holds both `a` and `b` without losing channels or precision.
+/// Floating point images always have colour.
+fn blended_color_type(a: ColorType, b: ColorType) -> ColorType {
+    let color = a.has_color() || b.has_color();
+    let alpha = a.has_alpha() || b.has_alpha();
+    let bytes_per_channel =
+        |color_type: ColorType| color_type.bytes_per_pixel() / color_type.channel_count().max(1);
+    match (bytes_per_channel(a).max(bytes_per_channel(b)), color, alpha) {
+        (1, false, false) => ColorType::L8,
+        (1, false, true) => ColorType::La8,
+        (1, true, false) => ColorType::Rgb8,
+        (1, true, true) => ColorType::Rgba8,
+        (2, false, false) => ColorType::L16,
+        (2, false, true) => ColorType::La16,
+        (2, true, false) => ColorType::Rgb16,
+        (2, true, true) => ColorType::Rgba16,
+        (_, _, false) => ColorType::Rgb32F,
+        (_, _, true) => ColorType::Rgba32F,
+    }
+}
+
+/// The planes of `image` converted to `color_type`.
+fn planes_as(image: &DynamicImage, color_type: ColorType) -> Planes {
+    if image.color() == color_type {
+        return Planes::from_image(image);
+    }
+    Planes::from_image(&match color_type {
+        ColorType::L8 => DynamicImage::ImageLuma8(image.to_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(image.to_rgb8()),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(image.to_rgba8()),
+        ColorType::L16 => DynamicImage::ImageLuma16(image.to_luma16()),
+        ColorType::La16 => DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(image.to_rgb16()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(image.to_rgba16()),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(image.to_rgb32f()),
+        _ => DynamicImage::ImageRgba32F(image.to_rgba32f()),
+    })
+}
+
+fn sorted([[x0, y0], [x1, y1]]: &[[f32; 2]; 2]) -> [[f32; 2]; 2] {
+    [[x0.min(*x1), y0.min(*y1)], [x0.max(*x1), y0.max(*y1)]]
+}
+
+/// Center and radii of the ellipse inside the rectangle of `corners`.
+fn ellipse(corners: &[[f32; 2]; 2]) -> ([f32; 2], [f32; 2]) {
+    let [[x0, y0], [x1, y1]] = sorted(corners);
+    (
+        [(x0 + x1) / 2.0, (y0 + y1) / 2.0],
+        [(x1 - x0) / 2.0, (y1 - y0) / 2.0],
+    )
+}
+
+/// Averages over (2 * `radius` + 1)^2 pixels, repeating the edge pixels outside of the mask.
+fn box_blur(mask: &mut [f32], width: usize, height: usize, radius: usize) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let mut line = Vec::new();
+    let mut blur_line = |values: &mut dyn Iterator<Item = &mut f32>, length: usize| {
+        let values: Vec<&mut f32> = values.collect();
+        line.clear();
+        line.extend(values.iter().map(|value| **value));
+        let at = |index: isize| line[index.clamp(0, length as isize - 1) as usize];
+        let radius = radius as isize;
+        let mut sum: f32 = (-radius..=radius).map(at).sum();
+        let count = (2 * radius + 1) as f32;
+        for (index, value) in values.into_iter().enumerate() {
+            *value = sum / count;
+            let index = index as isize;
+            sum += at(index + radius + 1) - at(index - radius);
+        }
+    };
+
+    for row in mask.chunks_mut(width) {
+        blur_line(&mut row.iter_mut(), width);
+    }
+    for x in 0..width {
+        blur_line(&mut mask.iter_mut().skip(x).step_by(width), height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn blending_a_grayscale_result_keeps_the_colour_outside() {
+        let original = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 1, Rgb([200, 0, 0])));
+        let processed = DynamicImage::ImageLuma8(GrayImage::from_pixel(2, 1, Luma([60])));
+
+        let blended = blend(&original, &processed, &[1.0, 0.0]).unwrap();
+        let blended = blended.as_rgb8().expect("colour is kept");
+        assert_eq!(blended.get_pixel(0, 0), &Rgb([60, 60, 60]));
+        assert_eq!(blended.get_pixel(1, 0), &Rgb([200, 0, 0]));
+    }
+
+    #[test]
+    fn blending_an_8_bit_mask_keeps_16_bit_levels_outside() {
+        let original = DynamicImage::ImageLuma16(ImageBuffer::from_pixel(2, 1, Luma([1234u16])));
+        let processed = DynamicImage::ImageLuma8(GrayImage::from_pixel(2, 1, Luma([255])));
+
+        let blended = blend(&original, &processed, &[1.0, 0.0]).unwrap();
+        let blended = blended.as_luma16().expect("the bit depth is kept");
+        assert_eq!(blended.get_pixel(0, 0), &Luma([u16::MAX]));
+        assert_eq!(blended.get_pixel(1, 0), &Luma([1234]));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::app::model::observable::Observable;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// State of the operation that is currently computed in the background.
+#[derive(Clone, Debug)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    /// Fraction of the work that is done, from 0 to 1.
+    pub progress: f32,
+}
+
+pub type Task = Observable<Option<TaskStatus>>;
+
+/// Handed to `Operation::apply` so that long running operations can report progress and stop
+/// early once their result is no longer needed. Operations can also leave notes about their
+/// result, e.g. an automatically chosen threshold.
+pub struct Progress {
+    cancelled: Arc<AtomicBool>,
+    last_reported: AtomicU32,
+    report: Box<dyn Fn(f32) + Send + Sync>,
+    notes: Mutex<Vec<String>>,
+}
+
+impl Progress {
+    /// Reports are throttled to steps of this size to keep the message channel quiet.
+    const REPORT_STEP: f32 = 0.01;
+
+    pub fn new(cancelled: Arc<AtomicBool>, report: impl Fn(f32) + Send + Sync + 'static) -> Self {
+        Self {
+            cancelled,
+            last_reported: AtomicU32::new(0f32.to_bits()),
+            report: Box::new(report),
+            notes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A progress handle that reports nowhere and is never cancelled.
+    #[allow(dead_code)]
+    pub fn none() -> Self {
+        Self::new(Arc::new(AtomicBool::new(false)), |_| {})
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, fraction: f32) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let last_reported = f32::from_bits(self.last_reported.load(Ordering::Relaxed));
+        if (fraction - last_reported).abs() >= Self::REPORT_STEP || fraction >= 1.0 {
+            self.last_reported
+                .store(fraction.to_bits(), Ordering::Relaxed);
+            (self.report)(fraction);
+        }
+    }
+
+    pub fn note(&self, note: String) {
+        self.notes.lock().unwrap().push(note);
+    }
+
+    pub fn take_notes(&self) -> Vec<String> {
+        std::mem::take(&mut *self.notes.lock().unwrap())
+    }
+
+    /// Convenience for row based loops. Returns `false` if the operation should stop.
+    pub fn row_done(&self, row: u32, height: u32) -> bool {
+        if height > 0 {
+            self.set((row + 1) as f32 / height as f32);
+        }
+        !self.is_cancelled()
+    }
+}
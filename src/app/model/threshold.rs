@@ -0,0 +1,438 @@
+use crate::app::model::luminance::Luminance;
+use crate::app::model::task::Progress;
+use image::{DynamicImage, GrayImage};
+
+/// The ways a pixel can be compared against the threshold, as in OpenCV.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThresholdType {
+    /// `max_value` above the threshold, 0 otherwise.
+    Binary,
+    /// 0 above the threshold, `max_value` otherwise.
+    BinaryInverted,
+    /// The threshold above the threshold, unchanged otherwise.
+    Truncate,
+    /// Unchanged above the threshold, 0 otherwise.
+    ToZero,
+    /// 0 above the threshold, unchanged otherwise.
+    ToZeroInverted,
+}
+
+impl ThresholdType {
+    pub const ALL: [ThresholdType; 5] = [
+        ThresholdType::Binary,
+        ThresholdType::BinaryInverted,
+        ThresholdType::Truncate,
+        ThresholdType::ToZero,
+        ThresholdType::ToZeroInverted,
+    ];
+
+    pub const NAMES: [&'static str; 5] = [
+        "binary",
+        "binary inverted",
+        "truncate",
+        "to zero",
+        "to zero inverted",
+    ];
+
+    /// Whether the result is a mask of `max_value` and 0 rather than the thresholded values.
+    pub fn is_binary(&self) -> bool {
+        matches!(self, ThresholdType::Binary | ThresholdType::BinaryInverted)
+    }
+
+    pub fn apply(&self, value: f32, threshold: f32, max_value: f32) -> f32 {
+        let above = value > threshold;
+        match self {
+            ThresholdType::Binary => {
+                if above {
+                    max_value
+                } else {
+                    0.0
+                }
+            }
+            ThresholdType::BinaryInverted => {
+                if above {
+                    0.0
+                } else {
+                    max_value
+                }
+            }
+            ThresholdType::Truncate => value.min(threshold),
+            ThresholdType::ToZero => {
+                if above {
+                    value
+                } else {
+                    0.0
+                }
+            }
+            ThresholdType::ToZeroInverted => {
+                if above {
+                    0.0
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdaptiveMethod {
+    /// Mean of the block.
+    Mean,
+    /// Gaussian weighted mean of the block.
+    Gaussian,
+}
+
+impl AdaptiveMethod {
+    pub const ALL: [AdaptiveMethod; 2] = [AdaptiveMethod::Mean, AdaptiveMethod::Gaussian];
+
+    pub const NAMES: [&'static str; 2] = ["mean", "gaussian"];
+}
+
+/// Compares the values of `luma` with `threshold`, both in the range of the pixel type. Binary
+/// types give a mask of `max_value` and 0, the others keep the pixel type.
+pub fn threshold(
+    luma: &Luminance,
+    threshold: f32,
+    max_value: u8,
+    threshold_type: ThresholdType,
+    progress: &Progress,
+) -> Option<DynamicImage> {
+    let width = (luma.width as usize).max(1);
+    let mut values = Vec::with_capacity(luma.values.len());
+    for (y, row) in luma.values.chunks(width).enumerate() {
+        values.extend(
+            row.iter()
+                .map(|value| threshold_type.apply(*value, threshold, max_value as f32)),
+        );
+        if !progress.row_done(y as u32, luma.height) {
+            return None;
+        }
+    }
+
+    if threshold_type.is_binary() {
+        let mask = values.into_iter().map(|value| value as u8).collect();
+        GrayImage::from_raw(luma.width, luma.height, mask).map(DynamicImage::ImageLuma8)
+    } else {
+        Some(
+            Luminance {
+                values,
+                ..luma.clone()
+            }
+            .into_image(),
+        )
+    }
+}
+
+/// Otsu's method: the threshold that maximizes the variance between the two classes.
+/// Returns the last bin of the lower class.
+pub fn otsu_threshold(histogram: &[u64]) -> usize {
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum();
+
+    let mut best = (0, -1.0);
+    let mut background_count = 0u64;
+    let mut background_sum = 0.0;
+    for (value, count) in histogram.iter().enumerate() {
+        background_count += count;
+        if background_count == 0 {
+            continue;
+        }
+        let foreground_count = total - background_count;
+        if foreground_count == 0 {
+            break;
+        }
+
+        background_sum += value as f64 * *count as f64;
+        let background_mean = background_sum / background_count as f64;
+        let foreground_mean = (sum - background_sum) / foreground_count as f64;
+        let variance = background_count as f64
+            * foreground_count as f64
+            * (background_mean - foreground_mean).powi(2);
+        if variance > best.1 {
+            best = (value, variance);
+        }
+    }
+
+    best.0
+}
+
+/// The triangle method: draws a line from the histogram peak to the far end of its longer tail
+/// and picks the level where the histogram is furthest below that line. Works well for images
+/// with one dominant background peak. Returns the last bin below the threshold.
+pub fn triangle_threshold(histogram: &[u64]) -> usize {
+    let last_bin = histogram.len().saturating_sub(1);
+    let first = histogram.iter().position(|count| *count > 0);
+    let last = histogram.iter().rposition(|count| *count > 0);
+    let (mut left, mut right) = match (first, last) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0,
+    };
+    left = left.saturating_sub(1);
+    right = (right + 1).min(last_bin);
+
+    let mut peak = 0;
+    for (value, count) in histogram.iter().enumerate() {
+        if *count > histogram[peak] {
+            peak = value;
+        }
+    }
+
+    // always search left of the peak, mirroring the histogram if the longer tail is on the right
+    let mut histogram = histogram.to_vec();
+    let flip = peak - left < right - peak;
+    if flip {
+        histogram.reverse();
+        left = last_bin - right;
+        peak = last_bin - peak;
+    }
+
+    let mut threshold = left;
+    let a = histogram[peak] as f64;
+    let b = left as f64 - peak as f64;
+    let mut max_distance = 0.0;
+    for (value, count) in histogram.iter().enumerate().take(peak + 1).skip(left + 1) {
+        let distance = a * value as f64 + b * *count as f64;
+        if distance > max_distance {
+            max_distance = distance;
+            threshold = value;
+        }
+    }
+    threshold = threshold.saturating_sub(1);
+
+    if flip {
+        last_bin - threshold
+    } else {
+        threshold
+    }
+}
+
+/// Compares every pixel with the (mean or Gaussian weighted) average of the
+/// `block_size` x `block_size` block around it minus `offset`, in the range of the pixel type.
+/// Pixels above it become `max_value`, the others 0, or the other way round if `inverted` is set.
+pub fn adaptive_threshold(
+    luma: &Luminance,
+    method: AdaptiveMethod,
+    block_size: u32,
+    offset: f32,
+    max_value: u8,
+    inverted: bool,
+    progress: &Progress,
+) -> Option<GrayImage> {
+    let local_mean = match method {
+        AdaptiveMethod::Mean => box_mean(luma, block_size / 2),
+        AdaptiveMethod::Gaussian => gaussian_mean(luma, block_size),
+    };
+    if progress.is_cancelled() {
+        return None;
+    }
+
+    let width = (luma.width as usize).max(1);
+    let mut result = Vec::with_capacity(luma.values.len());
+    for (y, row) in luma.values.chunks(width).enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            let above = *value > local_mean[y * width + x] - offset;
+            result.push(if above != inverted { max_value } else { 0 });
+        }
+        if !progress.row_done(y as u32, luma.height) {
+            return None;
+        }
+    }
+
+    GrayImage::from_raw(luma.width, luma.height, result)
+}
+
+/// Mean of the (2 * radius + 1)^2 block around every pixel, using a summed area table.
+/// Blocks are cropped at the image border.
+fn box_mean(luma: &Luminance, radius: u32) -> Vec<f32> {
+    let (width, height) = (luma.width as usize, luma.height as usize);
+    let mut integral = vec![0f64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row_sum = 0f64;
+        for x in 0..width {
+            row_sum += luma.values[y * width + x] as f64;
+            integral[(y + 1) * (width + 1) + x + 1] = integral[y * (width + 1) + x + 1] + row_sum;
+        }
+    }
+
+    let radius = radius as usize;
+    let mut result = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let y0 = y.saturating_sub(radius);
+        let y1 = (y + radius + 1).min(height);
+        for x in 0..width {
+            let x0 = x.saturating_sub(radius);
+            let x1 = (x + radius + 1).min(width);
+            let sum = integral[y1 * (width + 1) + x1] + integral[y0 * (width + 1) + x0]
+                - integral[y0 * (width + 1) + x1]
+                - integral[y1 * (width + 1) + x0];
+            result.push((sum / ((x1 - x0) * (y1 - y0)) as f64) as f32);
+        }
+    }
+
+    result
+}
+
+/// Gaussian weighted mean with the sigma OpenCV derives from the block size.
+/// Borders are handled by renormalizing the weights that fall inside the image.
+fn gaussian_mean(luma: &Luminance, block_size: u32) -> Vec<f32> {
+    let sigma = 0.3 * ((block_size as f32 - 1.0) * 0.5 - 1.0) + 0.8;
+    let radius = (block_size / 2) as i64;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let (width, height) = (luma.width as i64, luma.height as i64);
+    let blur = |source: &dyn Fn(i64, i64) -> f32, horizontal: bool| -> Vec<f32> {
+        let mut result = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                let mut weight_sum = 0.0;
+                for (index, weight) in kernel.iter().enumerate() {
+                    let offset = index as i64 - radius;
+                    let (sx, sy) = if horizontal {
+                        (x + offset, y)
+                    } else {
+                        (x, y + offset)
+                    };
+                    if sx >= 0 && sy >= 0 && sx < width && sy < height {
+                        sum += weight * source(sx, sy);
+                        weight_sum += weight;
+                    }
+                }
+                result.push(sum / weight_sum);
+            }
+        }
+        result
+    };
+
+    let horizontal = blur(&|x, y| luma.values[(y * width + x) as usize], true);
+    blur(&|x, y| horizontal[(y * width + x) as usize], false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::model::planes::Depth;
+
+    /// A peak of `height` at `center` falling off linearly over `width` levels on either side.
+    fn add_peak(histogram: &mut [u64; 256], center: usize, width: usize, height: u64) {
+        for offset in 0..=width {
+            let count = height * (width + 1 - offset) as u64 / (width + 1) as u64;
+            histogram[center + offset] += count;
+            if offset > 0 {
+                histogram[center - offset] += count;
+            }
+        }
+    }
+
+    #[test]
+    fn otsu_separates_two_peaks() {
+        let mut histogram = [0; 256];
+        add_peak(&mut histogram, 50, 10, 1000);
+        add_peak(&mut histogram, 190, 10, 1000);
+
+        let threshold = otsu_threshold(&histogram);
+        assert!((60..180).contains(&threshold), "threshold {}", threshold);
+    }
+
+    #[test]
+    fn otsu_splits_overlapping_symmetric_peaks_in_the_middle() {
+        let mut histogram = [0; 256];
+        add_peak(&mut histogram, 80, 50, 1000);
+        add_peak(&mut histogram, 160, 50, 1000);
+
+        let threshold = otsu_threshold(&histogram);
+        assert!((118..=121).contains(&threshold), "threshold {}", threshold);
+    }
+
+    #[test]
+    fn otsu_without_two_classes_is_zero() {
+        let mut histogram = [0; 256];
+        histogram[77] = 10;
+
+        assert_eq!(otsu_threshold(&histogram), 0);
+    }
+
+    #[test]
+    fn triangle_finds_the_knee_of_a_skewed_histogram() {
+        // a dark background peak with an exponential tail towards the bright end
+        let mut histogram = [0; 256];
+        for (value, count) in histogram.iter_mut().enumerate().skip(20).take(201) {
+            *count = (1000.0 * (-(value as f64 - 20.0) / 20.0).exp()).round() as u64;
+        }
+
+        // the tail is furthest below the line from the peak to its end where its slope matches
+        // the line's, about 46 levels after the peak
+        let threshold = triangle_threshold(&histogram);
+        assert!((60..=72).contains(&threshold), "threshold {}", threshold);
+    }
+
+    #[test]
+    fn triangle_is_mirrored_with_the_histogram() {
+        let mut histogram = [0; 256];
+        for (value, count) in histogram.iter_mut().enumerate().skip(20).take(201) {
+            *count = (1000.0 * (-(value as f64 - 20.0) / 20.0).exp()).round() as u64;
+        }
+        let threshold = triangle_threshold(&histogram) as i32;
+
+        let mut mirrored = histogram;
+        mirrored.reverse();
+        let mirrored_threshold = triangle_threshold(&mirrored) as i32;
+
+        assert!(
+            (255 - mirrored_threshold - threshold).abs() <= 2,
+            "{} and {}",
+            threshold,
+            mirrored_threshold
+        );
+    }
+
+    #[test]
+    fn triangle_of_an_empty_histogram_is_zero() {
+        assert_eq!(triangle_threshold(&[0; 256]), 0);
+    }
+
+    #[test]
+    fn otsu_separates_16_bit_levels_that_share_an_8_bit_level() {
+        // 1000 and 1100 both become 4 in 8 bit
+        let luma = Luminance {
+            width: 4,
+            height: 1,
+            values: vec![1000.0, 1000.0, 1100.0, 1100.0],
+            depth: Depth::U16,
+        };
+        let value = luma.bin_value(otsu_threshold(&luma.histogram()));
+        assert!((1000.0..1100.0).contains(&value), "threshold {}", value);
+
+        let mask = threshold(&luma, value, 255, ThresholdType::Binary, &Progress::none()).unwrap();
+        assert_eq!(mask.as_luma8().unwrap().as_raw(), &vec![0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn truncate_keeps_the_bit_depth() {
+        let luma = Luminance {
+            width: 3,
+            height: 1,
+            values: vec![100.0, 40000.0, 65535.0],
+            depth: Depth::U16,
+        };
+        let result = threshold(
+            &luma,
+            30000.0,
+            255,
+            ThresholdType::Truncate,
+            &Progress::none(),
+        )
+        .unwrap();
+        assert_eq!(
+            result.as_luma16().unwrap().as_raw(),
+            &vec![100, 30000, 30000]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use super::observable::Observable;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZoomMode {
+    /// Show the whole image, recomputed whenever the frame is resized.
+    Fit,
+    /// Cover the whole frame, recomputed whenever the frame is resized.
+    Fill,
+    /// Zoom and center were chosen by the user.
+    Manual,
+}
+
+/// The part of an image that is visible in a frame.
+/// `center` is the image coordinate (in pixels) shown in the middle of the frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub mode: ZoomMode,
+    pub zoom: f32,
+    pub center: [f32; 2],
+}
+
+impl Viewport {
+    pub const MIN_ZOOM: f32 = 0.01;
+    pub const MAX_ZOOM: f32 = 64.0;
+
+    pub fn fit() -> Self {
+        Self {
+            mode: ZoomMode::Fit,
+            zoom: 1.0,
+            center: [0.0, 0.0],
+        }
+    }
+
+    pub fn fill() -> Self {
+        Self {
+            mode: ZoomMode::Fill,
+            ..Self::fit()
+        }
+    }
+
+    /// One image pixel per screen point, centered on the image.
+    pub fn actual_size(image_size: [f32; 2]) -> Self {
+        Self {
+            mode: ZoomMode::Manual,
+            zoom: 1.0,
+            center: [image_size[0] / 2.0, image_size[1] / 2.0],
+        }
+    }
+
+    /// Turns `Fit` and `Fill` into the concrete zoom and center for the given sizes.
+    pub fn resolve(&self, image_size: [f32; 2], view_size: [f32; 2]) -> Self {
+        let scale_x = view_size[0] / image_size[0].max(1.0);
+        let scale_y = view_size[1] / image_size[1].max(1.0);
+        let zoom = match self.mode {
+            ZoomMode::Fit => scale_x.min(scale_y),
+            ZoomMode::Fill => scale_x.max(scale_y),
+            ZoomMode::Manual => return *self,
+        };
+
+        Self {
+            mode: self.mode,
+            zoom: zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM),
+            center: [image_size[0] / 2.0, image_size[1] / 2.0],
+        }
+    }
+
+    /// Multiplies the zoom by `factor` while keeping the image point `anchor` at the same place
+    /// on screen. Must be called on a resolved viewport.
+    pub fn zoom_around(&self, factor: f32, anchor: [f32; 2]) -> Self {
+        let zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let ratio = self.zoom / zoom;
+
+        Self {
+            mode: ZoomMode::Manual,
+            zoom,
+            center: [
+                anchor[0] + (self.center[0] - anchor[0]) * ratio,
+                anchor[1] + (self.center[1] - anchor[1]) * ratio,
+            ],
+        }
+    }
+
+    /// Moves the visible area by `delta` screen points. Must be called on a resolved viewport.
+    pub fn pan(&self, delta: [f32; 2]) -> Self {
+        Self {
+            mode: ZoomMode::Manual,
+            zoom: self.zoom,
+            center: [
+                self.center[0] - delta[0] / self.zoom,
+                self.center[1] - delta[1] / self.zoom,
+            ],
+        }
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::fit()
+    }
+}
+
+/// What an `Outline` stands for, which decides how it is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlineKind {
+    Contour,
+    Hole,
+    ConvexHull,
+    Rectangle,
+    Circle,
+}
+
+/// A closed polygon drawn over the image, in image coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outline {
+    pub kind: OutlineKind,
+    pub points: Vec<[f32; 2]>,
+}
+
+/// An area of the image to point out, e.g. a selected connected component.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highlight {
+    /// Minimum and maximum corner, framed so that small areas stay visible when zoomed out.
+    pub bounds: [[f32; 2]; 2],
+    /// Horizontal runs of the pixels that are meant, as `[y, first x, last x + 1]`.
+    pub runs: Vec<[u32; 3]>,
+}
+
+/// State shared by all frames whose views are linked.
+#[derive(Default)]
+pub struct ViewLink {
+    pub viewport: Observable<Viewport>,
+    /// The image coordinate under the mouse, if the mouse is over one of the linked frames.
+    pub cursor: Observable<Option<[f32; 2]>>,
+    /// Shown by all frames, linked or not.
+    pub highlight: Observable<Option<Highlight>>,
+    /// Vector shapes drawn over the image by all frames, e.g. contours.
+    pub outlines: Observable<Vec<Outline>>,
+}
@@ -0,0 +1,174 @@
+use super::{ImageFrame, View};
+use crate::app::model::channels::MergeLayout;
+use crate::app::viewmodel;
+use crate::app::viewmodel::channels_window::PropertyChangedNotification;
+use egui::{Context, Ui};
+use tokio::sync::broadcast;
+
+pub struct ChannelsWindow {
+    // properties
+    channels: Vec<String>,
+    has_current: bool,
+    merge_layout: MergeLayout,
+    merge_sources: Vec<usize>,
+    open: bool,
+
+    // frames of opened channels, with the channel index
+    frames: Vec<(usize, ImageFrame)>,
+
+    // dependencies
+    viewmodel: viewmodel::ChannelsWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl ChannelsWindow {
+    pub fn new(viewmodel: viewmodel::ChannelsWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            channels: viewmodel.get_channels().clone(),
+            has_current: viewmodel.get_has_current(),
+            merge_layout: viewmodel.get_merge_layout(),
+            merge_sources: viewmodel.get_merge_sources().clone(),
+            open: false,
+            frames: Vec::new(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        if ui
+            .add_enabled(self.has_current, egui::Button::new("split current image"))
+            .clicked()
+        {
+            self.viewmodel.split();
+        }
+
+        if self.channels.is_empty() {
+            ui.label("no channels, split an image first");
+            return;
+        }
+
+        ui.separator();
+
+        let mut opened = None;
+        egui::Grid::new("channels").striped(true).show(ui, |ui| {
+            for (index, name) in self.channels.iter().enumerate() {
+                ui.strong(name);
+                if ui.button("open").clicked() {
+                    opened = Some(index);
+                }
+                if ui
+                    .button("load")
+                    .on_hover_text("make this channel the current image")
+                    .clicked()
+                {
+                    self.viewmodel.load_channel(index);
+                }
+                if ui
+                    .add_enabled(self.has_current, egui::Button::new("store"))
+                    .on_hover_text("replace this channel with the current image")
+                    .clicked()
+                {
+                    self.viewmodel.store_channel(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = opened {
+            self.open_frame(index);
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("merge into");
+            let mut merge_layout = self.merge_layout;
+            egui::ComboBox::from_id_source("merge_layout")
+                .selected_text(MergeLayout::NAMES[merge_layout as usize])
+                .show_ui(ui, |ui| {
+                    for (layout, name) in MergeLayout::ALL.iter().zip(MergeLayout::NAMES) {
+                        ui.selectable_value(&mut merge_layout, *layout, name);
+                    }
+                });
+            if merge_layout != self.merge_layout {
+                self.merge_layout = merge_layout;
+                self.viewmodel.set_merge_layout(merge_layout);
+            }
+        });
+
+        for (slot, slot_name) in self.merge_layout.channels().iter().enumerate() {
+            let mut source = self.merge_sources.get(slot).copied().unwrap_or(0);
+            let selected = self.channels.get(source).map_or("-", String::as_str);
+            ui.horizontal(|ui| {
+                ui.label(*slot_name);
+                egui::ComboBox::from_id_source(("merge_source", slot))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (index, name) in self.channels.iter().enumerate() {
+                            ui.selectable_value(&mut source, index, name);
+                        }
+                    });
+            });
+            if Some(&source) != self.merge_sources.get(slot) {
+                self.viewmodel.set_merge_source(slot, source);
+            }
+        }
+
+        if ui.button("merge").clicked() {
+            self.viewmodel.merge();
+        }
+    }
+
+    fn open_frame(&mut self, index: usize) {
+        if self.frames.iter().any(|(opened, _)| *opened == index) {
+            return;
+        }
+        if let Some(frame) = self.viewmodel.create_frame(index) {
+            self.frames.push((index, ImageFrame::new(frame)));
+        }
+    }
+}
+
+impl View for ChannelsWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Channels => {
+                    self.channels = self.viewmodel.get_channels().clone();
+                    // the frames show channels of an earlier split
+                    self.frames.clear();
+                }
+                PropertyChangedNotification::HasCurrent => {
+                    self.has_current = self.viewmodel.get_has_current()
+                }
+                PropertyChangedNotification::MergeLayout => {
+                    self.merge_layout = self.viewmodel.get_merge_layout()
+                }
+                PropertyChangedNotification::MergeSources => {
+                    self.merge_sources = self.viewmodel.get_merge_sources().clone()
+                }
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Channels")
+            .open(&mut open)
+            .collapsible(true)
+            .resizable(false)
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+
+        for (_, frame) in self.frames.iter_mut() {
+            frame.show(ctx);
+        }
+        self.frames.retain(|(_, frame)| frame.is_open());
+    }
+}
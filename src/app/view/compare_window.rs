@@ -0,0 +1,348 @@
+use super::image_frame::{to_color_image, to_image, to_screen};
+use super::View;
+use crate::app::model::viewport::Viewport;
+use crate::app::viewmodel;
+use crate::app::viewmodel::compare_window::{CompareMode, PropertyChangedNotification};
+use egui::{
+    pos2, vec2, Color32, Context, Rect, Sense, Shape, Stroke, TextureHandle, TextureId, Ui,
+};
+use egui_extras::RetainedImage;
+use image::DynamicImage;
+use tokio::sync::broadcast;
+
+/// Dragging closer than this (in points) to the divider moves it instead of panning.
+const DIVIDER_GRAB_DISTANCE: f32 = 8.0;
+
+pub struct CompareWindow {
+    // properties
+    amplification: f32,
+    blink_interval: f32,
+    busy: bool,
+    current: Option<RetainedImage>,
+    /// Updated in place when the amplification changes.
+    difference: Option<TextureHandle>,
+    difference_stats: Option<(f32, f32)>,
+    mode: CompareMode,
+    open: bool,
+    preview: Option<RetainedImage>,
+    split: f32,
+
+    // view state
+    viewport: Viewport,
+    dragging_divider: bool,
+    show_preview: bool,
+
+    // dependencies
+    viewmodel: viewmodel::CompareWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl CompareWindow {
+    pub fn new(viewmodel: viewmodel::CompareWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            amplification: viewmodel.get_amplification(),
+            blink_interval: viewmodel.get_blink_interval(),
+            busy: viewmodel.get_busy(),
+            current: retained("compare current", &viewmodel.get_current()),
+            difference: None,
+            difference_stats: None,
+            mode: viewmodel.get_mode(),
+            open: viewmodel.get_open(),
+            preview: retained("compare preview", &viewmodel.get_preview()),
+            split: viewmodel.get_split(),
+            viewport: Viewport::fit(),
+            dragging_divider: false,
+            show_preview: false,
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.viewmodel.set_open(true);
+    }
+
+    fn update_difference(&mut self, ctx: &Context) {
+        match self.viewmodel.get_difference() {
+            Some(image) => {
+                let color_image = to_color_image(&image);
+                match &mut self.difference {
+                    Some(texture) => texture.set(color_image),
+                    None => {
+                        self.difference = Some(ctx.load_texture("compare difference", color_image))
+                    }
+                }
+            }
+            None => self.difference = None,
+        }
+        self.difference_stats = self.viewmodel.get_difference_stats();
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut mode = self.mode;
+            for option in CompareMode::ALL {
+                ui.selectable_value(&mut mode, option, option.name());
+            }
+            if mode != self.mode {
+                self.mode = mode;
+                self.viewmodel.set_mode(mode);
+            }
+
+            ui.separator();
+
+            if ui.button("fit").clicked() {
+                self.viewport = Viewport::fit();
+            }
+        });
+
+        match self.mode {
+            CompareMode::Split => {
+                ui.label("drag the divider to move it, drag elsewhere to pan");
+            }
+            CompareMode::Blink => {
+                ui.horizontal(|ui| {
+                    let mut interval = self.blink_interval;
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut interval, 0.0..=2.0)
+                                .text("interval (s, 0 = manual)"),
+                        )
+                        .changed()
+                    {
+                        self.blink_interval = interval;
+                        self.viewmodel.set_blink_interval(interval);
+                    }
+                    if ui.button("toggle").clicked() {
+                        self.show_preview = !self.show_preview;
+                    }
+                    ui.label(if self.show_preview {
+                        "showing preview"
+                    } else {
+                        "showing current"
+                    });
+                });
+            }
+            CompareMode::Difference => {
+                ui.horizontal(|ui| {
+                    let mut amplification = self.amplification;
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut amplification, 1.0..=256.0)
+                                .logarithmic(true)
+                                .text("amplification"),
+                        )
+                        .changed()
+                    {
+                        self.amplification = amplification;
+                        self.viewmodel.set_amplification(amplification);
+                    }
+                    if let Some((max, mean)) = self.difference_stats {
+                        ui.label(format!("max {:.4}  mean {:.4}", max, mean));
+                    }
+                    if self.busy {
+                        ui.spinner();
+                    }
+                });
+            }
+        }
+
+        let image_size = match &self.current {
+            Some(image) => [image.size()[0] as f32, image.size()[1] as f32],
+            None => {
+                ui.label("nothing to compare");
+                return;
+            }
+        };
+        if self.preview.is_none() {
+            ui.label("no preview to compare with");
+            return;
+        }
+        if self.mode == CompareMode::Difference && self.difference.is_none() {
+            if !self.busy {
+                ui.label("the images have different sizes");
+            }
+            return;
+        }
+
+        if self.mode == CompareMode::Blink && self.blink_interval > 0.0 {
+            let time = ui.input().time;
+            self.show_preview = (time / self.blink_interval as f64) as u64 % 2 == 1;
+            ui.ctx().request_repaint();
+        }
+
+        self.image_ui(ui, image_size);
+    }
+
+    fn image_ui(&mut self, ui: &mut Ui, image_size: [f32; 2]) {
+        let desired_size = ui.available_size().max(vec2(64.0, 64.0));
+        let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
+        let mut viewport = self
+            .viewport
+            .resolve(image_size, [rect.width(), rect.height()]);
+        let divider_x = rect.left() + rect.width() * self.split;
+
+        if response.drag_started() {
+            self.dragging_divider = self.mode == CompareMode::Split
+                && response.interact_pointer_pos().map_or(false, |pos| {
+                    (pos.x - divider_x).abs() < DIVIDER_GRAB_DISTANCE
+                });
+        }
+
+        if response.hovered() {
+            let (scroll, zoom_delta, hover_pos) = {
+                let input = ui.input();
+                (
+                    input.scroll_delta.y,
+                    input.zoom_delta(),
+                    input.pointer.hover_pos(),
+                )
+            };
+            let factor = zoom_delta * (scroll * 0.002).exp();
+            if let Some(hover_pos) = hover_pos {
+                if (factor - 1.0).abs() > f32::EPSILON {
+                    let anchor = to_image(&viewport, rect, hover_pos);
+                    viewport = viewport.zoom_around(factor, anchor);
+                    self.viewport = viewport;
+                }
+            }
+        }
+        if response.dragged() {
+            if self.dragging_divider {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    self.split = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+                    self.viewmodel.set_split(self.split);
+                }
+            } else {
+                let delta = response.drag_delta();
+                viewport = viewport.pan([delta.x, delta.y]);
+                self.viewport = viewport;
+            }
+        }
+        if response.drag_released() {
+            self.dragging_divider = false;
+        }
+        if response.double_clicked() {
+            self.viewport = Viewport::fit();
+            viewport = self
+                .viewport
+                .resolve(image_size, [rect.width(), rect.height()]);
+        } else if response.clicked() && self.mode == CompareMode::Blink {
+            self.show_preview = !self.show_preview;
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let retained = |image: &Option<RetainedImage>| {
+            image
+                .as_ref()
+                .map(|image| (image.texture_id(ui.ctx()), image.size()))
+        };
+        let draw = |painter: &egui::Painter, texture: Option<(TextureId, [usize; 2])>| {
+            if let Some((texture_id, size)) = texture {
+                let image_rect = Rect::from_min_max(
+                    to_screen(&viewport, rect, [0.0, 0.0]),
+                    to_screen(&viewport, rect, [size[0] as f32, size[1] as f32]),
+                );
+                painter.add(Shape::image(
+                    texture_id,
+                    image_rect,
+                    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                    Color32::WHITE,
+                ));
+            }
+        };
+
+        match self.mode {
+            CompareMode::Split => {
+                let divider_x = rect.left() + rect.width() * self.split;
+                let (left, right) = (
+                    Rect::from_min_max(rect.min, pos2(divider_x, rect.bottom())),
+                    Rect::from_min_max(pos2(divider_x, rect.top()), rect.max),
+                );
+                draw(&painter.with_clip_rect(left), retained(&self.current));
+                draw(&painter.with_clip_rect(right), retained(&self.preview));
+
+                painter.line_segment(
+                    [pos2(divider_x, rect.top()), pos2(divider_x, rect.bottom())],
+                    Stroke::new(2.0, Color32::WHITE),
+                );
+                if response.hovered() || self.dragging_divider {
+                    ui.output().cursor_icon = if self.dragging_divider
+                        || ui.input().pointer.hover_pos().map_or(false, |pos| {
+                            (pos.x - divider_x).abs() < DIVIDER_GRAB_DISTANCE
+                        }) {
+                        egui::CursorIcon::ResizeHorizontal
+                    } else {
+                        egui::CursorIcon::Grab
+                    };
+                }
+            }
+            CompareMode::Blink => {
+                if self.show_preview {
+                    draw(&painter, retained(&self.preview));
+                } else {
+                    draw(&painter, retained(&self.current));
+                }
+            }
+            CompareMode::Difference => draw(
+                &painter,
+                self.difference
+                    .as_ref()
+                    .map(|texture| (texture.id(), texture.size())),
+            ),
+        }
+    }
+}
+
+fn retained(name: &str, image: &Option<DynamicImage>) -> Option<RetainedImage> {
+    image
+        .as_ref()
+        .map(|image| RetainedImage::from_color_image(name, to_color_image(image)))
+}
+
+impl View for CompareWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Amplification => {
+                    self.amplification = self.viewmodel.get_amplification()
+                }
+                PropertyChangedNotification::BlinkInterval => {
+                    self.blink_interval = self.viewmodel.get_blink_interval()
+                }
+                PropertyChangedNotification::Current => {
+                    self.current = retained("compare current", &self.viewmodel.get_current())
+                }
+                PropertyChangedNotification::Busy => self.busy = self.viewmodel.get_busy(),
+                PropertyChangedNotification::Difference => self.update_difference(ctx),
+                PropertyChangedNotification::Mode => self.mode = self.viewmodel.get_mode(),
+                PropertyChangedNotification::Open => self.open = self.viewmodel.get_open(),
+                PropertyChangedNotification::Preview => {
+                    self.preview = retained("compare preview", &self.viewmodel.get_preview())
+                }
+                PropertyChangedNotification::Split => self.split = self.viewmodel.get_split(),
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Compare")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size([500.0, 400.0])
+            .min_width(250.0)
+            .min_height(200.0)
+            .show(ctx, |ui| self.ui(ui));
+        if open != self.open {
+            self.open = open;
+            self.viewmodel.set_open(open);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use super::View;
+use crate::app::model::components::{self, Component, Connectivity};
+use crate::app::viewmodel;
+use crate::app::viewmodel::components_window::PropertyChangedNotification;
+use egui::{vec2, Color32, Context, Sense, Ui};
+use egui_extras::{Size, TableBuilder};
+use tokio::sync::broadcast;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+pub struct ComponentsWindow {
+    // properties
+    busy: bool,
+    components: Vec<Component>,
+    connectivity: Connectivity,
+    is_binary: bool,
+    max_area: u32,
+    min_area: u32,
+    open: bool,
+    selected: Option<u32>,
+    total: usize,
+
+    // dependencies
+    viewmodel: viewmodel::ComponentsWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl ComponentsWindow {
+    pub fn new(viewmodel: viewmodel::ComponentsWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            busy: viewmodel.get_busy(),
+            components: viewmodel.get_components().clone(),
+            connectivity: viewmodel.get_connectivity(),
+            is_binary: viewmodel.get_is_binary(),
+            max_area: viewmodel.get_max_area(),
+            min_area: viewmodel.get_min_area(),
+            open: viewmodel.get_open(),
+            selected: viewmodel.get_selected(),
+            total: viewmodel.get_total(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.viewmodel.set_open(true);
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        if !self.is_binary {
+            ui.label("needs a binary Luma8 image, e.g. from a threshold");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("connectivity");
+            let mut connectivity = self.connectivity;
+            for (option, name) in Connectivity::ALL.iter().zip(Connectivity::NAMES) {
+                ui.selectable_value(&mut connectivity, *option, name);
+            }
+            if connectivity != self.connectivity {
+                self.connectivity = connectivity;
+                self.viewmodel.set_connectivity(connectivity);
+            }
+
+            ui.separator();
+
+            if ui
+                .button("preview")
+                .on_hover_text("show the components in their colours")
+                .clicked()
+            {
+                self.viewmodel.preview();
+            }
+
+            if self.busy {
+                ui.spinner();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("area");
+            let mut min_area = self.min_area;
+            let mut max_area = self.max_area;
+            let mut changed = ui
+                .add(egui::DragValue::new(&mut min_area).clamp_range(1..=u32::MAX))
+                .changed();
+            ui.label("to");
+            changed |= ui
+                .add(egui::DragValue::new(&mut max_area))
+                .on_hover_text("0 for no limit")
+                .changed();
+            if changed {
+                self.min_area = min_area;
+                self.max_area = max_area;
+                self.viewmodel.set_area_filter(min_area, max_area);
+            }
+
+            ui.separator();
+            ui.label(format!(
+                "{} of {} components",
+                self.components.len(),
+                self.total
+            ));
+        });
+
+        ui.separator();
+
+        let mut selected = self.selected;
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Size::exact(60.0))
+            .columns(Size::exact(70.0), 2)
+            .column(Size::exact(130.0))
+            .column(Size::exact(100.0))
+            .column(Size::remainder().at_least(70.0))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in [
+                    "label",
+                    "area",
+                    "perimeter",
+                    "bounds",
+                    "centroid",
+                    "eccentricity",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, self.components.len(), |index, mut row| {
+                    let component = &self.components[index];
+                    row.col(|ui| {
+                        let [r, g, b] = components::label_color(component.label).0;
+                        let (swatch, _) = ui.allocate_exact_size(vec2(10.0, 10.0), Sense::hover());
+                        ui.painter()
+                            .rect_filled(swatch, 0.0, Color32::from_rgb(r, g, b));
+                        let is_selected = selected == Some(component.label);
+                        if ui
+                            .selectable_label(is_selected, component.label.to_string())
+                            .on_hover_text("highlight in the image")
+                            .clicked()
+                        {
+                            selected = if is_selected {
+                                None
+                            } else {
+                                Some(component.label)
+                            };
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(component.area.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(component.perimeter.to_string());
+                    });
+                    row.col(|ui| {
+                        let [x, y, width, height] = component.bounds;
+                        ui.label(format!("{}x{} at {}, {}", width, height, x, y));
+                    });
+                    row.col(|ui| {
+                        let [x, y] = component.centroid;
+                        ui.label(format!("{:.1}, {:.1}", x, y));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.3}", component.eccentricity));
+                    });
+                });
+            });
+        if selected != self.selected {
+            self.selected = selected;
+            self.viewmodel.select(selected);
+        }
+    }
+}
+
+impl View for ComponentsWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Busy => self.busy = self.viewmodel.get_busy(),
+                PropertyChangedNotification::Components => {
+                    self.components = self.viewmodel.get_components().clone();
+                    self.total = self.viewmodel.get_total();
+                }
+                PropertyChangedNotification::Connectivity => {
+                    self.connectivity = self.viewmodel.get_connectivity()
+                }
+                PropertyChangedNotification::Filter => {
+                    self.min_area = self.viewmodel.get_min_area();
+                    self.max_area = self.viewmodel.get_max_area();
+                }
+                PropertyChangedNotification::IsBinary => {
+                    self.is_binary = self.viewmodel.get_is_binary()
+                }
+                PropertyChangedNotification::Open => self.open = self.viewmodel.get_open(),
+                PropertyChangedNotification::Selected => {
+                    self.selected = self.viewmodel.get_selected()
+                }
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Connected components")
+            .open(&mut open)
+            .collapsible(true)
+            .resizable(true)
+            .default_height(400.0)
+            .show(ctx, |ui| self.ui(ui));
+        if open != self.open {
+            self.open = open;
+            self.viewmodel.set_open(open);
+        }
+    }
+}
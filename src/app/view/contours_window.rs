@@ -0,0 +1,250 @@
+use super::View;
+use crate::app::model::contours::{Contour, ContourFormat, Descriptors};
+use crate::app::viewmodel::contours_window::{Overlay, PropertyChangedNotification};
+use crate::app::{modal, viewmodel};
+use egui::{Context, Ui};
+use egui_extras::{Size, TableBuilder};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+pub struct ContoursWindow {
+    // properties
+    busy: bool,
+    contours: Arc<Vec<(Contour, Descriptors)>>,
+    epsilon: f32,
+    format: ContourFormat,
+    is_binary: bool,
+    open: bool,
+    outer_only: bool,
+    overlay: Overlay,
+
+    // promises
+    save_file: modal::SaveFile,
+
+    // dependencies
+    viewmodel: viewmodel::ContoursWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl ContoursWindow {
+    pub fn new(viewmodel: viewmodel::ContoursWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            busy: viewmodel.get_busy(),
+            contours: viewmodel.get_contours(),
+            epsilon: viewmodel.get_epsilon(),
+            format: viewmodel.get_format(),
+            is_binary: viewmodel.get_is_binary(),
+            open: viewmodel.get_open(),
+            outer_only: viewmodel.get_outer_only(),
+            overlay: viewmodel.get_overlay(),
+            save_file: modal::SaveFile::default(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.viewmodel.set_open(true);
+    }
+
+    fn save(&mut self) {
+        if let Some(data) = self.viewmodel.encode() {
+            self.save_file.start(
+                self.viewmodel.get_file_name(),
+                self.format.extension(),
+                self.format.mime_type(),
+                data,
+            );
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        if !self.is_binary {
+            ui.label("needs a binary Luma8 image, e.g. from a threshold");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let mut epsilon = self.epsilon;
+            if ui
+                .add(egui::Slider::new(&mut epsilon, 0.0..=10.0).text("approximation"))
+                .on_hover_text("largest distance of a border pixel from the polygon, 0 keeps all")
+                .changed()
+            {
+                self.epsilon = epsilon;
+                self.viewmodel.set_epsilon(epsilon);
+            }
+
+            let mut outer_only = self.outer_only;
+            if ui
+                .checkbox(&mut outer_only, "outer only")
+                .on_hover_text("leave out holes and everything inside them")
+                .changed()
+            {
+                self.outer_only = outer_only;
+                self.viewmodel.set_outer_only(outer_only);
+            }
+
+            if self.busy {
+                ui.spinner();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("draw");
+            let mut overlay = self.overlay;
+            ui.checkbox(&mut overlay.convex_hull, "convex hull");
+            ui.checkbox(&mut overlay.min_area_rect, "rotated rectangle");
+            ui.checkbox(&mut overlay.enclosing_circle, "enclosing circle");
+            if overlay != self.overlay {
+                self.overlay = overlay;
+                self.viewmodel.set_overlay(overlay);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut format = self.format;
+            for option in ContourFormat::ALL {
+                ui.selectable_value(&mut format, option, option.name());
+            }
+            if format != self.format {
+                self.format = format;
+                self.viewmodel.set_format(format);
+            }
+
+            let busy = self.busy || self.save_file.is_busy();
+            if ui
+                .add_enabled(!busy, egui::Button::new("save..."))
+                .clicked()
+            {
+                self.save();
+            }
+
+            ui.separator();
+            ui.label(format!("{} contours", self.contours.len()));
+        });
+
+        ui.separator();
+
+        let contours = Arc::clone(&self.contours);
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Size::exact(40.0))
+            .columns(Size::exact(50.0), 3)
+            .columns(Size::exact(70.0), 2)
+            .column(Size::exact(150.0))
+            .column(Size::exact(120.0))
+            .column(Size::remainder().at_least(80.0))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in [
+                    "#",
+                    "parent",
+                    "kind",
+                    "points",
+                    "area",
+                    "perimeter",
+                    "rotated rectangle",
+                    "enclosing circle",
+                    "Hu moments",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, contours.len(), |index, mut row| {
+                    let (contour, descriptors) = &contours[index];
+                    row.col(|ui| {
+                        ui.label(index.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(contour.parent.map_or("-".to_string(), |p| p.to_string()));
+                    });
+                    row.col(|ui| {
+                        ui.label(if contour.is_hole { "hole" } else { "outer" });
+                    });
+                    row.col(|ui| {
+                        ui.label(contour.points.len().to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.1}", descriptors.area));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.1}", descriptors.perimeter));
+                    });
+                    row.col(|ui| {
+                        let rect = &descriptors.min_area_rect;
+                        ui.label(format!(
+                            "{:.1}x{:.1} at {:.1}°",
+                            rect.size[0], rect.size[1], rect.angle
+                        ));
+                    });
+                    row.col(|ui| {
+                        let circle = &descriptors.enclosing_circle;
+                        ui.label(format!(
+                            "r {:.1} at {:.1}, {:.1}",
+                            circle.radius, circle.center[0], circle.center[1]
+                        ));
+                    });
+                    row.col(|ui| {
+                        let hu = &descriptors.hu_moments;
+                        ui.label(format!("{:.3e}, {:.3e}, ...", hu[0], hu[1]))
+                            .on_hover_text(
+                                hu.iter()
+                                    .enumerate()
+                                    .map(|(index, value)| format!("h{} {:.6e}", index + 1, value))
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                            );
+                    });
+                });
+            });
+    }
+}
+
+impl View for ContoursWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        if let Some(result) = self.save_file.poll() {
+            self.viewmodel.report_saved(result);
+        }
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Busy => self.busy = self.viewmodel.get_busy(),
+                PropertyChangedNotification::Contours => {
+                    self.contours = self.viewmodel.get_contours()
+                }
+                PropertyChangedNotification::Epsilon => self.epsilon = self.viewmodel.get_epsilon(),
+                PropertyChangedNotification::Format => self.format = self.viewmodel.get_format(),
+                PropertyChangedNotification::IsBinary => {
+                    self.is_binary = self.viewmodel.get_is_binary()
+                }
+                PropertyChangedNotification::Open => self.open = self.viewmodel.get_open(),
+                PropertyChangedNotification::OuterOnly => {
+                    self.outer_only = self.viewmodel.get_outer_only()
+                }
+                PropertyChangedNotification::Overlay => self.overlay = self.viewmodel.get_overlay(),
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Contours")
+            .open(&mut open)
+            .collapsible(true)
+            .resizable(true)
+            .default_height(400.0)
+            .show(ctx, |ui| self.ui(ui));
+        if open != self.open {
+            self.open = open;
+            self.viewmodel.set_open(open);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use super::View;
+use crate::app::model::export::ExportFormat;
+use crate::app::viewmodel::export_window::PropertyChangedNotification;
+use crate::app::{modal, viewmodel};
+use egui::{Context, Ui};
+use image::ImageResult;
+use tokio::sync::{broadcast, oneshot};
+
+pub struct ExportWindow {
+    // properties
+    format: ExportFormat,
+    has_current: bool,
+    jpeg_quality: u8,
+    open: bool,
+
+    // promises
+    encode_promise: Option<oneshot::Receiver<ImageResult<Vec<u8>>>>,
+    save_file: modal::SaveFile,
+
+    // dependencies
+    viewmodel: viewmodel::ExportWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl ExportWindow {
+    pub fn new(viewmodel: viewmodel::ExportWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            format: viewmodel.get_format(),
+            has_current: viewmodel.get_has_current(),
+            jpeg_quality: viewmodel.get_jpeg_quality(),
+            open: false,
+            encode_promise: None,
+            save_file: modal::SaveFile::default(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    fn save(&mut self, data: Vec<u8>) {
+        self.save_file.start(
+            self.viewmodel.get_file_name(),
+            self.format.extension(),
+            self.format.mime_type(),
+            data,
+        );
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        let mut format = self.format;
+        egui::ComboBox::from_label("format")
+            .selected_text(format.name())
+            .show_ui(ui, |ui| {
+                for option in ExportFormat::ALL {
+                    ui.selectable_value(&mut format, option, option.name());
+                }
+            });
+        if format != self.format {
+            self.format = format;
+            self.viewmodel.set_format(format);
+        }
+
+        if self.format == ExportFormat::Jpeg {
+            let mut jpeg_quality = self.jpeg_quality;
+            if ui
+                .add(egui::Slider::new(&mut jpeg_quality, 1..=100).text("quality"))
+                .changed()
+            {
+                self.jpeg_quality = jpeg_quality;
+                self.viewmodel.set_jpeg_quality(jpeg_quality);
+            }
+        }
+
+        ui.separator();
+
+        let busy = self.encode_promise.is_some() || self.save_file.is_busy();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.has_current && !busy, egui::Button::new("save"))
+                .clicked()
+            {
+                self.encode_promise = self.viewmodel.encode();
+            }
+            if self.encode_promise.is_some() {
+                ui.spinner();
+            }
+        });
+    }
+}
+
+impl View for ExportWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        if let Some(encode_promise) = &mut self.encode_promise {
+            if let Ok(result) = encode_promise.try_recv() {
+                self.encode_promise.take();
+                if let Some(data) = self.viewmodel.report_encoded(result) {
+                    self.save(data);
+                }
+            }
+        }
+
+        if let Some(result) = self.save_file.poll() {
+            self.viewmodel.report_saved(result);
+        }
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Format => self.format = self.viewmodel.get_format(),
+                PropertyChangedNotification::HasCurrent => {
+                    self.has_current = self.viewmodel.get_has_current()
+                }
+                PropertyChangedNotification::JpegQuality => {
+                    self.jpeg_quality = self.viewmodel.get_jpeg_quality()
+                }
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| self.ui(ui));
+        self.open = open;
+    }
+}
@@ -0,0 +1,294 @@
+use super::View;
+use crate::app::model::histogram::{Channel, Histogram, BINS};
+use crate::app::viewmodel;
+use crate::app::viewmodel::histogram_window::{PropertyChangedNotification, Source};
+use egui::{pos2, vec2, Color32, Context, Sense, Shape, Stroke, Ui};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const PLOT_HEIGHT: f32 = 160.0;
+
+pub struct HistogramWindow {
+    // properties
+    busy: bool,
+    cumulative: bool,
+    has_roi: bool,
+    histogram: Option<Arc<Histogram>>,
+    log_scale: bool,
+    open: bool,
+    roi_only: bool,
+    source: Source,
+
+    // channels that are not drawn
+    hidden: Vec<Channel>,
+
+    // dependencies
+    viewmodel: viewmodel::HistogramWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl HistogramWindow {
+    pub fn new(viewmodel: viewmodel::HistogramWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            busy: viewmodel.get_busy(),
+            cumulative: viewmodel.get_cumulative(),
+            has_roi: viewmodel.get_has_roi(),
+            histogram: viewmodel.get_histogram(),
+            log_scale: viewmodel.get_log_scale(),
+            open: viewmodel.get_open(),
+            roi_only: viewmodel.get_roi_only(),
+            source: viewmodel.get_source(),
+            hidden: Vec::new(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.viewmodel.set_open(true);
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut source = self.source;
+            for option in Source::ALL {
+                ui.selectable_value(&mut source, option, option.name());
+            }
+            if source != self.source {
+                self.source = source;
+                self.viewmodel.set_source(source);
+            }
+
+            ui.separator();
+
+            let mut cumulative = self.cumulative;
+            if ui.checkbox(&mut cumulative, "cumulative").changed() {
+                self.cumulative = cumulative;
+                self.viewmodel.set_cumulative(cumulative);
+            }
+            let mut log_scale = self.log_scale;
+            if ui
+                .add_enabled(!self.cumulative, egui::Checkbox::new(&mut log_scale, "log"))
+                .changed()
+            {
+                self.log_scale = log_scale;
+                self.viewmodel.set_log_scale(log_scale);
+            }
+
+            let mut roi_only = self.roi_only;
+            if ui
+                .add_enabled(self.has_roi, egui::Checkbox::new(&mut roi_only, "roi only"))
+                .on_hover_text("only count the pixels in the region of interest")
+                .changed()
+            {
+                self.roi_only = roi_only;
+                self.viewmodel.set_roi_only(roi_only);
+            }
+
+            if self.busy {
+                ui.spinner();
+            }
+        });
+
+        let histogram = match &self.histogram {
+            Some(histogram) => Arc::clone(histogram),
+            None => {
+                ui.label("no image");
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            for channel in &histogram.channels {
+                let mut visible = !self.hidden.contains(&channel.channel);
+                if ui.checkbox(&mut visible, channel.channel.name()).changed() {
+                    if visible {
+                        self.hidden.retain(|hidden| *hidden != channel.channel);
+                    } else {
+                        self.hidden.push(channel.channel);
+                    }
+                }
+            }
+        });
+
+        self.plot_ui(ui, &histogram);
+        self.statistics_ui(ui, &histogram);
+    }
+
+    fn plot_ui(&self, ui: &mut Ui, histogram: &Histogram) {
+        let width = ui.available_width().max(BINS as f32);
+        let (rect, response) = ui.allocate_exact_size(vec2(width, PLOT_HEIGHT), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let visible = histogram
+            .channels
+            .iter()
+            .filter(|channel| !self.hidden.contains(&channel.channel));
+
+        // curves are scaled to the highest visible count, except the cumulative ones which
+        // always end at the pixel count
+        let scale = |count: u64, max_count: u64| -> f32 {
+            if max_count == 0 {
+                0.0
+            } else if self.log_scale && !self.cumulative {
+                (count as f32).ln_1p() / (max_count as f32).ln_1p()
+            } else {
+                count as f32 / max_count as f32
+            }
+        };
+        let max_count = if self.cumulative {
+            histogram.pixel_count
+        } else {
+            visible
+                .clone()
+                .flat_map(|channel| channel.bins.iter().copied())
+                .max()
+                .unwrap_or(0)
+        };
+
+        for channel in visible {
+            let counts = if self.cumulative {
+                channel.cumulative()
+            } else {
+                channel.bins.clone()
+            };
+            let points = counts
+                .iter()
+                .enumerate()
+                .map(|(bin, count)| {
+                    pos2(
+                        rect.left() + (bin as f32 + 0.5) / BINS as f32 * rect.width(),
+                        rect.bottom() - scale(*count, max_count) * rect.height(),
+                    )
+                })
+                .collect();
+            painter.add(Shape::line(
+                points,
+                Stroke::new(1.5, channel_color(channel.channel)),
+            ));
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let bin = (((pos.x - rect.left()) / rect.width() * BINS as f32) as usize).min(BINS - 1);
+            painter.line_segment(
+                [pos2(pos.x, rect.top()), pos2(pos.x, rect.bottom())],
+                Stroke::new(1.0, ui.visuals().weak_text_color()),
+            );
+
+            let bin_width = histogram.max_value / BINS as f32;
+            let text = histogram
+                .channels
+                .iter()
+                .map(|channel| {
+                    let count = if self.cumulative {
+                        channel.cumulative()[bin]
+                    } else {
+                        channel.bins[bin]
+                    };
+                    format!("{} {}", channel.channel.name(), count)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            response.on_hover_text(format!(
+                "{} .. {}\n{}",
+                format_value(bin as f32 * bin_width, histogram.max_value),
+                format_value((bin + 1) as f32 * bin_width, histogram.max_value),
+                text
+            ));
+        }
+    }
+
+    fn statistics_ui(&self, ui: &mut Ui, histogram: &Histogram) {
+        egui::Grid::new("histogram_statistics")
+            .striped(true)
+            .show(ui, |ui| {
+                for heading in ["", "min", "max", "mean", "std-dev", "median"] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+
+                for channel in &histogram.channels {
+                    let statistics = &channel.statistics;
+                    ui.colored_label(channel_color(channel.channel), channel.channel.name());
+                    for value in [
+                        statistics.min,
+                        statistics.max,
+                        statistics.mean,
+                        statistics.std_dev,
+                        statistics.median,
+                    ] {
+                        ui.monospace(format_value(value, histogram.max_value));
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.label(format!("{} pixels", histogram.pixel_count));
+    }
+}
+
+fn channel_color(channel: Channel) -> Color32 {
+    match channel {
+        Channel::Red => Color32::from_rgb(230, 60, 60),
+        Channel::Green => Color32::from_rgb(60, 200, 60),
+        Channel::Blue => Color32::from_rgb(80, 120, 255),
+        Channel::Luma => Color32::from_gray(170),
+        Channel::Alpha => Color32::from_rgb(200, 160, 255),
+    }
+}
+
+/// Float images get decimals, integer images do not need them for min, max and median.
+fn format_value(value: f32, max_value: f32) -> String {
+    if max_value <= 1.0 {
+        format!("{:.4}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+impl View for HistogramWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Busy => self.busy = self.viewmodel.get_busy(),
+                PropertyChangedNotification::Cumulative => {
+                    self.cumulative = self.viewmodel.get_cumulative()
+                }
+                PropertyChangedNotification::Histogram => {
+                    self.histogram = self.viewmodel.get_histogram()
+                }
+                PropertyChangedNotification::HasRoi => self.has_roi = self.viewmodel.get_has_roi(),
+                PropertyChangedNotification::RoiOnly => {
+                    self.roi_only = self.viewmodel.get_roi_only()
+                }
+                PropertyChangedNotification::LogScale => {
+                    self.log_scale = self.viewmodel.get_log_scale()
+                }
+                PropertyChangedNotification::Open => self.open = self.viewmodel.get_open(),
+                PropertyChangedNotification::Source => self.source = self.viewmodel.get_source(),
+            }
+        }
+
+        if self.busy {
+            // the result arrives from another thread, keep polling until it is there
+            ctx.request_repaint();
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Histogram")
+            .open(&mut open)
+            .collapsible(true)
+            .resizable(true)
+            .default_width(300.0)
+            .show(ctx, |ui| self.ui(ui));
+        if open != self.open {
+            self.open = open;
+            self.viewmodel.set_open(open);
+        }
+    }
+}
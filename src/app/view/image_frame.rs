@@ -1,18 +1,69 @@
 use super::View;
-use crate::app::viewmodel::image_frame::PropertyChangedNotification;
+use crate::app::model::display::{DisplayMapping, Renderer};
+use crate::app::model::pixel::PixelInfo;
+use crate::app::model::roi::{Region, Shape as RoiShape};
+use crate::app::model::task::TaskStatus;
+use crate::app::model::viewport::{Highlight, Outline, OutlineKind, Viewport};
+use crate::app::viewmodel::image_frame::{PropertyChangedNotification, RoiTool};
 use crate::app::{modal, viewmodel};
-use egui::{ColorImage, Context, Ui};
+use egui::{
+    pos2, vec2, Align2, Color32, ColorImage, Context, FontId, Key, PointerButton, Pos2, Rect,
+    Sense, Shape, Stroke, TextureHandle, Ui,
+};
 use egui_extras::RetainedImage;
 use image::DynamicImage;
 use rfd::FileHandle;
+use std::sync::Arc;
 use tokio::sync::{broadcast, oneshot};
 
+/// From this zoom level on, pixels are drawn as sharp squares instead of being interpolated.
+const NEAREST_ZOOM: f32 = 3.0;
+const ZOOM_STEP: f32 = 1.25;
+/// The loupe shows (2 * LOUPE_RADIUS + 1)^2 pixels around the cursor.
+const LOUPE_RADIUS: i64 = 3;
+const LOUPE_CELL_SIZE: f32 = 52.0;
+/// Screen radius of the handles of picked points.
+const HANDLE_RADIUS: f32 = 6.0;
+
+/// The visible part of the image, magnified with nearest neighbour sampling.
+struct Magnified {
+    region: [u32; 4],
+    factor: u32,
+    texture: TextureHandle,
+}
+
 pub struct ImageFrame {
     // properties
     accept_input: bool,
+    cursor: Option<[f32; 2]>,
+    display_mapping: DisplayMapping,
+    /// The values the display mapping shows as black and white, as fractions of the full range.
+    display_range: (f32, f32),
+    highlight: Arc<Option<Highlight>>,
     image: Option<RetainedImage>,
+    pixels: Option<ColorImage>,
+    magnified: Option<Magnified>,
+    hovered: Option<PixelInfo>,
+    hover_pos: Option<[f32; 2]>,
+    linked: bool,
+    loupe: bool,
     open: bool,
+    outlines: Arc<Vec<Outline>>,
+    points: Vec<(&'static str, Option<[f32; 2]>)>,
+    progress: Option<TaskStatus>,
+    roi: Arc<Option<Region>>,
+    roi_tool: RoiTool,
     title: String,
+    viewport: Viewport,
+    view_size: [f32; 2],
+
+    // the point handle being dragged and where it is, the parameter changes on release
+    dragged_point: Option<(usize, [f32; 2])>,
+    // the outline of a region of interest that is being drawn
+    drawing: Option<Vec<[f32; 2]>>,
+
+    // prepares the image for rendering it with changing display mappings
+    renderer: Renderer,
 
     // promises
     rfd_promise: Option<oneshot::Receiver<Option<FileHandle>>>,
@@ -28,43 +79,108 @@ impl ImageFrame {
 
         let mut result = Self {
             accept_input: viewmodel.get_accept_input(),
+            cursor: viewmodel.get_cursor(),
+            display_mapping: viewmodel.get_display_mapping(),
+            display_range: (0.0, 1.0),
+            highlight: viewmodel.get_highlight(),
             image: None,
+            pixels: None,
+            magnified: None,
+            hovered: None,
+            hover_pos: None,
+            linked: viewmodel.get_linked(),
+            loupe: viewmodel.get_loupe(),
             open: viewmodel.get_open(),
+            outlines: viewmodel.get_outlines(),
+            points: viewmodel.get_points().clone(),
+            progress: viewmodel.get_progress(),
+            roi: viewmodel.get_roi(),
+            roi_tool: viewmodel.get_roi_tool(),
             title: viewmodel.get_title().clone(),
+            viewport: viewmodel.get_viewport(),
+            view_size: [400.0, 400.0],
+            dragged_point: None,
+            drawing: None,
+            renderer: Renderer::new(Arc::new(None)),
             rfd_promise: None,
             vm_rx,
             viewmodel,
         };
 
-        result.set_image(&result.viewmodel.get_image());
+        result.set_image(result.viewmodel.get_image());
 
         result
     }
 
-    pub fn set_image(&mut self, image: &Option<DynamicImage>) {
-        match image {
-            Some(image) => {
-                let size = [image.width() as _, image.height() as _];
-                let image_buffer = image.to_rgba8();
-                let pixels = image_buffer.as_flat_samples();
-                let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+    /// `false` once the window has been closed.
+    pub fn is_open(&self) -> bool {
+        self.viewmodel.get_open()
+    }
+
+    pub fn set_image(&mut self, image: Arc<Option<DynamicImage>>) {
+        self.hovered = None;
+        self.renderer = Renderer::new(image);
+        self.render();
+    }
+
+    /// Maps the image to 8 bit with the current display mapping. Only the mapping is done again,
+    /// the renderer keeps what it prepared for the image.
+    fn render(&mut self) {
+        self.magnified = None;
+
+        match self.renderer.render(self.display_mapping) {
+            Some((rendered, range)) => {
+                self.display_range = range;
+                let color_image = ColorImage::from_rgba_unmultiplied(
+                    [rendered.width() as _, rendered.height() as _],
+                    rendered.as_raw(),
+                );
+                self.pixels = Some(color_image.clone());
                 self.image
-                    .replace(RetainedImage::from_color_image("foo", color_image));
+                    .replace(RetainedImage::from_color_image(&self.title, color_image));
+            }
+            None => {
+                self.image = None;
+                self.pixels = None;
             }
-            None => self.image = None,
         }
     }
 
+    fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.viewmodel.set_viewport(viewport);
+    }
+
     fn ui(&mut self, ui: &mut Ui) {
-        let Self { image, .. } = self;
+        if let Some(progress) = &self.progress {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.add(
+                    egui::ProgressBar::new(progress.progress)
+                        .text(format!(
+                            "{} {:.0}%",
+                            progress.name,
+                            progress.progress * 100.0
+                        ))
+                        .desired_width(200.0),
+                );
+                if ui.button("cancel").clicked() {
+                    self.viewmodel.cancel_operation();
+                }
+            });
+        }
 
-        match image {
-            Some(image) => {
-                let size = image.size();
-                let scale = 1f32 / (size[0].max(size[1]) as f32 / 300f32);
-                image.show_scaled(ui, scale);
+        let image_size = self.image.as_ref().map(|image| {
+            let size = image.size();
+            [size[0] as f32, size[1] as f32]
+        });
+
+        match image_size {
+            Some(image_size) => {
+                self.toolbar_ui(ui, image_size);
+                self.image_ui(ui, image_size);
             }
-            _ => {
+            None => {
                 ui.label("nothing to show");
                 if self.accept_input && ui.add(egui::widgets::Button::new("Open Image")).clicked() {
                     self.rfd_promise = Some(modal::open_file_dialog());
@@ -72,6 +188,694 @@ impl ImageFrame {
             }
         }
     }
+
+    fn toolbar_ui(&mut self, ui: &mut Ui, image_size: [f32; 2]) {
+        ui.horizontal(|ui| {
+            if ui.button("fit").clicked() {
+                self.set_viewport(Viewport::fit());
+            }
+            if ui.button("fill").clicked() {
+                self.set_viewport(Viewport::fill());
+            }
+            if ui.button("1:1").clicked() {
+                self.set_viewport(Viewport::actual_size(image_size));
+            }
+
+            // zoom around the center, using the image area size of the previous frame
+            let resolved = self.viewport.resolve(image_size, self.view_size);
+            if ui.button("-").clicked() {
+                self.set_viewport(resolved.zoom_around(1.0 / ZOOM_STEP, resolved.center));
+            }
+            ui.label(format!("{:.0}%", resolved.zoom * 100.0));
+            if ui.button("+").clicked() {
+                self.set_viewport(resolved.zoom_around(ZOOM_STEP, resolved.center));
+            }
+
+            ui.separator();
+
+            let mut linked = self.linked;
+            if ui
+                .checkbox(&mut linked, "link")
+                .on_hover_text("follow the zoom, pan and cursor of the other linked frames")
+                .changed()
+            {
+                self.linked = linked;
+                self.viewmodel.set_linked(linked);
+            }
+
+            let mut loupe = self.loupe;
+            if ui.checkbox(&mut loupe, "loupe").changed() {
+                self.loupe = loupe;
+                self.viewmodel.set_loupe(loupe);
+            }
+
+            ui.menu_button("display", |ui| self.display_ui(ui));
+            if self.accept_input {
+                ui.menu_button("roi", |ui| self.roi_ui(ui));
+            }
+        });
+    }
+
+    /// Settings for how pixel values are mapped to screen brightness.
+    fn display_ui(&mut self, ui: &mut Ui) {
+        let mut mapping = self.display_mapping;
+        let (low, high) = self.display_range;
+
+        if ui
+            .radio(mapping == DisplayMapping::FullRange, "full range")
+            .clicked()
+        {
+            mapping = DisplayMapping::FullRange;
+        }
+        let window = matches!(mapping, DisplayMapping::Window { .. });
+        if ui.radio(window, "window/level").clicked() && !window {
+            // start from what is shown at the moment, e.g. to fine-tune an auto-stretch
+            mapping = DisplayMapping::Window {
+                level: (low + high) / 2.0,
+                width: high - low,
+            };
+        }
+        let auto_stretch = matches!(mapping, DisplayMapping::AutoStretch { .. });
+        if ui.radio(auto_stretch, "auto-stretch").clicked() && !auto_stretch {
+            mapping = DisplayMapping::DEFAULT_AUTO_STRETCH;
+        }
+
+        match &mut mapping {
+            DisplayMapping::FullRange => {}
+            DisplayMapping::Window { level, width } => {
+                ui.add(
+                    egui::Slider::new(level, 0.0..=1.0)
+                        .clamp_to_range(false)
+                        .text("level"),
+                );
+                ui.add(
+                    egui::Slider::new(width, 0.001..=1.0)
+                        .logarithmic(true)
+                        .clamp_to_range(false)
+                        .text("width"),
+                );
+                *width = width.max(0.001);
+            }
+            DisplayMapping::AutoStretch { clip } => {
+                let mut percent = *clip * 100.0;
+                if ui
+                    .add(egui::Slider::new(&mut percent, 0.0..=10.0).text("clip %"))
+                    .changed()
+                {
+                    *clip = percent / 100.0;
+                }
+            }
+        }
+        ui.label(format!("black {:.4}  white {:.4}", low, high));
+
+        if mapping != self.display_mapping {
+            self.display_mapping = mapping;
+            self.viewmodel.set_display_mapping(mapping);
+        }
+    }
+
+    /// Tools for the region of interest and what can be done with it.
+    fn roi_ui(&mut self, ui: &mut Ui) {
+        let mut roi_tool = self.roi_tool;
+        for tool in RoiTool::ALL {
+            ui.radio_value(&mut roi_tool, tool, tool.name());
+        }
+        if roi_tool != self.roi_tool {
+            self.roi_tool = roi_tool;
+            self.drawing = None;
+            self.viewmodel.set_roi_tool(roi_tool);
+        }
+
+        ui.separator();
+
+        let roi = Arc::clone(&self.roi);
+        let region = match &*roi {
+            Some(region) => region,
+            None => {
+                ui.label("no region, operations change the whole image");
+                return;
+            }
+        };
+        let mut feather = region.feather;
+        if ui
+            .add(egui::Slider::new(&mut feather, 0.0..=64.0).text("feather"))
+            .changed()
+        {
+            self.viewmodel.set_roi_feather(feather);
+        }
+        let [[x0, y0], [x1, y1]] = region.bounds();
+        ui.label(format!(
+            "{:.0}, {:.0} to {:.0}, {:.0}",
+            x0.max(0.0),
+            y0.max(0.0),
+            x1,
+            y1
+        ));
+        if ui.button("crop to roi").clicked() {
+            self.viewmodel.crop_to_roi();
+            ui.close_menu();
+        }
+        if ui.button("clear").clicked() {
+            self.viewmodel.set_roi(None);
+            ui.close_menu();
+        }
+    }
+
+    fn image_ui(&mut self, ui: &mut Ui, image_size: [f32; 2]) {
+        let status_height =
+            ui.text_style_height(&egui::TextStyle::Body) + ui.spacing().item_spacing.y;
+        let desired_size = (ui.available_size() - vec2(0.0, status_height)).max(vec2(64.0, 64.0));
+        let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
+        self.view_size = [rect.width(), rect.height()];
+        let mut viewport = self
+            .viewport
+            .resolve(image_size, [rect.width(), rect.height()]);
+
+        // picking points of the active operation takes precedence over drawing a region
+        let draws_roi = self.points.is_empty() && self.roi_tool != RoiTool::None;
+        if !self.points.is_empty() {
+            self.point_input(ui, &response, &viewport, rect);
+        } else if draws_roi {
+            self.roi_input(ui, &response, &viewport, rect);
+        }
+
+        let mut changed = None;
+        if response.hovered() {
+            let (scroll, zoom_delta, hover_pos) = {
+                let input = ui.input();
+                (
+                    input.scroll_delta.y,
+                    input.zoom_delta(),
+                    input.pointer.hover_pos(),
+                )
+            };
+            let factor = zoom_delta * (scroll * 0.002).exp();
+            if let Some(hover_pos) = hover_pos {
+                if (factor - 1.0).abs() > f32::EPSILON {
+                    let anchor = to_image(&viewport, rect, hover_pos);
+                    changed = Some(viewport.zoom_around(factor, anchor));
+                }
+            }
+        }
+        // the secondary button always pans
+        let captured = self.dragged_point.is_some() || draws_roi;
+        if response.dragged() && !(captured && response.dragged_by(PointerButton::Primary)) {
+            let delta = response.drag_delta();
+            changed = Some(changed.unwrap_or(viewport).pan([delta.x, delta.y]));
+        }
+        if response.double_clicked() && self.roi_tool != RoiTool::Polygon {
+            changed = Some(Viewport::fit());
+        }
+        if let Some(new_viewport) = changed {
+            self.set_viewport(new_viewport);
+            viewport = new_viewport.resolve(image_size, [rect.width(), rect.height()]);
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let image_rect = Rect::from_min_max(
+            to_screen(&viewport, rect, [0.0, 0.0]),
+            to_screen(&viewport, rect, image_size),
+        );
+
+        if viewport.zoom >= NEAREST_ZOOM {
+            if let Some((texture_id, region_rect)) = self.magnified_texture(ui, &viewport, rect) {
+                painter.add(Shape::image(
+                    texture_id,
+                    region_rect,
+                    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                    Color32::WHITE,
+                ));
+            }
+        } else if let Some(image) = &self.image {
+            painter.add(Shape::image(
+                image.texture_id(ui.ctx()),
+                image_rect,
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                Color32::WHITE,
+            ));
+        }
+
+        let hover_pos = response
+            .hover_pos()
+            .map(|hover_pos| to_image(&viewport, rect, hover_pos));
+        if hover_pos != self.hover_pos {
+            self.hover_pos = hover_pos;
+            self.viewmodel.report_cursor(hover_pos);
+        }
+        self.hovered = hover_pos.and_then(|[x, y]| {
+            if x < 0.0 || y < 0.0 {
+                return None;
+            }
+            self.viewmodel.get_pixel_info(x as u32, y as u32)
+        });
+
+        paint_outlines(&painter, &viewport, rect, &self.outlines);
+        self.paint_roi(&painter, &viewport, rect, response.hover_pos());
+        if let Some(highlight) = &*self.highlight {
+            paint_highlight(&painter, &viewport, rect, highlight);
+        }
+        if !self.points.is_empty() {
+            self.paint_points(&painter, &viewport, rect);
+        }
+
+        // crosshair at the position the mouse has in another linked frame
+        if let (None, Some(cursor)) = (hover_pos, self.cursor) {
+            let center = to_screen(&viewport, rect, cursor);
+            if rect.contains(center) {
+                let stroke = Stroke::new(1.0, Color32::from_rgb(255, 64, 64));
+                painter.line_segment(
+                    [pos2(rect.left(), center.y), pos2(rect.right(), center.y)],
+                    stroke,
+                );
+                painter.line_segment(
+                    [pos2(center.x, rect.top()), pos2(center.x, rect.bottom())],
+                    stroke,
+                );
+            }
+        }
+
+        let status = match &self.hovered {
+            Some(info) => format!("x {} y {}  {}", info.x, info.y, info.values_text()),
+            None => format!("{}x{}", image_size[0] as u32, image_size[1] as u32),
+        };
+        ui.label(egui::RichText::new(status).monospace());
+
+        if self.loupe {
+            if let Some(info) = &self.hovered {
+                let (x, y) = (info.x, info.y);
+                egui::show_tooltip_at_pointer(
+                    ui.ctx(),
+                    egui::Id::new((&self.title, "loupe")),
+                    |ui| self.loupe_ui(ui, x, y),
+                );
+            }
+        }
+    }
+
+    /// Clicks pick the next unset point, dragging a handle moves its point.
+    fn point_input(&mut self, ui: &Ui, response: &egui::Response, viewport: &Viewport, rect: Rect) {
+        if response.drag_started() {
+            let press_origin = ui.input().pointer.press_origin();
+            self.dragged_point = press_origin.and_then(|origin| {
+                self.points
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, (_, point))| {
+                        let distance = to_screen(viewport, rect, (*point)?).distance(origin);
+                        Some((index, distance))
+                    })
+                    .filter(|(_, distance)| *distance <= 2.0 * HANDLE_RADIUS)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| (index, to_image(viewport, rect, origin)))
+            });
+        }
+
+        if let Some((index, position)) = &mut self.dragged_point {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                *position = to_image(viewport, rect, pointer);
+            }
+            if response.drag_released() {
+                self.viewmodel.move_point(*index, *position);
+                self.dragged_point = None;
+            }
+        } else if response.clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                self.viewmodel.pick_point(to_image(viewport, rect, pointer));
+            }
+        }
+    }
+
+    /// Draws a new region of interest with the selected tool. Escape cancels the drawing.
+    fn roi_input(&mut self, ui: &Ui, response: &egui::Response, viewport: &Viewport, rect: Rect) {
+        if ui.input().key_pressed(Key::Escape) {
+            self.drawing = None;
+            return;
+        }
+        let pointer = response
+            .interact_pointer_pos()
+            .map(|pointer| to_image(viewport, rect, pointer));
+
+        match self.roi_tool {
+            RoiTool::None => {}
+            RoiTool::Rectangle | RoiTool::Ellipse | RoiTool::Freehand => {
+                if response.dragged_by(PointerButton::Primary) {
+                    if self.drawing.is_none() {
+                        let press_origin = ui.input().pointer.press_origin();
+                        self.drawing =
+                            press_origin.map(|origin| vec![to_image(viewport, rect, origin)]);
+                    }
+                    if let (Some(drawing), Some(pointer)) = (&mut self.drawing, pointer) {
+                        if self.roi_tool == RoiTool::Freehand {
+                            // a new point for every screen pixel moved
+                            let last = drawing[drawing.len() - 1];
+                            let distance = (pointer[0] - last[0]).hypot(pointer[1] - last[1]);
+                            if distance * viewport.zoom >= 1.0 {
+                                drawing.push(pointer);
+                            }
+                        } else {
+                            drawing.truncate(1);
+                            drawing.push(pointer);
+                        }
+                    }
+                }
+                if response.drag_released() {
+                    self.finish_roi();
+                }
+            }
+            RoiTool::Polygon => {
+                if response.double_clicked() {
+                    self.finish_roi();
+                } else if let (true, Some(pointer)) =
+                    (response.clicked_by(PointerButton::Primary), pointer)
+                {
+                    let drawing = self.drawing.get_or_insert_with(Vec::new);
+                    let closes = drawing.len() >= 3 && {
+                        let first = to_screen(viewport, rect, drawing[0]);
+                        first.distance(to_screen(viewport, rect, pointer)) <= 2.0 * HANDLE_RADIUS
+                    };
+                    if closes {
+                        self.finish_roi();
+                    } else {
+                        drawing.push(pointer);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The region the current drawing describes, with the feather of the previous region.
+    fn drawn_region(&self) -> Option<Region> {
+        let drawing = self.drawing.as_ref()?;
+        let shape = match self.roi_tool {
+            RoiTool::None => return None,
+            RoiTool::Rectangle | RoiTool::Ellipse => {
+                let corners = [drawing[0], *drawing.get(1)?];
+                if corners[0][0] == corners[1][0] || corners[0][1] == corners[1][1] {
+                    return None;
+                }
+                match self.roi_tool {
+                    RoiTool::Rectangle => RoiShape::Rectangle(corners),
+                    _ => RoiShape::Ellipse(corners),
+                }
+            }
+            RoiTool::Polygon | RoiTool::Freehand => {
+                if drawing.len() < 3 {
+                    return None;
+                }
+                RoiShape::Polygon(drawing.clone())
+            }
+        };
+        Some(Region {
+            shape,
+            feather: (*self.roi).as_ref().map_or(0.0, |region| region.feather),
+        })
+    }
+
+    fn finish_roi(&mut self) {
+        if let Some(region) = self.drawn_region() {
+            self.viewmodel.set_roi(Some(region));
+        }
+        self.drawing = None;
+    }
+
+    /// Outlines the region of interest and the one being drawn.
+    fn paint_roi(
+        &self,
+        painter: &egui::Painter,
+        viewport: &Viewport,
+        rect: Rect,
+        hover_pos: Option<Pos2>,
+    ) {
+        let to_screen_points = |points: Vec<[f32; 2]>| -> Vec<Pos2> {
+            points
+                .into_iter()
+                .map(|point| to_screen(viewport, rect, point))
+                .collect()
+        };
+
+        if let Some(region) = &*self.roi {
+            let color = Color32::from_rgb(0, 220, 255);
+            let outline = to_screen_points(region.outline());
+            painter.add(Shape::closed_line(outline, Stroke::new(1.5, color)));
+        }
+
+        let drawing = match &self.drawing {
+            Some(drawing) => drawing,
+            None => return,
+        };
+        let stroke = Stroke::new(1.0, Color32::from_rgb(255, 255, 255));
+        match self.roi_tool {
+            RoiTool::Polygon | RoiTool::Freehand => {
+                let mut points = to_screen_points(drawing.clone());
+                // the edge the next click of the polygon tool would add
+                if let (RoiTool::Polygon, Some(hover_pos)) = (self.roi_tool, hover_pos) {
+                    points.push(hover_pos);
+                }
+                painter.add(Shape::line(points, stroke));
+            }
+            _ => {
+                if let Some(region) = self.drawn_region() {
+                    let outline = to_screen_points(region.outline());
+                    painter.add(Shape::closed_line(outline, stroke));
+                }
+            }
+        }
+    }
+
+    /// Outlines the picked points in order and draws a labelled handle on each.
+    fn paint_points(&self, painter: &egui::Painter, viewport: &Viewport, rect: Rect) {
+        let color = Color32::from_rgb(255, 200, 0);
+        let positions: Vec<Option<Pos2>> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(index, (_, point))| match self.dragged_point {
+                Some((dragged, position)) if dragged == index => Some(position),
+                _ => *point,
+            })
+            .map(|point| point.map(|point| to_screen(viewport, rect, point)))
+            .collect();
+
+        let count = positions.len();
+        for (index, position) in positions.iter().enumerate() {
+            // close the outline once all points are there
+            let next = positions[(index + 1) % count];
+            if let (Some(position), Some(next)) = (position, next) {
+                if index + 1 < count || positions.iter().all(Option::is_some) {
+                    painter.line_segment([*position, next], Stroke::new(1.5, color));
+                }
+            }
+        }
+        for ((name, _), position) in self.points.iter().zip(&positions) {
+            if let Some(position) = position {
+                painter.circle_stroke(*position, HANDLE_RADIUS, Stroke::new(2.0, color));
+                painter.text(
+                    *position + vec2(HANDLE_RADIUS + 2.0, -HANDLE_RADIUS - 2.0),
+                    Align2::LEFT_BOTTOM,
+                    *name,
+                    FontId::proportional(12.0),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Draws the neighbourhood of `x`, `y` as a grid of enlarged pixels with their raw values.
+    fn loupe_ui(&self, ui: &mut Ui, x: u32, y: u32) {
+        let pixels = match &self.pixels {
+            Some(pixels) => pixels,
+            None => return,
+        };
+        let [width, height] = pixels.size;
+
+        let cells = (2 * LOUPE_RADIUS + 1) as f32;
+        let (response, painter) =
+            ui.allocate_painter(vec2(cells, cells) * LOUPE_CELL_SIZE, Sense::hover());
+        let origin = response.rect.min;
+
+        for dy in -LOUPE_RADIUS..=LOUPE_RADIUS {
+            for dx in -LOUPE_RADIUS..=LOUPE_RADIUS {
+                let cell = Rect::from_min_size(
+                    origin
+                        + vec2((dx + LOUPE_RADIUS) as f32, (dy + LOUPE_RADIUS) as f32)
+                            * LOUPE_CELL_SIZE,
+                    vec2(LOUPE_CELL_SIZE, LOUPE_CELL_SIZE),
+                );
+
+                let (px, py) = (x as i64 + dx, y as i64 + dy);
+                if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                    painter.rect_filled(cell, 0.0, ui.visuals().extreme_bg_color);
+                    continue;
+                }
+
+                let color = pixels.pixels[py as usize * width + px as usize];
+                painter.rect_filled(cell, 0.0, color);
+
+                let luminance =
+                    0.3 * color.r() as f32 + 0.59 * color.g() as f32 + 0.11 * color.b() as f32;
+                let text_color = if luminance > 128.0 {
+                    Color32::BLACK
+                } else {
+                    Color32::WHITE
+                };
+                if let Some(info) = self.viewmodel.get_pixel_info(px as u32, py as u32) {
+                    let text = info
+                        .channels
+                        .iter()
+                        .map(|(_, value)| value.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    painter.text(
+                        cell.center(),
+                        Align2::CENTER_CENTER,
+                        text,
+                        FontId::monospace(10.0),
+                        text_color,
+                    );
+                }
+            }
+        }
+
+        let center = Rect::from_min_size(
+            origin + vec2(LOUPE_RADIUS as f32, LOUPE_RADIUS as f32) * LOUPE_CELL_SIZE,
+            vec2(LOUPE_CELL_SIZE, LOUPE_CELL_SIZE),
+        );
+        painter.rect_stroke(center, 0.0, Stroke::new(2.0, Color32::RED));
+    }
+
+    /// Returns a texture of the visible image region scaled up by an integer factor, and the
+    /// screen rectangle it has to be drawn to.
+    fn magnified_texture(
+        &mut self,
+        ui: &Ui,
+        viewport: &Viewport,
+        rect: Rect,
+    ) -> Option<(egui::TextureId, Rect)> {
+        let pixels = self.pixels.as_ref()?;
+        let [width, height] = pixels.size;
+
+        let min = to_image(viewport, rect, rect.min);
+        let max = to_image(viewport, rect, rect.max);
+        let x0 = min[0].floor().clamp(0.0, width as f32) as u32;
+        let y0 = min[1].floor().clamp(0.0, height as f32) as u32;
+        let x1 = max[0].ceil().clamp(0.0, width as f32) as u32;
+        let y1 = max[1].ceil().clamp(0.0, height as f32) as u32;
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+
+        let region = [x0, y0, x1, y1];
+        let factor = viewport.zoom.ceil() as u32;
+        let up_to_date = matches!(
+            &self.magnified,
+            Some(magnified) if magnified.region == region && magnified.factor == factor
+        );
+        if !up_to_date {
+            let region_width = ((x1 - x0) * factor) as usize;
+            let region_height = ((y1 - y0) * factor) as usize;
+            let mut magnified = ColorImage::new([region_width, region_height], Color32::BLACK);
+            for y in 0..region_height {
+                let source_row = (y0 as usize + y / factor as usize) * width;
+                for x in 0..region_width {
+                    magnified.pixels[y * region_width + x] =
+                        pixels.pixels[source_row + x0 as usize + x / factor as usize];
+                }
+            }
+
+            self.magnified = Some(Magnified {
+                region,
+                factor,
+                texture: ui
+                    .ctx()
+                    .load_texture(format!("{} (magnified)", self.title), magnified),
+            });
+        }
+
+        let region_rect = Rect::from_min_max(
+            to_screen(viewport, rect, [x0 as f32, y0 as f32]),
+            to_screen(viewport, rect, [x1 as f32, y1 as f32]),
+        );
+        self.magnified
+            .as_ref()
+            .map(|magnified| (magnified.texture.id(), region_rect))
+    }
+}
+
+/// Draws `outlines` as lines that keep their width at any zoom.
+fn paint_outlines(painter: &egui::Painter, viewport: &Viewport, rect: Rect, outlines: &[Outline]) {
+    for outline in outlines {
+        let color = match outline.kind {
+            OutlineKind::Contour => Color32::from_rgb(0, 255, 96),
+            OutlineKind::Hole => Color32::from_rgb(255, 96, 64),
+            OutlineKind::ConvexHull => Color32::from_rgb(255, 255, 0),
+            OutlineKind::Rectangle => Color32::from_rgb(64, 160, 255),
+            OutlineKind::Circle => Color32::from_rgb(200, 120, 255),
+        };
+        let points: Vec<Pos2> = outline
+            .points
+            .iter()
+            .map(|point| to_screen(viewport, rect, *point))
+            .collect();
+        if let [point] = points.as_slice() {
+            painter.circle_filled(*point, 1.5, color);
+        } else {
+            painter.add(Shape::closed_line(points, Stroke::new(1.5, color)));
+        }
+    }
+}
+
+/// Tints the pixels of `highlight` and frames its bounds with a margin, so that small areas stay
+/// visible when zoomed out.
+fn paint_highlight(
+    painter: &egui::Painter,
+    viewport: &Viewport,
+    rect: Rect,
+    highlight: &Highlight,
+) {
+    let tint = Color32::from_rgba_unmultiplied(255, 0, 255, 96);
+    for [y, x0, x1] in &highlight.runs {
+        let run = Rect::from_min_max(
+            to_screen(viewport, rect, [*x0 as f32, *y as f32]),
+            to_screen(viewport, rect, [*x1 as f32, (y + 1) as f32]),
+        );
+        if rect.intersects(run) {
+            painter.rect_filled(run, 0.0, tint);
+        }
+    }
+
+    let [min, max] = highlight.bounds;
+    let area = Rect::from_min_max(
+        to_screen(viewport, rect, min),
+        to_screen(viewport, rect, max),
+    )
+    .expand(3.0);
+    painter.rect_stroke(area, 0.0, Stroke::new(3.0, Color32::BLACK));
+    painter.rect_stroke(area, 0.0, Stroke::new(1.5, Color32::from_rgb(255, 0, 255)));
+}
+
+pub(super) fn to_color_image(image: &DynamicImage) -> ColorImage {
+    let size = [image.width() as _, image.height() as _];
+    let image_buffer = image.to_rgba8();
+    let pixels = image_buffer.as_flat_samples();
+    ColorImage::from_rgba_unmultiplied(size, pixels.as_slice())
+}
+
+pub(super) fn to_screen(viewport: &Viewport, rect: Rect, image_pos: [f32; 2]) -> Pos2 {
+    rect.center()
+        + vec2(
+            (image_pos[0] - viewport.center[0]) * viewport.zoom,
+            (image_pos[1] - viewport.center[1]) * viewport.zoom,
+        )
+}
+
+pub(super) fn to_image(viewport: &Viewport, rect: Rect, screen_pos: Pos2) -> [f32; 2] {
+    let offset = screen_pos - rect.center();
+    [
+        viewport.center[0] + offset.x / viewport.zoom,
+        viewport.center[1] + offset.y / viewport.zoom,
+    ]
 }
 
 impl View for ImageFrame {
@@ -88,16 +892,43 @@ impl View for ImageFrame {
         let mut open = self.open;
         let title = self.title.clone();
 
-        if let Ok(notification) = self.vm_rx.try_recv() {
+        while let Ok(notification) = self.vm_rx.try_recv() {
             match notification {
                 PropertyChangedNotification::AcceptInput => {
                     self.accept_input = self.viewmodel.get_accept_input()
                 }
-                PropertyChangedNotification::Image => self.set_image(&*self.viewmodel.get_image()),
+                PropertyChangedNotification::Cursor => self.cursor = self.viewmodel.get_cursor(),
+                PropertyChangedNotification::DisplayMapping => {
+                    self.display_mapping = self.viewmodel.get_display_mapping();
+                    self.render();
+                }
+                PropertyChangedNotification::Highlight => {
+                    self.highlight = self.viewmodel.get_highlight()
+                }
+                PropertyChangedNotification::Image => self.set_image(self.viewmodel.get_image()),
+                PropertyChangedNotification::Linked => self.linked = self.viewmodel.get_linked(),
+                PropertyChangedNotification::Loupe => self.loupe = self.viewmodel.get_loupe(),
                 PropertyChangedNotification::Open => self.open = self.viewmodel.get_open(),
+                PropertyChangedNotification::Outlines => {
+                    self.outlines = self.viewmodel.get_outlines()
+                }
+                PropertyChangedNotification::Points => {
+                    self.points = self.viewmodel.get_points().clone();
+                    self.dragged_point = None;
+                }
+                PropertyChangedNotification::Roi => self.roi = self.viewmodel.get_roi(),
+                PropertyChangedNotification::RoiTool => {
+                    self.roi_tool = self.viewmodel.get_roi_tool()
+                }
+                PropertyChangedNotification::Progress => {
+                    self.progress = self.viewmodel.get_progress()
+                }
                 PropertyChangedNotification::Title => {
                     self.title = self.viewmodel.get_title().clone()
                 }
+                PropertyChangedNotification::Viewport => {
+                    self.viewport = self.viewmodel.get_viewport()
+                }
             }
         }
 
@@ -105,9 +936,10 @@ impl View for ImageFrame {
             .open(&mut open)
             //.closable(false)
             .collapsible(false)
-            .resizable(false)
-            .default_width(300.0)
-            .default_height(300.0)
+            .resizable(true)
+            .default_size([400.0, 400.0])
+            .min_width(200.0)
+            .min_height(150.0)
             .show(ctx, |ui| self.ui(ui));
 
         self.viewmodel.set_open(open);
@@ -0,0 +1,108 @@
+use super::View;
+use crate::app::model::log::{LogEntries, LogEntry, Severity};
+use crate::app::viewmodel;
+use crate::app::viewmodel::log::PropertyChangedNotification;
+use egui::{Color32, Context, Ui};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// A collapsible panel at the bottom of the window listing all log entries.
+pub struct LogPanel {
+    // properties
+    entries: Arc<LogEntries>,
+    errors_only: bool,
+    expanded: bool,
+
+    // dependencies
+    viewmodel: viewmodel::Log,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl LogPanel {
+    pub fn new(viewmodel: viewmodel::Log) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            entries: viewmodel.get_entries(),
+            errors_only: false,
+            expanded: false,
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        let error_count = self
+            .entries
+            .iter()
+            .filter(|entry| entry.severity == Severity::Error)
+            .count();
+
+        ui.horizontal(|ui| {
+            let label = if self.expanded {
+                "hide log"
+            } else {
+                "show log"
+            };
+            if ui.small_button(label).clicked() {
+                self.expanded = !self.expanded;
+            }
+
+            ui.label(format!("{} errors", error_count));
+
+            if !self.expanded {
+                if let Some(entry) = self.entries.back() {
+                    entry_ui(ui, entry);
+                }
+            }
+        });
+
+        if !self.expanded {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.errors_only, "errors only");
+            if ui
+                .add_enabled(!self.entries.is_empty(), egui::Button::new("clear"))
+                .clicked()
+            {
+                self.viewmodel.clear();
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .stick_to_bottom()
+            .show(ui, |ui| {
+                for entry in self.entries.iter() {
+                    if !self.errors_only || entry.severity == Severity::Error {
+                        entry_ui(ui, entry);
+                    }
+                }
+            });
+    }
+}
+
+fn entry_ui(ui: &mut Ui, entry: &LogEntry) {
+    match entry.severity {
+        Severity::Info => ui.label(&entry.message),
+        Severity::Error => ui.colored_label(Color32::from_rgb(230, 80, 80), &entry.message),
+    };
+}
+
+impl View for LogPanel {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Entries => self.entries = self.viewmodel.get_entries(),
+            }
+        }
+
+        egui::TopBottomPanel::bottom("log_panel").show(ctx, |ui| self.ui(ui));
+    }
+}
@@ -1,9 +1,27 @@
 pub mod central_panel;
+pub mod channels_window;
+pub mod compare_window;
+pub mod components_window;
+pub mod contours_window;
+pub mod export_window;
+pub mod histogram_window;
 pub mod image_frame;
+pub mod log_panel;
+pub mod notifications;
+pub mod parameter_window;
 pub mod top_panel;
 
 pub use central_panel::CentralPanel;
+pub use channels_window::ChannelsWindow;
+pub use compare_window::CompareWindow;
+pub use components_window::ComponentsWindow;
+pub use contours_window::ContoursWindow;
+pub use export_window::ExportWindow;
+pub use histogram_window::HistogramWindow;
 pub use image_frame::ImageFrame;
+pub use log_panel::LogPanel;
+pub use notifications::Notifications;
+pub use parameter_window::ParameterWindow;
 pub use top_panel::TopPanel;
 
 pub trait View {
@@ -0,0 +1,111 @@
+use super::View;
+use crate::app::model::log::{LogEntry, Severity};
+use crate::app::viewmodel;
+use crate::app::viewmodel::log::PropertyChangedNotification;
+use egui::{Align2, Color32, Context};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const INFO_DURATION: f64 = 4.0;
+const ERROR_DURATION: f64 = 10.0;
+
+struct Toast {
+    entry: Arc<LogEntry>,
+    shown_at: Option<f64>,
+}
+
+/// Shows new log entries as toasts in the bottom right corner.
+pub struct Notifications {
+    // properties
+    toasts: Vec<Toast>,
+    last_seen_id: Option<u64>,
+
+    // dependencies
+    viewmodel: viewmodel::Log,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl Notifications {
+    pub fn new(viewmodel: viewmodel::Log) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+        let last_seen_id = viewmodel.get_entries().back().map(|entry| entry.id);
+
+        Self {
+            toasts: Vec::new(),
+            last_seen_id,
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    fn add_new_entries(&mut self) {
+        let entries = self.viewmodel.get_entries();
+        for entry in entries.iter() {
+            if self.last_seen_id.map_or(true, |id| entry.id > id) {
+                self.toasts.push(Toast {
+                    entry: entry.clone(),
+                    shown_at: None,
+                });
+                self.last_seen_id = Some(entry.id);
+            }
+        }
+    }
+}
+
+impl View for Notifications {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Entries => self.add_new_entries(),
+            }
+        }
+
+        let now = ctx.input().time;
+        self.toasts.retain(|toast| {
+            let duration = match toast.entry.severity {
+                Severity::Info => INFO_DURATION,
+                Severity::Error => ERROR_DURATION,
+            };
+            toast
+                .shown_at
+                .map_or(true, |shown_at| now - shown_at < duration)
+        });
+
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new("notifications")
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (index, toast) in self.toasts.iter_mut().enumerate() {
+                    toast.shown_at.get_or_insert(now);
+
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(350.0);
+                        ui.horizontal(|ui| {
+                            let color = match toast.entry.severity {
+                                Severity::Info => ui.visuals().text_color(),
+                                Severity::Error => Color32::from_rgb(230, 80, 80),
+                            };
+                            if ui.small_button("x").clicked() {
+                                dismissed = Some(index);
+                            }
+                            ui.colored_label(color, &toast.entry.message);
+                        });
+                    });
+                }
+            });
+
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+
+        // keep repainting so that toasts disappear without user input
+        ctx.request_repaint();
+    }
+}
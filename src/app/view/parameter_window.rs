@@ -0,0 +1,258 @@
+use super::View;
+use crate::app::model::parameter::{Matrix, Parameter, ParameterKind, Parameters, Value};
+use crate::app::viewmodel;
+use crate::app::viewmodel::parameter_window::PropertyChangedNotification;
+use egui::{Context, Ui};
+use tokio::sync::broadcast;
+
+pub struct ParameterWindow {
+    // properties
+    busy: bool,
+    notes: Vec<String>,
+    operation_name: Option<&'static str>,
+    parameters: Vec<Parameter>,
+    values: Parameters,
+
+    // dependencies
+    viewmodel: viewmodel::ParameterWindow,
+    vm_rx: broadcast::Receiver<PropertyChangedNotification>,
+}
+
+impl ParameterWindow {
+    pub fn new(viewmodel: viewmodel::ParameterWindow) -> Self {
+        let vm_rx = viewmodel.get_receiver();
+
+        Self {
+            busy: viewmodel.get_busy(),
+            notes: viewmodel.get_notes().clone(),
+            operation_name: viewmodel.get_operation_name(),
+            parameters: viewmodel.get_parameters().clone(),
+            values: viewmodel.get_values().clone(),
+            viewmodel,
+            vm_rx,
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        if self.parameters.is_empty() {
+            ui.label("This operation has no parameters.");
+        }
+
+        for parameter in &self.parameters {
+            let mut value = match self.values.get(parameter.name) {
+                Some(value) => value,
+                None => parameter.default_value(),
+            };
+
+            if parameter_ui(ui, parameter, &mut value) {
+                self.values.set(parameter.name, value.clone());
+                self.viewmodel.set_value(parameter.name, value);
+            }
+        }
+
+        if !self.notes.is_empty() {
+            ui.separator();
+            for note in &self.notes {
+                ui.label(note);
+            }
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.busy, egui::Button::new("accept"))
+                .clicked()
+            {
+                self.viewmodel.accept_operation();
+            }
+            if ui.button("discard").clicked() {
+                self.viewmodel.discard_operation();
+            }
+        });
+    }
+}
+
+/// Renders the widget matching the parameter's kind. Returns `true` if the value was changed.
+fn parameter_ui(ui: &mut Ui, parameter: &Parameter, value: &mut Value) -> bool {
+    match (&parameter.kind, value) {
+        (ParameterKind::Bool { .. }, Value::Bool(value)) => {
+            ui.checkbox(value, parameter.name).changed()
+        }
+        (ParameterKind::Int { min, max, .. }, Value::Int(value)) => ui
+            .add(egui::Slider::new(value, *min..=*max).text(parameter.name))
+            .changed(),
+        (ParameterKind::Float { min, max, .. }, Value::Float(value)) => ui
+            .add(egui::Slider::new(value, *min..=*max).text(parameter.name))
+            .changed(),
+        (ParameterKind::Choice { options, .. }, Value::Choice(value)) => {
+            egui::ComboBox::from_label(parameter.name)
+                .show_index(ui, value, options.len(), |index| options[index].to_string())
+                .changed()
+        }
+        (ParameterKind::KernelSize { min, max, .. }, Value::KernelSize(value)) => {
+            // kernel sizes are always odd, so the slider works on the radius instead
+            let mut radius = *value / 2;
+            let changed = ui
+                .add(
+                    egui::Slider::new(&mut radius, *min / 2..=*max / 2)
+                        .show_value(false)
+                        .text(format!("{} ({}x{})", parameter.name, *value, *value)),
+                )
+                .changed();
+            *value = radius * 2 + 1;
+            changed
+        }
+        (ParameterKind::Matrix { max_size, .. }, Value::Matrix(matrix)) => {
+            ui.label(parameter.name);
+            let mut changed = matrix_size_ui(ui, *max_size, matrix);
+
+            egui::Grid::new(parameter.name).show(ui, |ui| {
+                for row in matrix.values.chunks_mut(matrix.width as usize) {
+                    for value in row {
+                        changed |= ui
+                            .add(egui::DragValue::new(value).speed(0.05).max_decimals(3))
+                            .changed();
+                    }
+                    ui.end_row();
+                }
+            });
+            changed
+        }
+        (ParameterKind::Mask { max_size, .. }, Value::Matrix(matrix)) => {
+            ui.label(parameter.name);
+            let changed = matrix_size_ui(ui, *max_size, matrix);
+            mask_ui(ui, matrix) || changed
+        }
+        (ParameterKind::Point, Value::Point(point)) => {
+            ui.horizontal(|ui| {
+                ui.label(parameter.name);
+                match point {
+                    Some([x, y]) => {
+                        let changed = ui.add(egui::DragValue::new(x).speed(0.5)).changed()
+                            | ui.add(egui::DragValue::new(y).speed(0.5)).changed();
+                        if ui.button("clear").clicked() {
+                            *point = None;
+                            return true;
+                        }
+                        changed
+                    }
+                    None => {
+                        ui.weak("click on the current image");
+                        false
+                    }
+                }
+            })
+            .inner
+        }
+        _ => false,
+    }
+}
+
+/// Sliders for the odd width and height of a matrix. Returns `true` if the size was changed.
+fn matrix_size_ui(ui: &mut Ui, max_size: u32, matrix: &mut Matrix) -> bool {
+    // like kernel sizes, the edge lengths are odd and the sliders work on the radius
+    let mut radius_x = matrix.width / 2;
+    let mut radius_y = matrix.height / 2;
+    let mut changed = ui
+        .add(
+            egui::Slider::new(&mut radius_x, 0..=max_size / 2)
+                .show_value(false)
+                .text(format!("width ({})", matrix.width)),
+        )
+        .changed();
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut radius_y, 0..=max_size / 2)
+                .show_value(false)
+                .text(format!("height ({})", matrix.height)),
+        )
+        .changed();
+    if changed {
+        *matrix = matrix.resized(radius_x * 2 + 1, radius_y * 2 + 1);
+    }
+    changed
+}
+
+/// A grid of cells that are switched on and off by clicking. Dragging paints the state the
+/// first cell was switched to. Returns `true` if a cell was changed.
+fn mask_ui(ui: &mut Ui, matrix: &mut Matrix) -> bool {
+    const CELL_SIZE: f32 = 14.0;
+    let size = egui::vec2(matrix.width as f32, matrix.height as f32) * CELL_SIZE;
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+    let rect = response.rect;
+
+    let mut changed = false;
+    let paint_id = response.id;
+    if let Some(position) = response.interact_pointer_pos() {
+        let cell = (position - rect.min) / CELL_SIZE;
+        let (x, y) = (cell.x.floor() as i64, cell.y.floor() as i64);
+        if (0..matrix.width as i64).contains(&x) && (0..matrix.height as i64).contains(&y) {
+            let index = (y * matrix.width as i64 + x) as usize;
+            let paint = if response.drag_started() || response.clicked() {
+                let paint = matrix.values[index] == 0.0;
+                ui.data().insert_temp(paint_id, paint);
+                paint
+            } else {
+                ui.data()
+                    .get_temp(paint_id)
+                    .unwrap_or(matrix.values[index] == 0.0)
+            };
+            let value = if paint { 1.0 } else { 0.0 };
+            if matrix.values[index] != value {
+                matrix.values[index] = value;
+                changed = true;
+            }
+        }
+    }
+
+    let visuals = ui.visuals();
+    for (index, value) in matrix.values.iter().enumerate() {
+        let x = (index as u32 % matrix.width) as f32;
+        let y = (index as u32 / matrix.width) as f32;
+        let cell = egui::Rect::from_min_size(
+            rect.min + egui::vec2(x, y) * CELL_SIZE,
+            egui::Vec2::splat(CELL_SIZE),
+        )
+        .shrink(1.0);
+        let fill = if *value != 0.0 {
+            visuals.selection.bg_fill
+        } else {
+            visuals.extreme_bg_color
+        };
+        painter.rect_filled(cell, 0.0, fill);
+    }
+
+    changed
+}
+
+impl View for ParameterWindow {
+    fn show(&mut self, ctx: &Context) {
+        self.viewmodel.process_messages();
+
+        while let Ok(notification) = self.vm_rx.try_recv() {
+            match notification {
+                PropertyChangedNotification::Busy => self.busy = self.viewmodel.get_busy(),
+                PropertyChangedNotification::Notes => {
+                    self.notes = self.viewmodel.get_notes().clone()
+                }
+                PropertyChangedNotification::Operation => {
+                    self.operation_name = self.viewmodel.get_operation_name();
+                    self.parameters = self.viewmodel.get_parameters().clone();
+                }
+                PropertyChangedNotification::Values => {
+                    self.values = self.viewmodel.get_values().clone()
+                }
+            }
+        }
+
+        if let Some(operation_name) = self.operation_name {
+            egui::Window::new(format!("Parameters: {}", operation_name))
+                .id(egui::Id::new("parameter_window"))
+                .collapsible(false)
+                .resizable(false)
+                .default_width(250.0)
+                .show(ctx, |ui| self.ui(ui));
+        }
+    }
+}
@@ -1,28 +1,58 @@
-use super::View;
-use crate::app::viewmodel::top_panel::PropertyChangedNotification;
+use super::{
+    ChannelsWindow, CompareWindow, ComponentsWindow, ContoursWindow, ExportWindow, HistogramWindow,
+    View,
+};
+use crate::app::model::operation::Category;
+use crate::app::viewmodel::top_panel::{OperationItem, PropertyChangedNotification};
 use crate::app::{modal, viewmodel};
-use egui::{Context, Ui};
+use egui::{Context, Key, Modifiers, Ui};
 use rfd::FileHandle;
 use tokio::sync::oneshot;
 
 pub struct TopPanel {
+    can_redo: bool,
+    can_undo: bool,
     has_current: bool,
     has_preview: bool,
+    operations: Vec<OperationItem>,
 
     rfd_promise: Option<oneshot::Receiver<Option<FileHandle>>>,
 
+    channels_window: ChannelsWindow,
+    compare_window: CompareWindow,
+    components_window: ComponentsWindow,
+    contours_window: ContoursWindow,
+    export_window: ExportWindow,
+    histogram_window: HistogramWindow,
     viewmodel: viewmodel::TopPanel,
     vm_rx: tokio::sync::broadcast::Receiver<PropertyChangedNotification>,
 }
 
 impl TopPanel {
-    pub fn new(viewmodel: viewmodel::TopPanel) -> Self {
+    pub fn new(
+        viewmodel: viewmodel::TopPanel,
+        export_window: ExportWindow,
+        compare_window: CompareWindow,
+        histogram_window: HistogramWindow,
+        channels_window: ChannelsWindow,
+        components_window: ComponentsWindow,
+        contours_window: ContoursWindow,
+    ) -> Self {
         let vm_rx = viewmodel.get_receiver();
 
         Self {
+            can_redo: false,
+            can_undo: false,
             has_current: false,
             has_preview: false,
+            operations: viewmodel.get_operations().clone(),
             rfd_promise: None,
+            channels_window,
+            compare_window,
+            components_window,
+            contours_window,
+            export_window,
+            histogram_window,
             viewmodel,
             vm_rx,
         }
@@ -35,20 +65,12 @@ impl TopPanel {
                     self.rfd_promise = Some(modal::open_file_dialog());
                 }
 
-                ui.separator();
-
-                if ui
-                    .add_enabled(self.has_current, egui::Button::new("grayscale"))
-                    .clicked()
-                {
-                    self.viewmodel.apply_grayscale();
-                }
-
                 if ui
-                    .add_enabled(self.has_current, egui::Button::new("invert"))
+                    .add_enabled(self.has_current, egui::Button::new("export..."))
                     .clicked()
                 {
-                    self.viewmodel.apply_invert();
+                    self.export_window.open();
+                    ui.close_menu();
                 }
 
                 ui.separator();
@@ -79,8 +101,102 @@ impl TopPanel {
                     self.viewmodel.reset_images();
                 }
             });
+
+            ui.menu_button("Operations", |ui| {
+                for category in Category::ALL {
+                    let operations: Vec<&OperationItem> = self
+                        .operations
+                        .iter()
+                        .filter(|operation| operation.category == category)
+                        .collect();
+                    if operations.is_empty() {
+                        continue;
+                    }
+
+                    let mut selected = None;
+                    ui.menu_button(category.name(), |ui| {
+                        for operation in operations {
+                            if ui
+                                .add_enabled(operation.enabled, egui::Button::new(operation.name))
+                                .on_hover_text(&operation.signature)
+                                .on_disabled_hover_text(&operation.signature)
+                                .clicked()
+                            {
+                                selected = Some(operation.name);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    if let Some(name) = selected {
+                        self.viewmodel.apply_operation(name);
+                    }
+                }
+            });
+
+            ui.menu_button("View", |ui| {
+                if ui
+                    .add_enabled(self.has_current, egui::Button::new("compare..."))
+                    .on_hover_text("compare the current image with the preview")
+                    .clicked()
+                {
+                    self.compare_window.open();
+                    ui.close_menu();
+                }
+
+                if ui.button("histogram...").clicked() {
+                    self.histogram_window.open();
+                    ui.close_menu();
+                }
+
+                if ui.button("channels...").clicked() {
+                    self.channels_window.open();
+                    ui.close_menu();
+                }
+
+                if ui.button("components...").clicked() {
+                    self.components_window.open();
+                    ui.close_menu();
+                }
+
+                if ui.button("contours...").clicked() {
+                    self.contours_window.open();
+                    ui.close_menu();
+                }
+            });
+
+            ui.menu_button("Edit", |ui| {
+                if ui
+                    .add_enabled(self.can_undo, egui::Button::new("undo"))
+                    .clicked()
+                {
+                    self.viewmodel.undo();
+                }
+
+                if ui
+                    .add_enabled(self.can_redo, egui::Button::new("redo"))
+                    .clicked()
+                {
+                    self.viewmodel.redo();
+                }
+            });
         });
     }
+
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        let mut input = ctx.input_mut();
+        let redo = input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+            || input.consume_key(Modifiers::COMMAND, Key::Y);
+        let undo = input.consume_key(Modifiers::COMMAND, Key::Z);
+        drop(input);
+
+        if undo && self.can_undo {
+            self.viewmodel.undo();
+        }
+        if redo && self.can_redo {
+            self.viewmodel.redo();
+        }
+    }
 }
 
 impl View for TopPanel {
@@ -96,17 +212,35 @@ impl View for TopPanel {
 
         while let Ok(notification) = self.vm_rx.try_recv() {
             match notification {
+                PropertyChangedNotification::CanRedo => {
+                    self.can_redo = self.viewmodel.get_can_redo()
+                }
+                PropertyChangedNotification::CanUndo => {
+                    self.can_undo = self.viewmodel.get_can_undo()
+                }
                 PropertyChangedNotification::HasCurrent => {
                     self.has_current = self.viewmodel.get_has_current()
                 }
                 PropertyChangedNotification::HasPreview => {
                     self.has_preview = self.viewmodel.get_has_preview()
                 }
+                PropertyChangedNotification::Operations => {
+                    self.operations = self.viewmodel.get_operations().clone()
+                }
             }
         }
 
+        self.handle_shortcuts(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             self.ui(ui);
         });
+
+        self.export_window.show(ctx);
+        self.compare_window.show(ctx);
+        self.histogram_window.show(ctx);
+        self.channels_window.show(ctx);
+        self.components_window.show(ctx);
+        self.contours_window.show(ctx);
     }
 }
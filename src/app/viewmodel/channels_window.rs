@@ -0,0 +1,179 @@
+use crate::app::model;
+use crate::app::model::channels::{Channel, MergeLayout};
+use crate::app::model::observable::Observable;
+use crate::app::model::viewport::ViewLink;
+use crate::app::model::ImageService;
+use crate::app::viewmodel;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Channels,
+    HasCurrent,
+    MergeLayout,
+    MergeSources,
+}
+
+pub struct ChannelsWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    channels: Vec<String>,
+    has_current: bool,
+    merge_layout: MergeLayout,
+    merge_sources: Vec<usize>,
+
+    // dependencies
+    image_service: Arc<ImageService>,
+    model: Arc<Observable<Vec<Channel>>>,
+    model_rx: broadcast::Receiver<()>,
+    current_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+    link: Arc<ViewLink>,
+}
+
+impl ChannelsWindow {
+    /// Frames of opened channels share `link` with the other image frames.
+    pub fn new(
+        image_service: Arc<ImageService>,
+        current_image: Arc<model::Image>,
+        link: Arc<ViewLink>,
+    ) -> Self {
+        let model = image_service.get_channels();
+        let model_rx = model.get_property_changed_rx();
+        let current_image_rx = current_image.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            channels: Vec::new(),
+            has_current: current_image.get().is_some(),
+            merge_layout: MergeLayout::Rgb,
+            merge_sources: vec![0, 1, 2],
+            image_service,
+            model,
+            model_rx,
+            current_image,
+            current_image_rx,
+            link,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.model_rx.try_recv().is_ok() {
+            while self.model_rx.try_recv().is_ok() {}
+            let channels = self
+                .model
+                .get()
+                .iter()
+                .map(|channel| channel.name.clone())
+                .collect();
+            self.set_channels(channels);
+        }
+
+        if self.current_image_rx.try_recv().is_ok() {
+            while self.current_image_rx.try_recv().is_ok() {}
+            self.set_has_current(self.current_image.get().is_some());
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    /// Names of the channels of the last split.
+    pub fn get_channels(&self) -> &Vec<String> {
+        &self.channels
+    }
+
+    pub fn get_has_current(&self) -> bool {
+        self.has_current
+    }
+
+    pub fn get_merge_layout(&self) -> MergeLayout {
+        self.merge_layout
+    }
+
+    /// Index of the channel used for every channel of the merge layout.
+    pub fn get_merge_sources(&self) -> &Vec<usize> {
+        &self.merge_sources
+    }
+
+    pub fn set_merge_layout(&mut self, merge_layout: MergeLayout) {
+        self.merge_layout = merge_layout;
+        let sources = (0..merge_layout.channels().len())
+            .map(|slot| self.merge_sources.get(slot).copied().unwrap_or(slot))
+            .collect();
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::MergeLayout)
+            .ok();
+        self.set_merge_sources(sources);
+    }
+
+    pub fn set_merge_source(&mut self, slot: usize, channel: usize) {
+        let mut sources = self.merge_sources.clone();
+        if let Some(source) = sources.get_mut(slot) {
+            *source = channel;
+            self.set_merge_sources(sources);
+        }
+    }
+
+    pub fn split(&mut self) {
+        self.image_service.split_channels();
+    }
+
+    pub fn merge(&mut self) {
+        self.image_service
+            .merge_channels(&self.merge_sources, self.merge_layout);
+    }
+
+    pub fn load_channel(&mut self, index: usize) {
+        self.image_service.load_channel(index);
+    }
+
+    pub fn store_channel(&mut self, index: usize) {
+        self.image_service.store_channel(index);
+    }
+
+    /// A read-only frame showing the channel at `index`, following later changes of it.
+    pub fn create_frame(&self, index: usize) -> Option<viewmodel::ImageFrame> {
+        let channels = self.model.get();
+        let channel = channels.get(index)?;
+        Some(viewmodel::ImageFrame::new(
+            &format!("Channel {}", channel.name),
+            false,
+            Arc::clone(&self.image_service),
+            Arc::clone(&channel.image),
+            None,
+            Arc::clone(&self.link),
+        ))
+    }
+
+    fn set_channels(&mut self, channels: Vec<String>) {
+        self.channels = channels;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Channels)
+            .ok();
+    }
+
+    fn set_has_current(&mut self, has_current: bool) {
+        self.has_current = has_current;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::HasCurrent)
+            .ok();
+    }
+
+    fn set_merge_sources(&mut self, merge_sources: Vec<usize>) {
+        self.merge_sources = merge_sources;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::MergeSources)
+            .ok();
+    }
+}
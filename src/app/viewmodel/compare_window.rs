@@ -0,0 +1,310 @@
+use crate::app::model;
+use crate::app::model::compare::{self, Difference};
+use image::DynamicImage;
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareMode {
+    /// Current image left of a movable divider, preview right of it.
+    Split,
+    /// Alternates between both images at the same place.
+    Blink,
+    /// Heatmap of the absolute difference.
+    Difference,
+}
+
+impl CompareMode {
+    pub const ALL: [CompareMode; 3] = [
+        CompareMode::Split,
+        CompareMode::Blink,
+        CompareMode::Difference,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompareMode::Split => "split",
+            CompareMode::Blink => "blink",
+            CompareMode::Difference => "difference",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Amplification,
+    BlinkInterval,
+    Busy,
+    Current,
+    Difference,
+    Mode,
+    Open,
+    Preview,
+    Split,
+}
+
+pub struct CompareWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    amplification: f32,
+    blink_interval: f32,
+    busy: bool,
+    current: Arc<Option<DynamicImage>>,
+    difference: Option<Arc<DynamicImage>>,
+    difference_stats: Option<(f32, f32)>,
+    mode: CompareMode,
+    open: bool,
+    preview: Arc<Option<DynamicImage>>,
+    split: f32,
+
+    // cached difference of `current` and `preview`, computed when first needed
+    difference_values: Option<Arc<Difference>>,
+    difference_dirty: bool,
+    // the images changed while the window was closed
+    images_dirty: bool,
+
+    // promises
+    difference_promise: Option<oneshot::Receiver<Option<Difference>>>,
+
+    // dependencies
+    current_image: Arc<model::Image>,
+    preview_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+    preview_image_rx: broadcast::Receiver<()>,
+}
+
+impl CompareWindow {
+    pub fn new(current_image: Arc<model::Image>, preview_image: Arc<model::Image>) -> Self {
+        let current_image_rx = current_image.get_property_changed_rx();
+        let preview_image_rx = preview_image.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            amplification: 4.0,
+            blink_interval: 0.5,
+            busy: false,
+            current: current_image.get(),
+            difference: None,
+            difference_stats: None,
+            mode: CompareMode::Split,
+            open: false,
+            preview: preview_image.get(),
+            split: 0.5,
+            difference_values: None,
+            difference_dirty: true,
+            images_dirty: false,
+            difference_promise: None,
+            current_image,
+            preview_image,
+            current_image_rx,
+            preview_image_rx,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        let mut current_changed = false;
+        while self.current_image_rx.try_recv().is_ok() {
+            current_changed = true;
+        }
+        let mut preview_changed = false;
+        while self.preview_image_rx.try_recv().is_ok() {
+            preview_changed = true;
+        }
+        if !self.open {
+            self.images_dirty |= current_changed || preview_changed;
+            return;
+        }
+
+        if current_changed {
+            self.update_current();
+        }
+        if preview_changed {
+            self.update_preview();
+        }
+        if self.difference_dirty && self.mode == CompareMode::Difference {
+            self.update_difference();
+        }
+
+        if let Some(difference_promise) = &mut self.difference_promise {
+            if let Ok(difference) = difference_promise.try_recv() {
+                self.difference_promise = None;
+                self.difference_values = difference.map(Arc::new);
+                self.difference_stats = self
+                    .difference_values
+                    .as_ref()
+                    .map(|difference| (difference.max, difference.mean));
+                self.update_heatmap();
+                self.set_busy(false);
+            }
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    pub fn get_amplification(&self) -> f32 {
+        self.amplification
+    }
+
+    /// Seconds between switching images in blink mode. Zero means switching by hand.
+    pub fn get_blink_interval(&self) -> f32 {
+        self.blink_interval
+    }
+
+    /// `true` while the difference is computed in the background.
+    pub fn get_busy(&self) -> bool {
+        self.busy
+    }
+
+    pub fn get_current(&self) -> Arc<Option<DynamicImage>> {
+        Arc::clone(&self.current)
+    }
+
+    /// The difference heatmap, `None` if an image is missing or the sizes differ.
+    pub fn get_difference(&self) -> Option<Arc<DynamicImage>> {
+        self.difference.clone()
+    }
+
+    /// Maximum and mean difference in `0.0..=1.0`.
+    pub fn get_difference_stats(&self) -> Option<(f32, f32)> {
+        self.difference_stats
+    }
+
+    pub fn get_mode(&self) -> CompareMode {
+        self.mode
+    }
+
+    pub fn get_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn get_preview(&self) -> Arc<Option<DynamicImage>> {
+        Arc::clone(&self.preview)
+    }
+
+    /// Position of the divider in split mode, as a fraction of the view width.
+    pub fn get_split(&self) -> f32 {
+        self.split
+    }
+
+    /// Only recolours the cached difference, it is not computed again.
+    pub fn set_amplification(&mut self, amplification: f32) {
+        self.amplification = amplification;
+        self.update_heatmap();
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Amplification)
+            .ok();
+    }
+
+    pub fn set_blink_interval(&mut self, blink_interval: f32) {
+        self.blink_interval = blink_interval.max(0.0);
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::BlinkInterval)
+            .ok();
+    }
+
+    pub fn set_mode(&mut self, mode: CompareMode) {
+        self.mode = mode;
+        if self.open && self.difference_dirty && mode == CompareMode::Difference {
+            self.update_difference();
+        }
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Mode)
+            .ok();
+    }
+
+    /// The images are only followed and compared while the window is open.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Open)
+            .ok();
+        if !open {
+            if self.difference_promise.take().is_some() {
+                self.difference_dirty = true;
+                self.set_busy(false);
+            }
+            return;
+        }
+
+        if self.images_dirty {
+            self.images_dirty = false;
+            self.update_current();
+            self.update_preview();
+        }
+        if self.difference_dirty && self.mode == CompareMode::Difference {
+            self.update_difference();
+        }
+    }
+
+    pub fn set_split(&mut self, split: f32) {
+        self.split = split.clamp(0.0, 1.0);
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Split)
+            .ok();
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Busy)
+            .ok();
+    }
+
+    fn update_current(&mut self) {
+        self.current = self.current_image.get();
+        self.difference_dirty = true;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Current)
+            .ok();
+    }
+
+    fn update_preview(&mut self) {
+        self.preview = self.preview_image.get();
+        self.difference_dirty = true;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Preview)
+            .ok();
+    }
+
+    /// Starts comparing the images in the background. A comparison that is still running is
+    /// superseded and its result dropped.
+    fn update_difference(&mut self) {
+        self.difference_dirty = false;
+        let (current, preview) = (Arc::clone(&self.current), Arc::clone(&self.preview));
+        let (tx, rx) = oneshot::channel();
+        self.difference_promise = Some(rx);
+        self.set_busy(true);
+        crate::app::execute(async move {
+            let difference = match (&*current, &*preview) {
+                (Some(current), Some(preview)) => compare::difference(current, preview),
+                _ => None,
+            };
+            tx.send(difference).ok();
+        });
+    }
+
+    fn update_heatmap(&mut self) {
+        self.difference = self
+            .difference_values
+            .as_ref()
+            .map(|difference| Arc::new(compare::heatmap(difference, self.amplification)));
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Difference)
+            .ok();
+    }
+}
@@ -0,0 +1,322 @@
+use crate::app::model;
+use crate::app::model::components::{self, Component, Connectivity, Labeling};
+use crate::app::model::parameter::Value;
+use crate::app::model::task::Progress;
+use crate::app::model::viewport::{Highlight, ViewLink};
+use crate::app::model::ImageService;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Busy,
+    Components,
+    Connectivity,
+    Filter,
+    IsBinary,
+    Open,
+    Selected,
+}
+
+pub struct ComponentsWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    busy: bool,
+    components: Vec<Component>,
+    connectivity: Connectivity,
+    is_binary: bool,
+    max_area: u32,
+    min_area: u32,
+    open: bool,
+    selected: Option<u32>,
+    total: usize,
+
+    // all components of the current image before filtering, and the label of every pixel
+    labeling: Option<Arc<Labeling>>,
+    // the image changed while the window was closed
+    image_dirty: bool,
+
+    // promises
+    components_promise: Option<oneshot::Receiver<Option<Labeling>>>,
+    // cancels the labeling that is running, if any
+    cancelled: Arc<AtomicBool>,
+
+    // dependencies
+    image_service: Arc<ImageService>,
+    current_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+    link: Arc<ViewLink>,
+}
+
+impl ComponentsWindow {
+    /// Selecting a component highlights it in all frames sharing `link`.
+    pub fn new(
+        image_service: Arc<ImageService>,
+        current_image: Arc<model::Image>,
+        link: Arc<ViewLink>,
+    ) -> Self {
+        let current_image_rx = current_image.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            busy: false,
+            components: Vec::new(),
+            connectivity: Connectivity::Eight,
+            is_binary: false,
+            max_area: 0,
+            min_area: 1,
+            open: false,
+            selected: None,
+            total: 0,
+            labeling: None,
+            image_dirty: true,
+            components_promise: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            image_service,
+            current_image,
+            current_image_rx,
+            link,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.current_image_rx.try_recv().is_ok() {
+            while self.current_image_rx.try_recv().is_ok() {}
+            if self.open {
+                self.update_components();
+            } else {
+                self.image_dirty = true;
+            }
+        }
+
+        if let Some(components_promise) = &mut self.components_promise {
+            if let Ok(labeling) = components_promise.try_recv() {
+                self.components_promise = None;
+                if let Some(labeling) = labeling {
+                    self.labeling = Some(Arc::new(labeling));
+                    self.filter();
+                }
+                self.set_busy(false);
+            }
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    /// `true` while the components are labeled in the background.
+    pub fn get_busy(&self) -> bool {
+        self.busy
+    }
+
+    /// The components within the area filter, by label.
+    pub fn get_components(&self) -> &Vec<Component> {
+        &self.components
+    }
+
+    pub fn get_connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Whether the current image is a Luma8 image, whose non-zero pixels are labeled.
+    pub fn get_is_binary(&self) -> bool {
+        self.is_binary
+    }
+
+    /// Largest area of the listed components, 0 for no limit.
+    pub fn get_max_area(&self) -> u32 {
+        self.max_area
+    }
+
+    /// Smallest area of the listed components.
+    pub fn get_min_area(&self) -> u32 {
+        self.min_area
+    }
+
+    pub fn get_open(&self) -> bool {
+        self.open
+    }
+
+    /// Label of the highlighted component.
+    pub fn get_selected(&self) -> Option<u32> {
+        self.selected
+    }
+
+    /// Number of components before filtering.
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    pub fn set_connectivity(&mut self, connectivity: Connectivity) {
+        self.connectivity = connectivity;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Connectivity)
+            .ok();
+        if self.open {
+            self.update_components();
+        } else {
+            self.image_dirty = true;
+        }
+    }
+
+    /// The current image is only labeled while the window is open.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Open)
+            .ok();
+        if !open {
+            if self.components_promise.take().is_some() {
+                self.cancel_labeling();
+                self.image_dirty = true;
+                self.set_busy(false);
+            }
+            return;
+        }
+
+        if self.image_dirty {
+            self.update_components();
+        }
+    }
+
+    pub fn set_area_filter(&mut self, min_area: u32, max_area: u32) {
+        self.min_area = min_area.max(1);
+        self.max_area = max_area;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Filter)
+            .ok();
+        self.filter();
+    }
+
+    /// Highlights the pixels of the component with `label` in the image frames, `None` removes
+    /// the highlight.
+    pub fn select(&mut self, label: Option<u32>) {
+        let component = label.and_then(|label| {
+            self.components
+                .iter()
+                .find(|component| component.label == label)
+        });
+        let highlight = component
+            .zip(self.labeling.as_ref())
+            .map(|(component, labeling)| {
+                let [x, y, width, height] = component.bounds;
+                Highlight {
+                    bounds: [
+                        [x as f32, y as f32],
+                        [(x + width) as f32, (y + height) as f32],
+                    ],
+                    runs: labeling.runs(component),
+                }
+            });
+        self.link.highlight.set(highlight);
+        self.selected = component.map(|component| component.label);
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Selected)
+            .ok();
+    }
+
+    /// Previews the listed components in their colours with the connected components operation.
+    pub fn preview(&self) {
+        let to_int = |area: u32| area.min(i32::MAX as u32) as i32;
+        self.image_service.apply_operation("connected components");
+        self.image_service
+            .set_parameter("connectivity", Value::Choice(self.connectivity as usize));
+        self.image_service
+            .set_parameter("min area", Value::Int(to_int(self.min_area)));
+        self.image_service
+            .set_parameter("max area", Value::Int(to_int(self.max_area)));
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Busy)
+            .ok();
+    }
+
+    fn set_components(&mut self, components: Vec<Component>) {
+        self.components = components;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Components)
+            .ok();
+    }
+
+    fn set_is_binary(&mut self, is_binary: bool) {
+        self.is_binary = is_binary;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::IsBinary)
+            .ok();
+    }
+
+    /// Lists the labeled components within the area filter. The selection is kept if it is
+    /// still listed.
+    fn filter(&mut self) {
+        let (min_area, max_area) = (self.min_area, self.max_area);
+        let labeled = self
+            .labeling
+            .as_ref()
+            .map_or(&[][..], |labeling| &labeling.components);
+        let components = labeled
+            .iter()
+            .filter(|component| {
+                component.area >= min_area && (max_area == 0 || component.area <= max_area)
+            })
+            .cloned()
+            .collect();
+        self.total = labeled.len();
+        self.set_components(components);
+        self.select(self.selected);
+    }
+
+    /// Starts labeling the current image. A labeling that is still running is cancelled.
+    fn update_components(&mut self) {
+        self.image_dirty = false;
+        self.cancel_labeling();
+        let image = self.current_image.get();
+        let is_binary = (*image)
+            .as_ref()
+            .map_or(false, |image| image.as_luma8().is_some());
+        self.set_is_binary(is_binary);
+        if !is_binary {
+            self.components_promise = None;
+            self.labeling = None;
+            self.selected = None;
+            self.filter();
+            self.set_busy(false);
+            return;
+        }
+
+        let connectivity = self.connectivity;
+        let progress = Progress::new(Arc::clone(&self.cancelled), |_| {});
+        let (tx, rx) = oneshot::channel();
+        self.components_promise = Some(rx);
+        self.selected = None;
+        self.set_busy(true);
+        crate::app::execute(async move {
+            let labeling = (*image)
+                .as_ref()
+                .and_then(|image| image.as_luma8())
+                .and_then(|image| components::label(image, connectivity, &progress));
+            tx.send(labeling).ok();
+        });
+    }
+
+    /// Cancels the running labeling, if any, and prepares a new flag for the next one.
+    fn cancel_labeling(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.cancelled = Arc::new(AtomicBool::new(false));
+    }
+}
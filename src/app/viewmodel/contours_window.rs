@@ -0,0 +1,313 @@
+use crate::app::model;
+use crate::app::model::contours::{self, Contour, ContourFormat, Descriptors};
+use crate::app::model::task::Progress;
+use crate::app::model::viewport::{Outline, OutlineKind, ViewLink};
+use crate::app::model::ImageService;
+use std::io;
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Busy,
+    Contours,
+    Epsilon,
+    Format,
+    IsBinary,
+    Open,
+    OuterOnly,
+    Overlay,
+}
+
+/// Which shapes besides the contours are drawn over the image frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Overlay {
+    pub convex_hull: bool,
+    pub min_area_rect: bool,
+    pub enclosing_circle: bool,
+}
+
+/// Contours with the descriptors of their approximated polygons.
+type DescribedContours = Vec<(Contour, Descriptors)>;
+
+pub struct ContoursWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    busy: bool,
+    contours: Arc<DescribedContours>,
+    epsilon: f32,
+    format: ContourFormat,
+    is_binary: bool,
+    open: bool,
+    outer_only: bool,
+    overlay: Overlay,
+
+    // promises
+    contours_promise: Option<oneshot::Receiver<Option<DescribedContours>>>,
+
+    // dependencies
+    image_service: Arc<ImageService>,
+    current_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+    link: Arc<ViewLink>,
+}
+
+impl ContoursWindow {
+    /// The contours are drawn by all frames sharing `link` while the window is open.
+    pub fn new(
+        image_service: Arc<ImageService>,
+        current_image: Arc<model::Image>,
+        link: Arc<ViewLink>,
+    ) -> Self {
+        let current_image_rx = current_image.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            busy: false,
+            contours: Arc::new(Vec::new()),
+            epsilon: 1.0,
+            format: ContourFormat::Json,
+            is_binary: false,
+            open: false,
+            outer_only: false,
+            overlay: Overlay::default(),
+            contours_promise: None,
+            image_service,
+            current_image,
+            current_image_rx,
+            link,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.current_image_rx.try_recv().is_ok() {
+            while self.current_image_rx.try_recv().is_ok() {}
+            self.update_contours();
+        }
+
+        if let Some(contours_promise) = &mut self.contours_promise {
+            if let Ok(contours) = contours_promise.try_recv() {
+                self.contours_promise = None;
+                if let Some(contours) = contours {
+                    self.set_contours(Arc::new(contours));
+                }
+                self.set_busy(false);
+            }
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    /// `true` while the contours are traced in the background.
+    pub fn get_busy(&self) -> bool {
+        self.busy
+    }
+
+    /// Contours of the current image with the descriptors of their approximated polygons.
+    pub fn get_contours(&self) -> Arc<DescribedContours> {
+        Arc::clone(&self.contours)
+    }
+
+    /// Largest distance of a border pixel from the approximated polygon, 0 keeps all pixels.
+    pub fn get_epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    pub fn get_format(&self) -> ContourFormat {
+        self.format
+    }
+
+    /// Whether the current image is a Luma8 image, whose non-zero pixels are traced.
+    pub fn get_is_binary(&self) -> bool {
+        self.is_binary
+    }
+
+    pub fn get_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether only the outermost contours are kept, without holes and nested components.
+    pub fn get_outer_only(&self) -> bool {
+        self.outer_only
+    }
+
+    pub fn get_overlay(&self) -> Overlay {
+        self.overlay
+    }
+
+    pub fn get_file_name(&self) -> String {
+        format!("contours.{}", self.format.extension())
+    }
+
+    pub fn set_epsilon(&mut self, epsilon: f32) {
+        self.epsilon = epsilon.max(0.0);
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Epsilon)
+            .ok();
+        self.update_contours();
+    }
+
+    pub fn set_format(&mut self, format: ContourFormat) {
+        self.format = format;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Format)
+            .ok();
+    }
+
+    /// Contours are only traced and drawn while the window is open.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Open)
+            .ok();
+        self.update_contours();
+    }
+
+    pub fn set_outer_only(&mut self, outer_only: bool) {
+        self.outer_only = outer_only;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::OuterOnly)
+            .ok();
+        self.update_contours();
+    }
+
+    pub fn set_overlay(&mut self, overlay: Overlay) {
+        self.overlay = overlay;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Overlay)
+            .ok();
+        self.update_outlines();
+    }
+
+    /// The contours in the selected format, `None` without a current image.
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let image = self.current_image.get();
+        let image = (*image).as_ref()?;
+        Some(contours::encode(
+            &self.contours,
+            image.width(),
+            image.height(),
+            self.format,
+        ))
+    }
+
+    /// Reports the outcome of a save dialog to the log. `None` means the dialog was cancelled.
+    pub fn report_saved(&self, result: Option<io::Result<()>>) {
+        self.image_service
+            .get_log()
+            .report_saved(&self.get_file_name(), result);
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Busy)
+            .ok();
+    }
+
+    fn set_contours(&mut self, contours: Arc<DescribedContours>) {
+        self.contours = contours;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Contours)
+            .ok();
+        self.update_outlines();
+    }
+
+    fn set_is_binary(&mut self, is_binary: bool) {
+        self.is_binary = is_binary;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::IsBinary)
+            .ok();
+    }
+
+    /// Starts tracing the current image. A tracing that is still running is superseded and its
+    /// result dropped.
+    fn update_contours(&mut self) {
+        let image = self.current_image.get();
+        let is_binary = (*image)
+            .as_ref()
+            .map_or(false, |image| image.as_luma8().is_some());
+        self.set_is_binary(is_binary);
+        if !is_binary || !self.open {
+            self.contours_promise = None;
+            self.set_contours(Arc::new(Vec::new()));
+            self.set_busy(false);
+            return;
+        }
+
+        let (epsilon, outer_only) = (self.epsilon, self.outer_only);
+        let (tx, rx) = oneshot::channel();
+        self.contours_promise = Some(rx);
+        self.set_busy(true);
+        crate::app::execute(async move {
+            let contours = (*image)
+                .as_ref()
+                .and_then(|image| image.as_luma8())
+                .and_then(|image| contours::find_contours(image, &Progress::none()))
+                .map(|contours| {
+                    contours
+                        .into_iter()
+                        .filter(|contour| !outer_only || contour.parent.is_none())
+                        .map(|contour| {
+                            let points = contours::approximate(&contour.points, epsilon);
+                            let descriptors = Descriptors::of(&points);
+                            (Contour { points, ..contour }, descriptors)
+                        })
+                        .collect()
+                });
+            tx.send(contours).ok();
+        });
+    }
+
+    /// Hands the contours and the selected shapes to the image frames.
+    fn update_outlines(&self) {
+        let mut outlines = Vec::new();
+        for (contour, descriptors) in self.contours.iter() {
+            outlines.push(Outline {
+                kind: if contour.is_hole {
+                    OutlineKind::Hole
+                } else {
+                    OutlineKind::Contour
+                },
+                points: contour.points.clone(),
+            });
+            // the shapes only make sense around components
+            if contour.is_hole {
+                continue;
+            }
+            if self.overlay.convex_hull {
+                outlines.push(Outline {
+                    kind: OutlineKind::ConvexHull,
+                    points: descriptors.convex_hull.clone(),
+                });
+            }
+            if self.overlay.min_area_rect {
+                outlines.push(Outline {
+                    kind: OutlineKind::Rectangle,
+                    points: descriptors.min_area_rect.corners(),
+                });
+            }
+            if self.overlay.enclosing_circle {
+                outlines.push(Outline {
+                    kind: OutlineKind::Circle,
+                    points: descriptors.enclosing_circle.outline(),
+                });
+            }
+        }
+        self.link.outlines.set(outlines);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::app::model;
+use crate::app::model::export::ExportFormat;
+use crate::app::model::log::Severity;
+use crate::app::model::ImageService;
+use image::ImageResult;
+use std::io;
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Format,
+    HasCurrent,
+    JpegQuality,
+}
+
+pub struct ExportWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    format: ExportFormat,
+    has_current: bool,
+    jpeg_quality: u8,
+
+    // dependencies
+    image_service: Arc<ImageService>,
+    current_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+}
+
+impl ExportWindow {
+    pub fn new(image_service: Arc<ImageService>, current_image: Arc<model::Image>) -> Self {
+        let current_image_rx = current_image.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            format: ExportFormat::Png,
+            has_current: current_image.get().is_some(),
+            jpeg_quality: 90,
+            image_service,
+            current_image,
+            current_image_rx,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.current_image_rx.try_recv().is_ok() {
+            self.set_has_current(self.current_image.get().is_some());
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    /// Starts encoding the current image with the selected format and quality in the
+    /// background. `None` without a current image.
+    pub fn encode(&self) -> Option<oneshot::Receiver<ImageResult<Vec<u8>>>> {
+        self.image_service
+            .encode_current_image(self.format, self.jpeg_quality)
+    }
+
+    /// Returns the encoded data, failures are reported to the log.
+    pub fn report_encoded(&self, result: ImageResult<Vec<u8>>) -> Option<Vec<u8>> {
+        match result {
+            Ok(data) => Some(data),
+            Err(error) => {
+                self.image_service.get_log().push(
+                    Severity::Error,
+                    format!("could not encode {}: {}", self.format.name(), error),
+                );
+                None
+            }
+        }
+    }
+
+    /// Reports the outcome of a save dialog to the log. `None` means the dialog was cancelled.
+    pub fn report_saved(&self, result: Option<io::Result<()>>) {
+        self.image_service
+            .get_log()
+            .report_saved(&self.get_file_name(), result);
+    }
+
+    /// The name of the loaded file with the extension of the chosen format.
+    pub fn get_file_name(&self) -> String {
+        self.format
+            .file_name(self.image_service.get_file_name().as_deref())
+    }
+
+    pub fn get_format(&self) -> ExportFormat {
+        self.format
+    }
+
+    pub fn get_has_current(&self) -> bool {
+        self.has_current
+    }
+
+    pub fn get_jpeg_quality(&self) -> u8 {
+        self.jpeg_quality
+    }
+
+    pub fn set_format(&mut self, format: ExportFormat) {
+        self.format = format;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Format)
+            .ok();
+    }
+
+    pub fn set_jpeg_quality(&mut self, jpeg_quality: u8) {
+        self.jpeg_quality = jpeg_quality.clamp(1, 100);
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::JpegQuality)
+            .ok();
+    }
+
+    fn set_has_current(&mut self, has_current: bool) {
+        self.has_current = has_current;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::HasCurrent)
+            .ok();
+    }
+}
@@ -0,0 +1,273 @@
+use crate::app::model;
+use crate::app::model::histogram::{self, Histogram as HistogramData};
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Current,
+    Preview,
+}
+
+impl Source {
+    pub const ALL: [Source; 2] = [Source::Current, Source::Preview];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Current => "current",
+            Source::Preview => "preview",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Busy,
+    Cumulative,
+    HasRoi,
+    Histogram,
+    LogScale,
+    Open,
+    RoiOnly,
+    Source,
+}
+
+pub struct HistogramWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    busy: bool,
+    cumulative: bool,
+    has_roi: bool,
+    histogram: Option<Arc<HistogramData>>,
+    log_scale: bool,
+    open: bool,
+    roi_only: bool,
+    source: Source,
+
+    // promises
+    histogram_promise: Option<oneshot::Receiver<Option<HistogramData>>>,
+
+    // dependencies
+    current_image: Arc<model::Image>,
+    preview_image: Arc<model::Image>,
+    current_image_rx: broadcast::Receiver<()>,
+    preview_image_rx: broadcast::Receiver<()>,
+    roi: Arc<model::Roi>,
+    roi_rx: broadcast::Receiver<()>,
+}
+
+impl HistogramWindow {
+    /// With `roi_only`, the histogram only covers the region of interest in `roi`.
+    pub fn new(
+        current_image: Arc<model::Image>,
+        preview_image: Arc<model::Image>,
+        roi: Arc<model::Roi>,
+    ) -> Self {
+        let current_image_rx = current_image.get_property_changed_rx();
+        let preview_image_rx = preview_image.get_property_changed_rx();
+        let roi_rx = roi.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            busy: false,
+            cumulative: false,
+            has_roi: roi.get().is_some(),
+            histogram: None,
+            log_scale: false,
+            open: false,
+            roi_only: false,
+            source: Source::Current,
+            histogram_promise: None,
+            current_image,
+            preview_image,
+            current_image_rx,
+            preview_image_rx,
+            roi,
+            roi_rx,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        let mut current_changed = false;
+        while self.current_image_rx.try_recv().is_ok() {
+            current_changed = true;
+        }
+        let mut preview_changed = false;
+        while self.preview_image_rx.try_recv().is_ok() {
+            preview_changed = true;
+        }
+        let mut roi_changed = false;
+        while self.roi_rx.try_recv().is_ok() {
+            roi_changed = true;
+        }
+        if roi_changed {
+            self.set_has_roi(self.roi.get().is_some());
+        }
+        let changed = match self.source {
+            Source::Current => current_changed,
+            Source::Preview => preview_changed,
+        } || (roi_changed && self.roi_only);
+        if changed {
+            self.update_histogram();
+        }
+
+        if let Some(histogram_promise) = &mut self.histogram_promise {
+            if let Ok(histogram) = histogram_promise.try_recv() {
+                self.histogram_promise = None;
+                self.set_histogram(histogram.map(Arc::new));
+                self.set_busy(false);
+            }
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    /// `true` while the histogram is computed in the background.
+    pub fn get_busy(&self) -> bool {
+        self.busy
+    }
+
+    pub fn get_cumulative(&self) -> bool {
+        self.cumulative
+    }
+
+    /// `None` if the selected image does not exist.
+    pub fn get_histogram(&self) -> Option<Arc<HistogramData>> {
+        self.histogram.clone()
+    }
+
+    pub fn get_has_roi(&self) -> bool {
+        self.has_roi
+    }
+
+    pub fn get_log_scale(&self) -> bool {
+        self.log_scale
+    }
+
+    pub fn get_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether only the pixels in the region of interest are counted, if there is one.
+    pub fn get_roi_only(&self) -> bool {
+        self.roi_only
+    }
+
+    pub fn get_source(&self) -> Source {
+        self.source
+    }
+
+    pub fn set_cumulative(&mut self, cumulative: bool) {
+        self.cumulative = cumulative;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Cumulative)
+            .ok();
+    }
+
+    pub fn set_log_scale(&mut self, log_scale: bool) {
+        self.log_scale = log_scale;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::LogScale)
+            .ok();
+    }
+
+    /// The histogram is only computed while the window is open.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Open)
+            .ok();
+        self.update_histogram();
+    }
+
+    pub fn set_roi_only(&mut self, roi_only: bool) {
+        self.roi_only = roi_only;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::RoiOnly)
+            .ok();
+        self.update_histogram();
+    }
+
+    pub fn set_source(&mut self, source: Source) {
+        self.source = source;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Source)
+            .ok();
+        self.update_histogram();
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Busy)
+            .ok();
+    }
+
+    fn set_has_roi(&mut self, has_roi: bool) {
+        self.has_roi = has_roi;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::HasRoi)
+            .ok();
+    }
+
+    fn set_histogram(&mut self, histogram: Option<Arc<HistogramData>>) {
+        self.histogram = histogram;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Histogram)
+            .ok();
+    }
+
+    /// Starts computing the histogram of the selected image. A computation that is still running
+    /// is superseded and its result dropped.
+    fn update_histogram(&mut self) {
+        if !self.open {
+            self.histogram_promise = None;
+            self.set_busy(false);
+            return;
+        }
+
+        let image = match self.source {
+            Source::Current => self.current_image.get(),
+            Source::Preview => self.preview_image.get(),
+        };
+        if image.is_none() {
+            self.histogram_promise = None;
+            self.set_histogram(None);
+            self.set_busy(false);
+            return;
+        }
+
+        let region = if self.roi_only {
+            self.roi.get()
+        } else {
+            Arc::new(None)
+        };
+        let (tx, rx) = oneshot::channel();
+        self.histogram_promise = Some(rx);
+        self.set_busy(true);
+        crate::app::execute(async move {
+            let histogram = (*image).as_ref().map(|image| match &*region {
+                Some(region) => {
+                    let mask = region.mask(image.width(), image.height());
+                    histogram::masked_histogram(image, &mask)
+                }
+                None => histogram::histogram(image),
+            });
+            tx.send(histogram).ok();
+        });
+    }
+}
@@ -1,17 +1,68 @@
 use crate::app::model;
+use crate::app::model::display::DisplayMapping;
+use crate::app::model::observable::Observable;
+use crate::app::model::operation::ActiveOperation;
+use crate::app::model::parameter::{ParameterKind, Value};
+use crate::app::model::pixel::{self, PixelInfo};
+use crate::app::model::roi::Region;
+use crate::app::model::task::{Task, TaskStatus};
+use crate::app::model::viewport::{Highlight, Outline, ViewLink, Viewport};
 use crate::app::model::ImageService;
 use image::DynamicImage;
 use rfd::FileHandle;
 use std::sync::Arc;
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::TryRecvError;
+
+/// What dragging and clicking on the image draws, if anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoiTool {
+    None,
+    Rectangle,
+    Ellipse,
+    /// Clicks add corners, a double click or a click on the first corner closes the polygon.
+    Polygon,
+    Freehand,
+}
+
+impl RoiTool {
+    pub const ALL: [RoiTool; 5] = [
+        RoiTool::None,
+        RoiTool::Rectangle,
+        RoiTool::Ellipse,
+        RoiTool::Polygon,
+        RoiTool::Freehand,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoiTool::None => "none",
+            RoiTool::Rectangle => "rectangle",
+            RoiTool::Ellipse => "ellipse",
+            RoiTool::Polygon => "polygon",
+            RoiTool::Freehand => "freehand",
+        }
+    }
+}
 
 #[derive(Clone)]
 #[allow(dead_code)]
 pub enum PropertyChangedNotification {
     AcceptInput,
+    Cursor,
+    DisplayMapping,
+    Highlight,
     Image,
+    Linked,
+    Loupe,
     Open,
+    Outlines,
+    Points,
+    Progress,
+    Roi,
+    RoiTool,
     Title,
+    Viewport,
 }
 
 pub struct ImageFrame {
@@ -22,40 +73,184 @@ pub struct ImageFrame {
 
     // properties
     accept_input: bool,
+    cursor: Option<[f32; 2]>,
+    display_mapping: DisplayMapping,
+    highlight: Arc<Option<Highlight>>,
     image: Arc<Option<DynamicImage>>,
+    linked: bool,
+    loupe: bool,
     open: bool,
+    outlines: Arc<Vec<Outline>>,
+    points: Vec<(&'static str, Option<[f32; 2]>)>,
+    progress: Option<TaskStatus>,
+    roi: Arc<Option<Region>>,
+    roi_tool: RoiTool,
     title: String,
+    viewport: Viewport,
 
     // dependencies
     image_service: Arc<ImageService>,
+    active_operation: Arc<Observable<Option<ActiveOperation>>>,
+    active_operation_rx: broadcast::Receiver<()>,
     model: Arc<model::Image>,
     model_rx: broadcast::Receiver<()>,
+    roi_model: Arc<model::Roi>,
+    roi_model_rx: broadcast::Receiver<()>,
+    task: Option<(Arc<Task>, broadcast::Receiver<()>)>,
+    link: Arc<ViewLink>,
+    link_viewport_rx: broadcast::Receiver<()>,
+    link_cursor_rx: broadcast::Receiver<()>,
+    link_highlight_rx: broadcast::Receiver<()>,
+    link_outlines_rx: broadcast::Receiver<()>,
 }
 
 impl ImageFrame {
+    /// If `task` is given, the frame shows the progress of background operations.
+    /// Frames sharing the same `link` follow each other's zoom, pan and cursor while linked.
     pub fn new(
         title: &str,
         accept_input: bool,
         image_service: Arc<ImageService>,
         model: Arc<model::Image>,
+        task: Option<Arc<Task>>,
+        link: Arc<ViewLink>,
     ) -> Self {
         let model_rx = model.get_property_changed_rx();
+        let image = model.get();
+        let task = task.map(|task| {
+            let task_rx = task.get_property_changed_rx();
+            (task, task_rx)
+        });
+        let link_viewport_rx = link.viewport.get_property_changed_rx();
+        let link_cursor_rx = link.cursor.get_property_changed_rx();
+        let link_highlight_rx = link.highlight.get_property_changed_rx();
+        let link_outlines_rx = link.outlines.get_property_changed_rx();
+        let active_operation = image_service.get_active_operation();
+        let active_operation_rx = active_operation.get_property_changed_rx();
+        let roi_model = image_service.get_roi();
+        let roi_model_rx = roi_model.get_property_changed_rx();
 
         Self {
             view_channel: broadcast::channel(32),
             title: title.to_string(),
-            image: Arc::new(None),
+            image,
             accept_input,
+            cursor: None,
+            display_mapping: DisplayMapping::default(),
+            highlight: link.highlight.get(),
+            linked: true,
+            loupe: false,
             open: true,
+            outlines: link.outlines.get(),
+            points: Vec::new(),
+            progress: None,
+            roi: roi_model.get(),
+            roi_tool: RoiTool::None,
+            viewport: *link.viewport.get(),
             image_service,
+            active_operation,
+            active_operation_rx,
             model,
             model_rx,
+            roi_model,
+            roi_model_rx,
+            task,
+            link,
+            link_viewport_rx,
+            link_cursor_rx,
+            link_highlight_rx,
+            link_outlines_rx,
         }
     }
 
     pub fn process_messages(&mut self) {
         if self.model_rx.try_recv().is_ok() {
-            self.set_image(self.model.get());
+            let image = self.model.get();
+            let size = dimensions(&image);
+            if size.is_some() && size != dimensions(&self.image) {
+                if self.linked && self.image.is_none() {
+                    // a frame that gets its first image joins the linked frames where they are
+                    self.update_viewport(*self.link.viewport.get());
+                } else {
+                    self.set_viewport(Viewport::fit());
+                }
+            }
+            self.set_image(image);
+        }
+
+        if changed(&mut self.link_viewport_rx) && self.linked {
+            let viewport = *self.link.viewport.get();
+            if viewport != self.viewport {
+                self.update_viewport(viewport);
+            }
+        }
+
+        if changed(&mut self.link_cursor_rx) && self.linked {
+            let cursor = *self.link.cursor.get();
+            if cursor != self.cursor {
+                self.cursor = cursor;
+                self.view_channel
+                    .0
+                    .send(PropertyChangedNotification::Cursor)
+                    .ok();
+            }
+        }
+
+        if changed(&mut self.link_highlight_rx) {
+            self.highlight = self.link.highlight.get();
+            self.view_channel
+                .0
+                .send(PropertyChangedNotification::Highlight)
+                .ok();
+        }
+
+        if changed(&mut self.link_outlines_rx) {
+            self.outlines = self.link.outlines.get();
+            self.view_channel
+                .0
+                .send(PropertyChangedNotification::Outlines)
+                .ok();
+        }
+
+        if changed(&mut self.roi_model_rx) {
+            self.roi = self.roi_model.get();
+            self.view_channel
+                .0
+                .send(PropertyChangedNotification::Roi)
+                .ok();
+        }
+
+        // only the frame that accepts input picks points, the others would show stale ones
+        if changed(&mut self.active_operation_rx) && self.accept_input {
+            let points = match &*self.active_operation.get() {
+                Some(active_operation) => active_operation
+                    .operation
+                    .parameters()
+                    .iter()
+                    .filter(|parameter| parameter.kind == ParameterKind::Point)
+                    .map(|parameter| {
+                        (
+                            parameter.name,
+                            active_operation.parameters.get_point(parameter.name),
+                        )
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            if points != self.points {
+                self.set_points(points);
+            }
+        }
+
+        let mut progress = None;
+        if let Some((task, task_rx)) = &mut self.task {
+            if task_rx.try_recv().is_ok() {
+                while task_rx.try_recv().is_ok() {}
+                progress = Some((*task.get()).clone());
+            }
+        }
+        if let Some(progress) = progress {
+            self.set_progress(progress);
         }
     }
 
@@ -67,22 +262,131 @@ impl ImageFrame {
         self.image_service.load_new_image(file);
     }
 
+    pub fn cancel_operation(&self) {
+        self.image_service.cancel_operation();
+    }
+
+    /// Tells the linked frames which image coordinate is under the mouse.
+    pub fn report_cursor(&self, cursor: Option<[f32; 2]>) {
+        if self.linked {
+            self.link.cursor.set(cursor);
+        }
+    }
+
+    /// Sets the first point parameter of the active operation that is still unset.
+    pub fn pick_point(&self, position: [f32; 2]) {
+        if let Some(index) = self.points.iter().position(|(_, point)| point.is_none()) {
+            self.move_point(index, position);
+        }
+    }
+
+    /// Sets the point parameter at `index` of `get_points`. Positions are kept on the image.
+    pub fn move_point(&self, index: usize, [x, y]: [f32; 2]) {
+        let (width, height) = match dimensions(&self.image) {
+            Some(size) => size,
+            None => return,
+        };
+        if let Some((name, _)) = self.points.get(index) {
+            let position = [x.clamp(0.0, width as f32), y.clamp(0.0, height as f32)];
+            self.image_service
+                .set_parameter(name, Value::Point(Some(position)));
+        }
+    }
+
+    /// Restricts operations to `region`, `None` removes the restriction.
+    pub fn set_roi(&self, region: Option<Region>) {
+        self.image_service.set_roi(region);
+    }
+
+    /// Changes how soft the edge of the region of interest is.
+    pub fn set_roi_feather(&self, feather: f32) {
+        if let Some(region) = &*self.roi {
+            self.image_service.set_roi(Some(Region {
+                feather: feather.max(0.0),
+                ..region.clone()
+            }));
+        }
+    }
+
+    pub fn crop_to_roi(&self) {
+        self.image_service.crop_to_roi();
+    }
+
     pub fn get_accept_input(&self) -> bool {
         self.accept_input
     }
 
+    /// The mouse position in image coordinates reported by any of the linked frames.
+    pub fn get_cursor(&self) -> Option<[f32; 2]> {
+        self.cursor
+    }
+
+    /// How the image is rendered. Does not change the image itself.
+    pub fn get_display_mapping(&self) -> DisplayMapping {
+        self.display_mapping
+    }
+
+    /// An area of the image to point out, shared by all frames whether linked or not.
+    pub fn get_highlight(&self) -> Arc<Option<Highlight>> {
+        Arc::clone(&self.highlight)
+    }
+
     pub fn get_image(&self) -> Arc<Option<DynamicImage>> {
         self.model.get()
     }
 
+    /// Raw values of the pixel at `x`, `y` of the original image (not of the displayed copy).
+    pub fn get_pixel_info(&self, x: u32, y: u32) -> Option<PixelInfo> {
+        (*self.image)
+            .as_ref()
+            .and_then(|image| pixel::pixel_info(image, x, y))
+    }
+
+    pub fn get_linked(&self) -> bool {
+        self.linked
+    }
+
+    pub fn get_loupe(&self) -> bool {
+        self.loupe
+    }
+
     pub fn get_open(&self) -> bool {
         self.open
     }
 
+    /// Shapes drawn over the image, shared by all frames whether linked or not.
+    pub fn get_outlines(&self) -> Arc<Vec<Outline>> {
+        Arc::clone(&self.outlines)
+    }
+
+    /// The point parameters of the active operation, in image coordinates. Only frames that
+    /// accept input have them.
+    pub fn get_points(&self) -> &Vec<(&'static str, Option<[f32; 2]>)> {
+        &self.points
+    }
+
+    /// `None` if no operation is running.
+    pub fn get_progress(&self) -> Option<TaskStatus> {
+        self.progress.clone()
+    }
+
+    /// The region of interest, shared by all frames.
+    pub fn get_roi(&self) -> Arc<Option<Region>> {
+        Arc::clone(&self.roi)
+    }
+
+    pub fn get_roi_tool(&self) -> RoiTool {
+        self.roi_tool
+    }
+
     pub fn get_title(&self) -> &String {
         &self.title
     }
 
+    pub fn get_viewport(&self) -> Viewport {
+        self.viewport
+    }
+
     #[allow(dead_code)]
     pub fn set_accept_input(&mut self, accept_input: bool) {
         self.accept_input = accept_input;
@@ -92,6 +396,14 @@ impl ImageFrame {
             .ok();
     }
 
+    pub fn set_display_mapping(&mut self, display_mapping: DisplayMapping) {
+        self.display_mapping = display_mapping;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::DisplayMapping)
+            .ok();
+    }
+
     #[allow(dead_code)]
     pub fn set_image(&mut self, image: Arc<Option<DynamicImage>>) {
         self.image = image;
@@ -101,6 +413,33 @@ impl ImageFrame {
             .ok();
     }
 
+    /// A frame that becomes linked moves the other linked frames to its own viewport.
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+        if linked {
+            self.link.viewport.set(self.viewport);
+        } else {
+            self.link.cursor.set(None);
+            self.cursor = None;
+            self.view_channel
+                .0
+                .send(PropertyChangedNotification::Cursor)
+                .ok();
+        }
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Linked)
+            .ok();
+    }
+
+    pub fn set_loupe(&mut self, loupe: bool) {
+        self.loupe = loupe;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Loupe)
+            .ok();
+    }
+
     #[allow(dead_code)]
     pub fn set_open(&mut self, open: bool) {
         self.open = open;
@@ -110,6 +449,30 @@ impl ImageFrame {
             .ok();
     }
 
+    fn set_points(&mut self, points: Vec<(&'static str, Option<[f32; 2]>)>) {
+        self.points = points;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Points)
+            .ok();
+    }
+
+    fn set_progress(&mut self, progress: Option<TaskStatus>) {
+        self.progress = progress;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Progress)
+            .ok();
+    }
+
+    pub fn set_roi_tool(&mut self, roi_tool: RoiTool) {
+        self.roi_tool = roi_tool;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::RoiTool)
+            .ok();
+    }
+
     #[allow(dead_code)]
     pub fn set_title(&mut self, title: String) {
         self.title = title;
@@ -118,4 +481,35 @@ impl ImageFrame {
             .send(PropertyChangedNotification::Title)
             .ok();
     }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.update_viewport(viewport);
+        if self.linked {
+            self.link.viewport.set(viewport);
+        }
+    }
+
+    /// Changes the viewport of this frame only.
+    fn update_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Viewport)
+            .ok();
+    }
+}
+
+/// Drains `rx` and returns whether anything was received. Lagging counts as a change.
+fn changed(rx: &mut broadcast::Receiver<()>) -> bool {
+    let mut changed = false;
+    loop {
+        match rx.try_recv() {
+            Ok(()) | Err(TryRecvError::Lagged(_)) => changed = true,
+            Err(_) => return changed,
+        }
+    }
+}
+
+fn dimensions(image: &Option<DynamicImage>) -> Option<(u32, u32)> {
+    image.as_ref().map(|image| (image.width(), image.height()))
 }
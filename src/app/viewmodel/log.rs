@@ -0,0 +1,63 @@
+use crate::app::model;
+use crate::app::model::log::LogEntries;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Entries,
+}
+
+pub struct Log {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    entries: Arc<LogEntries>,
+
+    // dependencies
+    log: Arc<model::Log>,
+    log_rx: broadcast::Receiver<()>,
+}
+
+impl Log {
+    pub fn new(log: Arc<model::Log>) -> Self {
+        let log_rx = log.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            entries: log.get(),
+            log,
+            log_rx,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.log_rx.try_recv().is_ok() {
+            while self.log_rx.try_recv().is_ok() {}
+            self.set_entries(self.log.get());
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    pub fn get_entries(&self) -> Arc<LogEntries> {
+        Arc::clone(&self.entries)
+    }
+
+    fn set_entries(&mut self, entries: Arc<LogEntries>) {
+        self.entries = entries;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Entries)
+            .ok();
+    }
+}
@@ -0,0 +1,164 @@
+use crate::app::model::observable::Observable;
+use crate::app::model::operation::ActiveOperation;
+use crate::app::model::parameter::{Parameter, Parameters, Value};
+use crate::app::model::task::Task;
+use crate::app::model::ImageService;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub enum PropertyChangedNotification {
+    Busy,
+    Notes,
+    Operation,
+    Values,
+}
+
+pub struct ParameterWindow {
+    view_channel: (
+        broadcast::Sender<PropertyChangedNotification>,
+        broadcast::Receiver<PropertyChangedNotification>,
+    ),
+
+    // properties
+    busy: bool,
+    notes: Vec<String>,
+    operation_name: Option<&'static str>,
+    parameters: Vec<Parameter>,
+    values: Parameters,
+
+    // dependencies
+    image_service: Arc<ImageService>,
+    active_operation: Arc<Observable<Option<ActiveOperation>>>,
+    active_operation_rx: broadcast::Receiver<()>,
+    task: Arc<Task>,
+    task_rx: broadcast::Receiver<()>,
+}
+
+impl ParameterWindow {
+    pub fn new(
+        image_service: Arc<ImageService>,
+        active_operation: Arc<Observable<Option<ActiveOperation>>>,
+        task: Arc<Task>,
+    ) -> Self {
+        let active_operation_rx = active_operation.get_property_changed_rx();
+        let task_rx = task.get_property_changed_rx();
+
+        Self {
+            view_channel: broadcast::channel(32),
+            busy: false,
+            notes: Vec::new(),
+            operation_name: None,
+            parameters: Vec::new(),
+            values: Parameters::default(),
+            image_service,
+            active_operation,
+            active_operation_rx,
+            task,
+            task_rx,
+        }
+    }
+
+    pub fn process_messages(&mut self) {
+        if self.active_operation_rx.try_recv().is_ok() {
+            while self.active_operation_rx.try_recv().is_ok() {}
+
+            match &*self.active_operation.get() {
+                Some(active_operation) => {
+                    let name = active_operation.operation.name();
+                    if self.operation_name != Some(name) {
+                        self.set_operation(Some(name), active_operation.operation.parameters());
+                    }
+                    self.set_values(active_operation.parameters.clone());
+                    if self.notes != active_operation.notes {
+                        self.set_notes(active_operation.notes.clone());
+                    }
+                }
+                None => {
+                    self.set_operation(None, Vec::new());
+                    self.set_values(Parameters::default());
+                    self.set_notes(Vec::new());
+                }
+            }
+        }
+
+        if self.task_rx.try_recv().is_ok() {
+            while self.task_rx.try_recv().is_ok() {}
+
+            let busy = self.task.get().is_some();
+            if busy != self.busy {
+                self.set_busy(busy);
+            }
+        }
+    }
+
+    pub fn get_receiver(&self) -> broadcast::Receiver<PropertyChangedNotification> {
+        self.view_channel.0.subscribe()
+    }
+
+    pub fn set_value(&mut self, name: &str, value: Value) {
+        self.image_service.set_parameter(name, value);
+    }
+
+    pub fn accept_operation(&mut self) {
+        self.image_service.accept_operation();
+    }
+
+    pub fn discard_operation(&mut self) {
+        self.image_service.discard_operation();
+    }
+
+    pub fn get_busy(&self) -> bool {
+        self.busy
+    }
+
+    /// What the operation reported about the current preview, e.g. a chosen threshold.
+    pub fn get_notes(&self) -> &Vec<String> {
+        &self.notes
+    }
+
+    pub fn get_operation_name(&self) -> Option<&'static str> {
+        self.operation_name
+    }
+
+    pub fn get_parameters(&self) -> &Vec<Parameter> {
+        &self.parameters
+    }
+
+    pub fn get_values(&self) -> &Parameters {
+        &self.values
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Busy)
+            .ok();
+    }
+
+    fn set_notes(&mut self, notes: Vec<String>) {
+        self.notes = notes;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Notes)
+            .ok();
+    }
+
+    fn set_operation(&mut self, operation_name: Option<&'static str>, parameters: Vec<Parameter>) {
+        self.operation_name = operation_name;
+        self.parameters = parameters;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Operation)
+            .ok();
+    }
+
+    fn set_values(&mut self, values: Parameters) {
+        self.values = values;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Values)
+            .ok();
+    }
+}
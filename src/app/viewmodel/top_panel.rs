@@ -1,4 +1,5 @@
 use crate::app::model;
+use crate::app::model::operation::Category;
 use crate::app::model::ImageService;
 use rfd::FileHandle;
 use std::sync::Arc;
@@ -6,8 +7,20 @@ use tokio::sync::broadcast;
 
 #[derive(Clone)]
 pub enum PropertyChangedNotification {
+    CanRedo,
+    CanUndo,
     HasCurrent,
     HasPreview,
+    Operations,
+}
+
+/// A menu entry for one registered operation.
+#[derive(Clone)]
+pub struct OperationItem {
+    pub name: &'static str,
+    pub category: Category,
+    pub enabled: bool,
+    pub signature: String,
 }
 
 pub struct TopPanel {
@@ -17,8 +30,11 @@ pub struct TopPanel {
     ),
 
     // properties
+    can_redo: bool,
+    can_undo: bool,
     has_current: bool,
     has_preview: bool,
+    operations: Vec<OperationItem>,
 
     // dependencies
     image_service: Arc<ImageService>,
@@ -37,16 +53,23 @@ impl TopPanel {
         let current_image_rx = current_image.get_property_changed_rx();
         let preview_image_rx = preview_image.get_property_changed_rx();
 
-        Self {
+        let mut result = Self {
             view_channel: broadcast::channel(32),
+            can_redo: false,
+            can_undo: false,
             has_current: false,
             has_preview: false,
+            operations: Vec::new(),
             image_service,
             current_image,
             preview_image,
             current_image_rx,
             preview_image_rx,
-        }
+        };
+
+        result.update_operations();
+
+        result
     }
 
     pub fn open_file(&mut self, file: Option<FileHandle>) {
@@ -56,6 +79,9 @@ impl TopPanel {
     pub fn process_messages(&mut self) {
         if self.current_image_rx.try_recv().is_ok() {
             self.set_has_current(self.current_image.get().is_some());
+            self.set_can_undo(self.image_service.can_undo());
+            self.set_can_redo(self.image_service.can_redo());
+            self.update_operations();
         }
 
         if self.preview_image_rx.try_recv().is_ok() {
@@ -67,12 +93,8 @@ impl TopPanel {
         self.view_channel.0.subscribe()
     }
 
-    pub fn apply_grayscale(&mut self) {
-        self.image_service.apply_grayscale();
-    }
-
-    pub fn apply_invert(&mut self) {
-        self.image_service.apply_invert();
+    pub fn apply_operation(&mut self, name: &str) {
+        self.image_service.apply_operation(name);
     }
 
     pub fn accept_operation(&mut self) {
@@ -87,6 +109,22 @@ impl TopPanel {
         self.image_service.reset(None);
     }
 
+    pub fn undo(&mut self) {
+        self.image_service.undo();
+    }
+
+    pub fn redo(&mut self) {
+        self.image_service.redo();
+    }
+
+    pub fn get_can_redo(&self) -> bool {
+        self.can_redo
+    }
+
+    pub fn get_can_undo(&self) -> bool {
+        self.can_undo
+    }
+
     pub fn get_has_current(&self) -> bool {
         self.has_current
     }
@@ -95,6 +133,49 @@ impl TopPanel {
         self.has_preview
     }
 
+    pub fn get_operations(&self) -> &Vec<OperationItem> {
+        &self.operations
+    }
+
+    fn update_operations(&mut self) {
+        let current_image = self.current_image.get();
+        let operations = self
+            .image_service
+            .get_operations()
+            .iter()
+            .map(|operation| OperationItem {
+                name: operation.name(),
+                category: operation.category(),
+                signature: format!(
+                    "{} -> {}",
+                    operation.input_type().name(),
+                    operation.output_type().name()
+                ),
+                enabled: match &*current_image {
+                    Some(image) => operation.input_type().accepts(image),
+                    None => false,
+                },
+            })
+            .collect();
+        self.set_operations(operations);
+    }
+
+    fn set_can_redo(&mut self, can_redo: bool) {
+        self.can_redo = can_redo;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::CanRedo)
+            .ok();
+    }
+
+    fn set_can_undo(&mut self, can_undo: bool) {
+        self.can_undo = can_undo;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::CanUndo)
+            .ok();
+    }
+
     fn set_has_current(&mut self, has_current: bool) {
         self.has_current = has_current;
         self.view_channel
@@ -110,4 +191,12 @@ impl TopPanel {
             .send(PropertyChangedNotification::HasPreview)
             .ok();
     }
+
+    fn set_operations(&mut self, operations: Vec<OperationItem>) {
+        self.operations = operations;
+        self.view_channel
+            .0
+            .send(PropertyChangedNotification::Operations)
+            .ok();
+    }
 }
@@ -0,0 +1,324 @@
+//! Runs operations in web workers. Without shared memory the browser offers wasm no threads, so
+//! the main thread posts the operation name, its parameters and the raw image to a worker that
+//! runs the same module, and gets the raw result back.
+//!
+//! Workers are started by `spawnOperationWorker` in `www/index.js`, where the bundler can resolve
+//! `www/worker.js`. That script calls `run_operation` for every request it receives. Starting a
+//! worker loads the module again, so a worker that finished is kept for the next operation. A
+//! worker whose operation is cancelled is terminated right away, since it cannot be interrupted.
+
+use crate::app::model::operation::Operation;
+use crate::app::model::operations;
+use crate::app::model::parameter::{Matrix, Parameters, Value};
+use crate::app::model::task::Progress;
+use eframe::wasm_bindgen::{self, prelude::*, JsCast};
+use image::{DynamicImage, ImageBuffer};
+use js_sys::{Array, Float32Array, Object, Reflect, Uint8Array};
+use std::cell::RefCell;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// How often a running operation is checked for cancellation.
+const CANCEL_CHECK_MS: i32 = 50;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = spawnOperationWorker)]
+    fn spawn_operation_worker() -> web_sys::Worker;
+
+    /// The worker's own `postMessage`, which sends to the main thread.
+    #[wasm_bindgen(js_name = postMessage)]
+    fn post_to_main_thread(message: &JsValue);
+}
+
+thread_local! {
+    /// A worker that has loaded the module and waits for the next operation.
+    static IDLE_WORKER: RefCell<Option<web_sys::Worker>> = RefCell::new(None);
+}
+
+/// What the main thread hears while an operation runs.
+enum Event {
+    Message(JsValue),
+    Error(String),
+    /// Time to check whether the operation was cancelled.
+    Tick,
+}
+
+/// Applies `operation` in a worker and hands the result to `done` on the main thread.
+/// Progress reports arrive through `progress`. Once `progress` is cancelled the worker is
+/// terminated within `CANCEL_CHECK_MS` and `done` is not called.
+pub fn apply(
+    operation: &dyn Operation,
+    parameters: &Parameters,
+    image: &DynamicImage,
+    progress: Progress,
+    done: impl FnOnce(Option<DynamicImage>, &Progress) + 'static,
+) {
+    let worker = IDLE_WORKER
+        .with(|idle| idle.borrow_mut().take())
+        .unwrap_or_else(spawn_operation_worker);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let message_tx = tx.clone();
+    let on_message =
+        Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+            message_tx.send(Event::Message(event.data())).ok();
+        });
+    let error_tx = tx.clone();
+    let on_error =
+        Closure::<dyn FnMut(web_sys::ErrorEvent)>::new(move |event: web_sys::ErrorEvent| {
+            error_tx.send(Event::Error(event.message())).ok();
+        });
+    let on_tick = Closure::<dyn FnMut()>::new(move || {
+        tx.send(Event::Tick).ok();
+    });
+    worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    let request = Object::new();
+    set(&request, "operation", &operation.name().into());
+    set(
+        &request,
+        "parameters",
+        &parameters_to_js(operation, parameters),
+    );
+    let (image, buffer) = image_to_js(image);
+    set(&request, "image", &image);
+    if let Err(error) = worker.post_message_with_transfer(&request, &Array::of1(&buffer)) {
+        worker.terminate();
+        progress.note(format!("could not start the worker: {:?}", error));
+        done(None, &progress);
+        return;
+    }
+    let window = web_sys::window().expect("operations run on the main thread");
+    let interval = window
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            on_tick.as_ref().unchecked_ref(),
+            CANCEL_CHECK_MS,
+        )
+        .ok();
+
+    wasm_bindgen_futures::spawn_local(async move {
+        // `None` if the worker has to go, because it failed or its operation was cancelled
+        let mut outcome = None;
+        while let Some(event) = rx.recv().await {
+            match event {
+                Event::Tick => {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                }
+                Event::Message(data) => {
+                    if let Some(fraction) = get(&data, "progress").as_f64() {
+                        progress.set(fraction as f32);
+                        continue;
+                    }
+                    if let Ok(notes) = get(&data, "notes").dyn_into::<Array>() {
+                        for note in notes.iter().filter_map(|note| note.as_string()) {
+                            progress.note(note);
+                        }
+                    }
+                    outcome = Some(image_from_js(&get(&data, "result")));
+                    break;
+                }
+                Event::Error(error) => {
+                    progress.note(format!("the worker failed: {}", error));
+                    break;
+                }
+            }
+        }
+
+        if let Some(interval) = interval {
+            window.clear_interval_with_handle(interval);
+        }
+        worker.set_onmessage(None);
+        worker.set_onerror(None);
+        // the handlers must outlive every event, which end with the operation
+        drop((on_message, on_error, on_tick));
+
+        match outcome {
+            Some(result) => {
+                IDLE_WORKER.with(|idle| {
+                    if let Some(spare) = idle.borrow_mut().replace(worker) {
+                        spare.terminate();
+                    }
+                });
+                done(result, &progress);
+            }
+            None => {
+                worker.terminate();
+                if progress.is_cancelled() {
+                    // the next operation usually follows right away, have a worker ready for it
+                    IDLE_WORKER.with(|idle| {
+                        idle.borrow_mut().get_or_insert_with(spawn_operation_worker);
+                    });
+                } else {
+                    done(None, &progress);
+                }
+            }
+        }
+    });
+}
+
+/// Entry point of the worker. Returns the notes of the operation and, unless it failed, the
+/// result image. Progress is posted to the main thread while the operation runs.
+#[wasm_bindgen]
+pub fn run_operation(request: JsValue) -> Result<JsValue, JsValue> {
+    let name = get(&request, "operation")
+        .as_string()
+        .ok_or("the request names no operation")?;
+    let operation = operations::registry()
+        .get(&name)
+        .ok_or_else(|| format!("unknown operation {}", name))?;
+    let parameters = parameters_from_js(operation.as_ref(), &get(&request, "parameters"));
+    let image = image_from_js(&get(&request, "image")).ok_or("the request has no image")?;
+
+    // the main thread terminates the worker to cancel, so the operation never sees it
+    let progress = Progress::new(Arc::new(AtomicBool::new(false)), |fraction| {
+        let report = Object::new();
+        set(&report, "progress", &fraction.into());
+        post_to_main_thread(&report);
+    });
+    let result = operation.apply(&image, &parameters, &progress);
+
+    let response = Object::new();
+    let notes: Array = progress
+        .take_notes()
+        .into_iter()
+        .map(JsValue::from)
+        .collect();
+    set(&response, "notes", &notes);
+    if let Some(result) = result {
+        set(&response, "result", &image_to_js(&result).0);
+    }
+    Ok(response.into())
+}
+
+fn get(object: &JsValue, key: &str) -> JsValue {
+    Reflect::get(object, &key.into()).unwrap_or(JsValue::UNDEFINED)
+}
+
+fn set(object: &Object, key: &str, value: &JsValue) {
+    Reflect::set(object, &key.into(), value).ok();
+}
+
+/// Values are stored by parameter name as arrays that start with the kind of the value.
+fn parameters_to_js(operation: &dyn Operation, parameters: &Parameters) -> Object {
+    let object = Object::new();
+    for parameter in operation.parameters() {
+        let (kind, values): (&str, Vec<JsValue>) = match parameters.get(parameter.name) {
+            Some(Value::Bool(value)) => ("bool", vec![value.into()]),
+            Some(Value::Int(value)) => ("int", vec![value.into()]),
+            Some(Value::Float(value)) => ("float", vec![value.into()]),
+            Some(Value::Choice(value)) => ("choice", vec![(value as u32).into()]),
+            Some(Value::KernelSize(value)) => ("kernel size", vec![value.into()]),
+            Some(Value::Matrix(matrix)) => (
+                "matrix",
+                vec![
+                    matrix.width.into(),
+                    matrix.height.into(),
+                    Float32Array::from(&matrix.values[..]).into(),
+                ],
+            ),
+            Some(Value::Point(Some([x, y]))) => ("point", vec![x.into(), y.into()]),
+            Some(Value::Point(None)) => ("point", Vec::new()),
+            None => continue,
+        };
+        let value: Array = std::iter::once(JsValue::from(kind)).chain(values).collect();
+        set(&object, parameter.name, &value);
+    }
+    object
+}
+
+/// Parameters missing from `object` keep their defaults.
+fn parameters_from_js(operation: &dyn Operation, object: &JsValue) -> Parameters {
+    let definitions = operation.parameters();
+    let mut parameters = Parameters::from_defaults(&definitions);
+    for parameter in definitions {
+        let entry = match get(object, parameter.name).dyn_into::<Array>() {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let number = |index| entry.get(index).as_f64();
+        let value = match entry.get(0).as_string().as_deref() {
+            Some("bool") => entry.get(1).as_bool().map(Value::Bool),
+            Some("int") => number(1).map(|value| Value::Int(value as i32)),
+            Some("float") => number(1).map(|value| Value::Float(value as f32)),
+            Some("choice") => number(1).map(|value| Value::Choice(value as usize)),
+            Some("kernel size") => number(1).map(|value| Value::KernelSize(value as u32)),
+            Some("matrix") => match (number(1), number(2)) {
+                (Some(width), Some(height)) => Some(Value::Matrix(Matrix {
+                    width: width as u32,
+                    height: height as u32,
+                    values: Float32Array::from(entry.get(3)).to_vec(),
+                })),
+                _ => None,
+            },
+            Some("point") => Some(Value::Point(
+                number(1).zip(number(2)).map(|(x, y)| [x as f32, y as f32]),
+            )),
+            _ => None,
+        };
+        if let Some(value) = value {
+            parameters.set(parameter.name, value);
+        }
+    }
+    parameters
+}
+
+/// The image with its raw, native endian pixel data, and the buffer holding that data so it can
+/// be transferred instead of copied.
+fn image_to_js(image: &DynamicImage) -> (Object, JsValue) {
+    let color = match image {
+        DynamicImage::ImageLuma8(_) => "L8",
+        DynamicImage::ImageLumaA8(_) => "La8",
+        DynamicImage::ImageRgb8(_) => "Rgb8",
+        DynamicImage::ImageRgba8(_) => "Rgba8",
+        DynamicImage::ImageLuma16(_) => "L16",
+        DynamicImage::ImageLumaA16(_) => "La16",
+        DynamicImage::ImageRgb16(_) => "Rgb16",
+        DynamicImage::ImageRgba16(_) => "Rgba16",
+        DynamicImage::ImageRgb32F(_) => "Rgb32F",
+        DynamicImage::ImageRgba32F(_) => "Rgba32F",
+        // not produced by the decoders or operations, fall back to the closest layout
+        _ => return image_to_js(&DynamicImage::ImageRgba32F(image.to_rgba32f())),
+    };
+    let data = Uint8Array::from(image.as_bytes());
+    let object = Object::new();
+    set(&object, "width", &image.width().into());
+    set(&object, "height", &image.height().into());
+    set(&object, "color", &color.into());
+    set(&object, "data", &data);
+    (object, data.buffer().into())
+}
+
+fn image_from_js(object: &JsValue) -> Option<DynamicImage> {
+    let width = get(object, "width").as_f64()? as u32;
+    let height = get(object, "height").as_f64()? as u32;
+    let data = get(object, "data").dyn_into::<Uint8Array>().ok()?.to_vec();
+    match get(object, "color").as_string()?.as_str() {
+        "L8" => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+        "La8" => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8),
+        "Rgb8" => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+        "Rgba8" => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8),
+        "L16" => ImageBuffer::from_raw(width, height, words(&data, u16::from_ne_bytes))
+            .map(DynamicImage::ImageLuma16),
+        "La16" => ImageBuffer::from_raw(width, height, words(&data, u16::from_ne_bytes))
+            .map(DynamicImage::ImageLumaA16),
+        "Rgb16" => ImageBuffer::from_raw(width, height, words(&data, u16::from_ne_bytes))
+            .map(DynamicImage::ImageRgb16),
+        "Rgba16" => ImageBuffer::from_raw(width, height, words(&data, u16::from_ne_bytes))
+            .map(DynamicImage::ImageRgba16),
+        "Rgb32F" => ImageBuffer::from_raw(width, height, words(&data, f32::from_ne_bytes))
+            .map(DynamicImage::ImageRgb32F),
+        "Rgba32F" => ImageBuffer::from_raw(width, height, words(&data, f32::from_ne_bytes))
+            .map(DynamicImage::ImageRgba32F),
+        _ => None,
+    }
+}
+
+fn words<T, const N: usize>(data: &[u8], from_bytes: fn([u8; N]) -> T) -> Vec<T> {
+    data.chunks_exact(N)
+        .map(|chunk| from_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
@@ -1,5 +1,9 @@
 console.debug("loading wasm…");
 
+// The app runs operations in workers. They are started from here, where the bundler can resolve
+// the worker script.
+window.spawnOperationWorker = () => new Worker(new URL('./worker.js', import.meta.url));
+
 import('./pkg')
   .then(on_wasm_loaded)
   .catch(on_wasm_error);
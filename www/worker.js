@@ -0,0 +1,10 @@
+// Runs operations for the app, see `src/app/worker.rs`. Requests can arrive before the module is
+// loaded, so the handler waits for it instead of being installed once it is there.
+const wasm = import('./pkg');
+
+self.onmessage = async (event) => {
+    const { run_operation } = await wasm;
+    const response = run_operation(event.data);
+    const transfer = response.result ? [response.result.data.buffer] : [];
+    self.postMessage(response, transfer);
+};